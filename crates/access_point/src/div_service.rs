@@ -1,10 +1,12 @@
 use super::{
     div_types::{compute_sha256_base64, DivEnvelope},
-    AccessPointClient, DeliveryState, DeliveryStatus,
+    error::HttpError,
+    AccessPointClient, DeliveryState, DeliveryStatus, RateLimit,
 };
 use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use lat_einv_core::parsing::parse_ubl_invoice;
+use lat_einv_core::xmldsig::{self, SigningKey};
 use serde::Deserialize;
 use std::sync::Arc;
 use std::time::Duration;
@@ -102,6 +104,8 @@ pub struct DivServiceClient {
     pub cert_thumbprint: String,
     /// Sender's e-adrese identifier
     pub sender_eaddress: String,
+    /// Key that signs invoices and envelopes; unsigned when `None`
+    signing_key: Option<Arc<SigningKey>>,
     /// HTTP client configured for SOAP requests
     http_client: reqwest::Client,
 }
@@ -123,6 +127,17 @@ impl DivServiceClient {
     /// );
     /// ```
     pub fn new(base_url: String, cert_thumbprint: String, sender_eaddress: String) -> Arc<Self> {
+        Self::new_with_signing_key(base_url, cert_thumbprint, sender_eaddress, None)
+    }
+
+    /// Create a client that signs each invoice and the `SenderSection` of its
+    /// DIV Envelope with `signing_key`
+    pub fn new_with_signing_key(
+        base_url: String,
+        cert_thumbprint: String,
+        sender_eaddress: String,
+        signing_key: Option<SigningKey>,
+    ) -> Arc<Self> {
         // Build HTTP client with longer timeout for SOAP requests
         let http_client = reqwest::Client::builder()
             .timeout(Duration::from_secs(60))
@@ -134,6 +149,7 @@ impl DivServiceClient {
             base_url,
             cert_thumbprint,
             sender_eaddress,
+            signing_key: signing_key.map(Arc::new),
             http_client,
         })
     }
@@ -239,10 +255,11 @@ impl DivServiceClient {
     fn map_status(div_status: &MessageStatus) -> DeliveryState {
         match div_status {
             MessageStatus::New | MessageStatus::Sent | MessageStatus::DeliveryDelayed => {
-                DeliveryState::InFlight
+                DeliveryState::Sent
             }
             MessageStatus::Accepted | MessageStatus::RecipientAccepted => DeliveryState::Delivered,
-            MessageStatus::Rejected | MessageStatus::RecipientRejected => DeliveryState::Failed,
+            MessageStatus::Rejected => DeliveryState::Failed,
+            MessageStatus::RecipientRejected => DeliveryState::Rejected,
         }
     }
 }
@@ -274,14 +291,22 @@ impl AccessPointClient for DivServiceClient {
             "E-Invoice Sender".to_string()
         };
 
+        let signed_invoice = self.prepare(xml)?;
+        let xml = signed_invoice.as_str();
+
         // Build DIV Envelope using structured types
         let div_envelope = self.build_div_envelope(xml, receiver, &sender_org_name)?;
         
         // Get SenderRefNumber from the envelope for tracking
         let invoice_id = div_envelope.sender_document.sender_transport_metadata.sender_ref_number.clone();
 
-        // Serialize DIV Envelope to XML
-        let div_envelope_xml = div_envelope.to_xml();
+        // Serialize DIV Envelope to XML, signing the SenderSection if a key is loaded
+        let div_envelope_xml = match &self.signing_key {
+            Some(key) => div_envelope
+                .to_signed_xml(key)
+                .context("Failed to sign DIV Envelope")?,
+            None => div_envelope.to_xml(),
+        };
 
         // Build SOAP envelope
         let soap_body = self.build_soap_envelope(&div_envelope_xml);
@@ -306,9 +331,7 @@ impl AccessPointClient for DivServiceClient {
             .context("Failed to send SOAP request to DIV UnifiedService")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            bail!("DIV UnifiedService submit failed: {} - {}", status, body);
+            bail!(HttpError::from_response("DIV UnifiedService submit", response).await);
         }
 
         let response_body = response.text().await
@@ -324,6 +347,16 @@ impl AccessPointClient for DivServiceClient {
         Ok(invoice_id)
     }
 
+    /// Sign the invoice itself unless it already carries a signature
+    fn prepare(&self, xml: &str) -> Result<String> {
+        match &self.signing_key {
+            Some(key) if !xml.contains(xmldsig::DSIG_NS) => {
+                xmldsig::sign_invoice(xml, key).context("Failed to sign invoice")
+            }
+            _ => Ok(xml.to_string()),
+        }
+    }
+
     /// Query the delivery status of an e-invoice
     ///
     /// DIV UnifiedService provides status tracking via the GetNotificationList operation.
@@ -356,9 +389,7 @@ impl AccessPointClient for DivServiceClient {
             .context("Failed to query DIV UnifiedService notifications")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            bail!("DIV notification query failed: {} - {}", status, body);
+            bail!(HttpError::from_response("DIV notification query", response).await);
         }
 
         let response_body = response
@@ -375,12 +406,21 @@ impl AccessPointClient for DivServiceClient {
             "Polled DIV UnifiedService notifications"
         );
 
-        // If we can't find the message, assume it's still in flight
+        // If we can't find the message, assume it's still on its way
         // TODO: Properly parse SOAP response and find matching notification
         Ok(DeliveryStatus {
             transmission_id: message_id.to_string(),
-            state: DeliveryState::InFlight,
+            state: DeliveryState::Sent,
             message: Some("Notification parsing not yet fully implemented".to_string()),
         })
     }
+
+    /// Each check fetches the whole notification list, so checks are spaced out
+    fn status_poll_interval(&self) -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+
+    fn submit_rate_limit(&self) -> RateLimit {
+        RateLimit::new(1.0, 5)
+    }
 }
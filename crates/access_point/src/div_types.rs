@@ -3,6 +3,8 @@
 //! These types represent the DIV Envelope structure as defined in the XSD schemas.
 //! This is a manual implementation based on the WSDL document.
 
+use anyhow::Result;
+use lat_einv_core::xmldsig::{sign_div_envelope, SigningKey};
 use std::fmt;
 
 /// DIV Envelope - the top-level structure for DIV messages
@@ -276,6 +278,11 @@ impl DivEnvelope {
             self.sender_document.sender_transport_metadata.priority,
         )
     }
+
+    /// Serialize to XML with an XMLDSig/XAdES signature over the SenderSection
+    pub fn to_signed_xml(&self, key: &SigningKey) -> Result<String> {
+        sign_div_envelope(&self.to_xml(), key)
+    }
 }
 
 impl fmt::Display for DivEnvelope {
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// Unsuccessful HTTP response from an access point
+#[derive(Debug, thiserror::Error)]
+#[error("{operation} failed: {status} - {body}")]
+pub struct HttpError {
+    pub operation: String,
+    pub status: StatusCode,
+    /// Wait requested by the server with `Retry-After`
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+impl HttpError {
+    /// Error for `response`, reading its body
+    pub async fn from_response(operation: &str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await.unwrap_or_default();
+        Self {
+            operation: operation.to_string(),
+            status,
+            retry_after,
+            body,
+        }
+    }
+}
+
+/// `Retry-After` header value, given in seconds or as an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc);
+    Some((at - Utc::now()).to_std().unwrap_or_default())
+}
+
+/// A failure that may go away when the request is repeated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Transient {
+    pub retry_after: Option<Duration>,
+    /// The access point asked for fewer requests (HTTP 429)
+    pub throttled: bool,
+}
+
+/// Whether `err` was caused by a timeout, a connection failure or a 408, 429
+/// or 5xx response
+pub fn transient(err: &anyhow::Error) -> Option<Transient> {
+    for cause in err.chain() {
+        if let Some(http) = cause.downcast_ref::<HttpError>() {
+            let retryable = http.status.is_server_error()
+                || http.status == StatusCode::TOO_MANY_REQUESTS
+                || http.status == StatusCode::REQUEST_TIMEOUT;
+            return retryable.then_some(Transient {
+                retry_after: http.retry_after,
+                throttled: http.status == StatusCode::TOO_MANY_REQUESTS,
+            });
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() || e.is_connect() {
+                return Some(Transient::default());
+            }
+        }
+    }
+    None
+}
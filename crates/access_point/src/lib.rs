@@ -1,14 +1,9 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum DeliveryState {
-    Pending,
-    InFlight,
-    Delivered,
-    Failed,
-}
+pub use lat_einv_core::models::DeliveryState;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeliveryStatus {
@@ -17,6 +12,23 @@ pub struct DeliveryStatus {
     pub message: Option<String>,
 }
 
+/// Submissions an access point accepts: `per_second` on average, with bursts
+/// of up to `burst`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        Self {
+            per_second,
+            burst: burst.max(1),
+        }
+    }
+}
+
 #[async_trait]
 pub trait AccessPointClient: Send + Sync {
     async fn submit(
@@ -26,9 +38,40 @@ pub trait AccessPointClient: Send + Sync {
         receiver: &str,
         profile: &str,
     ) -> Result<String>;
+
+    /// Submit with a key that stays the same across attempts to send the same
+    /// job, so that access points supporting one can drop repeated submissions
+    async fn submit_with_key(
+        &self,
+        xml: &str,
+        sender: &str,
+        receiver: &str,
+        profile: &str,
+        _idempotency_key: &str,
+    ) -> Result<String> {
+        self.submit(xml, sender, receiver, profile).await
+    }
+
+    /// The invoice as it is transmitted, e.g. signed. Submitting it in place
+    /// of `xml` sends the same document.
+    fn prepare(&self, xml: &str) -> Result<String> {
+        Ok(xml.to_string())
+    }
+
     async fn status(&self, transmission_id: &str) -> Result<DeliveryStatus>;
+
+    /// How often the delivery status of a sent invoice is worth asking for
+    fn status_poll_interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    /// How fast invoices may be submitted
+    fn submit_rate_limit(&self) -> RateLimit {
+        RateLimit::new(5.0, 10)
+    }
 }
 
+pub mod error;
 pub mod mock;
 pub mod unifiedpost;
 pub mod div_service;
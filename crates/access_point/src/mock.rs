@@ -40,4 +40,8 @@ impl AccessPointClient for MockClient {
             message: Some("Mock delivered".to_string()),
         })
     }
+
+    fn status_poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
 }
@@ -1,4 +1,4 @@
-use super::{AccessPointClient, DeliveryState, DeliveryStatus};
+use super::{error::HttpError, AccessPointClient, DeliveryState, DeliveryStatus};
 use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -103,9 +103,7 @@ impl UnifiedpostClient {
                     .context("Failed to request OAuth2 token")?;
 
                 if !resp.status().is_success() {
-                    let status = resp.status();
-                    let body = resp.text().await.unwrap_or_default();
-                    bail!("OAuth2 token request failed: {} - {}", status, body);
+                    bail!(HttpError::from_response("OAuth2 token request", resp).await);
                 }
 
                 let token_resp: OAuth2TokenResponse = resp
@@ -123,16 +121,15 @@ impl UnifiedpostClient {
             }
         }
     }
-}
 
-#[async_trait]
-impl AccessPointClient for UnifiedpostClient {
-    async fn submit(
+    /// Send an invoice, with an `Idempotency-Key` header if a key is given
+    async fn send(
         &self,
         xml: &str,
         sender: &str,
         receiver: &str,
         profile: &str,
+        idempotency_key: Option<&str>,
     ) -> Result<String> {
         let auth_header = self.get_auth_header().await?;
         let submit_url = format!("{}/api/v1/peppol/send", self.base_url);
@@ -144,20 +141,22 @@ impl AccessPointClient for UnifiedpostClient {
             document_type: profile.to_string(),
         };
 
-        let resp = self
+        let mut request = self
             .http_client
             .post(&submit_url)
             .header("Authorization", auth_header)
-            .header("Content-Type", "application/json")
+            .header("Content-Type", "application/json");
+        if let Some(key) = idempotency_key {
+            request = request.header("Idempotency-Key", key);
+        }
+        let resp = request
             .json(&payload)
             .send()
             .await
             .context("Failed to send invoice to Unifiedpost")?;
 
         if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!("Unifiedpost submit failed: {} - {}", status, body);
+            bail!(HttpError::from_response("Unifiedpost submit", resp).await);
         }
 
         let submit_resp: SubmitResponse = resp
@@ -172,6 +171,31 @@ impl AccessPointClient for UnifiedpostClient {
 
         Ok(submit_resp.transmission_id)
     }
+}
+
+#[async_trait]
+impl AccessPointClient for UnifiedpostClient {
+    async fn submit(
+        &self,
+        xml: &str,
+        sender: &str,
+        receiver: &str,
+        profile: &str,
+    ) -> Result<String> {
+        self.send(xml, sender, receiver, profile, None).await
+    }
+
+    async fn submit_with_key(
+        &self,
+        xml: &str,
+        sender: &str,
+        receiver: &str,
+        profile: &str,
+        idempotency_key: &str,
+    ) -> Result<String> {
+        self.send(xml, sender, receiver, profile, Some(idempotency_key))
+            .await
+    }
 
     async fn status(&self, transmission_id: &str) -> Result<DeliveryStatus> {
         let auth_header = self.get_auth_header().await?;
@@ -186,13 +210,7 @@ impl AccessPointClient for UnifiedpostClient {
             .context("Failed to query status from Unifiedpost")?;
 
         if !resp.status().is_success() {
-            let status_code = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            bail!(
-                "Unifiedpost status query failed: {} - {}",
-                status_code,
-                body
-            );
+            bail!(HttpError::from_response("Unifiedpost status query", resp).await);
         }
 
         let status_resp: StatusResponse = resp
@@ -202,9 +220,9 @@ impl AccessPointClient for UnifiedpostClient {
 
         let state = match status_resp.state.to_lowercase().as_str() {
             "delivered" | "accepted" => DeliveryState::Delivered,
-            "failed" | "rejected" => DeliveryState::Failed,
-            "in_transit" | "sending" => DeliveryState::InFlight,
-            _ => DeliveryState::Pending,
+            "failed" => DeliveryState::Failed,
+            "rejected" => DeliveryState::Rejected,
+            _ => DeliveryState::Sent,
         };
 
         Ok(DeliveryStatus {
@@ -1,8 +1,14 @@
 use anyhow::{Context, Result};
+use lat_einv_core::validation::{CustomRuleSpec, Language, Severity, ValidationProfile};
+use lat_einv_core::xmldsig::SigningKey;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
 
 const APP_NAME: &str = "lv-einvoice-app";
 const KEYCHAIN_SERVICE: &str = "lv.einvoice.credentials";
+/// Keychain entry holding the password of `certificate.pkcs12_path`
+pub const CERTIFICATE_PASSWORD_SECRET: &str = "certificate_password";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -12,6 +18,29 @@ pub struct AppConfig {
     pub certificate: CertificateConfig,
     #[serde(default)]
     pub sender: SenderConfig,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// Language of validation messages
+    #[serde(default)]
+    pub language: Language,
+}
+
+impl AppConfig {
+    /// The active validation profile plus the sender's national rule pack
+    pub fn validation_profile(&self) -> Result<ValidationProfile> {
+        let profile = self.validation.active_profile();
+        match self
+            .sender
+            .rule_pack
+            .as_deref()
+            .filter(|p| !p.trim().is_empty())
+        {
+            Some(pack) => profile.with_rule_pack(pack),
+            None => Ok(profile),
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -23,11 +52,15 @@ impl Default for AppConfig {
                 client_id: None,
                 token_url: None,
             },
-            certificate: CertificateConfig { thumbprint: None },
+            certificate: CertificateConfig::default(),
             sender: SenderConfig {
                 from_title: None,
                 from_eadrese: None,
+                rule_pack: None,
             },
+            validation: ValidationConfig::default(),
+            queue: QueueConfig::default(),
+            language: Language::default(),
         }
     }
 }
@@ -43,19 +76,158 @@ pub struct ProviderConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CertificateConfig {
-    pub thumbprint: Option<String>, // Certificate thumbprint for signing
+    pub thumbprint: Option<String>,  // Certificate thumbprint for signing
+    pub pkcs12_path: Option<String>, // PKCS#12 (.p12/.pfx) file with the signing key
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SenderConfig {
     pub from_title: Option<String>,   // Display name/title for sender
     pub from_eadrese: Option<String>, // Sender e-adrese identifier
+    #[serde(default)]
+    pub rule_pack: Option<String>, // National validation rules, e.g. "lv"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    pub codelist_dir: Option<String>, // Directory with updated code list files
+    #[serde(default = "default_validation_profile")]
+    pub profile: String, // "peppol-bis3" | "lv-b2g" | "internal-strict" | name in `profiles`
+    #[serde(default)]
+    pub disabled_rules: Vec<String>,
+    #[serde(default)]
+    pub severity_overrides: BTreeMap<String, Severity>,
+    #[serde(default)]
+    pub profiles: Vec<ValidationProfile>, // Additional user-defined profiles
+    pub custom_rules_path: Option<String>, // YAML file with declarative rules
+    pub trust_anchor_dir: Option<String>,  // CA certificates trusted to sign incoming invoices
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            codelist_dir: None,
+            profile: default_validation_profile(),
+            disabled_rules: Vec::new(),
+            severity_overrides: BTreeMap::new(),
+            profiles: Vec::new(),
+            custom_rules_path: None,
+            trust_anchor_dir: None,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// The selected profile with the configured rule adjustments applied
+    pub fn active_profile(&self) -> ValidationProfile {
+        let mut profile = self
+            .profiles
+            .iter()
+            .find(|p| p.name == self.profile)
+            .cloned()
+            .or_else(|| ValidationProfile::builtin(&self.profile))
+            .unwrap_or_default();
+        profile
+            .disabled_rules
+            .extend(self.disabled_rules.iter().cloned());
+        profile.severity_overrides.extend(
+            self.severity_overrides
+                .iter()
+                .map(|(id, severity)| (id.clone(), *severity)),
+        );
+        profile
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueConfig {
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32, // Submission attempts per invoice, including the first
+    #[serde(default = "default_initial_retry_delay")]
+    pub initial_retry_delay_secs: u64, // Wait after the first transient failure, doubled after each further one
+    #[serde(default = "default_max_retry_delay")]
+    pub max_retry_delay_secs: u64,
+    pub status_poll_interval_secs: Option<u64>, // Overrides the provider's delivery status poll interval
+    #[serde(default = "default_delivery_deadline")]
+    pub delivery_deadline_hours: u64, // Sent invoices without a final status by then fail
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize, // Submissions running at the same time
+    pub submissions_per_second: Option<f64>,    // Overrides the provider's rate limit
+    pub submission_burst: Option<u32>, // Submissions allowed at once before the rate limit applies
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_retry_delay_secs: default_initial_retry_delay(),
+            max_retry_delay_secs: default_max_retry_delay(),
+            status_poll_interval_secs: None,
+            delivery_deadline_hours: default_delivery_deadline(),
+            concurrency: default_concurrency(),
+            submissions_per_second: None,
+            submission_burst: None,
+        }
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_initial_retry_delay() -> u64 {
+    30
+}
+
+fn default_max_retry_delay() -> u64 {
+    30 * 60
+}
+
+fn default_delivery_deadline() -> u64 {
+    72
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_validation_profile() -> String {
+    lat_einv_core::validation::PEPPOL_BIS3.to_string()
 }
 
 fn default_provider_kind() -> String {
     "mock".to_string()
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct CustomRulesFile {
+    // Conditions are written as plain maps (`regex: ...`) rather than YAML tags
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    rules: Vec<CustomRuleSpec>,
+}
+
+/// Load declarative validation rules from a YAML file with a top-level `rules` list
+pub fn load_custom_rules(path: &Path) -> Result<Vec<CustomRuleSpec>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read custom rules {}", path.display()))?;
+    let file: CustomRulesFile = serde_yaml::from_str(&text)
+        .with_context(|| format!("Failed to parse custom rules {}", path.display()))?;
+    Ok(file.rules)
+}
+
+/// Signing key from `certificate.pkcs12_path`, `None` if no file is configured.
+/// The password comes from `EINV_CERT_PASSWORD` or the keychain.
+pub fn load_signing_key(cfg: &CertificateConfig) -> Result<Option<SigningKey>> {
+    let path = match cfg.pkcs12_path.as_deref() {
+        Some(path) if !path.is_empty() => Path::new(path),
+        _ => return Ok(None),
+    };
+    let password = std::env::var("EINV_CERT_PASSWORD")
+        .or_else(|_| get_secret(CERTIFICATE_PASSWORD_SECRET))
+        .unwrap_or_default();
+    SigningKey::load_pkcs12(path, &password).map(Some)
+}
+
 pub fn load() -> Result<AppConfig> {
     let cfg: AppConfig = confy::load(APP_NAME, None).context("Failed to load app config")?;
     Ok(cfg)
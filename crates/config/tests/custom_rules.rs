@@ -0,0 +1,48 @@
+use config::load_custom_rules;
+use lat_einv_core::validation::{Condition, Severity};
+
+#[test]
+fn rules_are_read_from_plain_yaml_maps() {
+    let path = std::env::temp_dir().join(format!("einv-rules-{}.yaml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+rules:
+  - id: ACME-01
+    field: BT-13
+    condition: required
+    message: Order reference is required
+    receivers: ["0218:40003032949"]
+  - id: ACME-02
+    field: BT-5
+    condition:
+      one_of: [EUR]
+    message: "Currency {0} is not accepted"
+    severity: warning
+    when:
+      field: BT-10
+"#,
+    )
+    .unwrap();
+
+    let rules = load_custom_rules(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(rules.len(), 2);
+    assert!(matches!(rules[0].condition, Condition::Required));
+    assert_eq!(rules[0].severity, Severity::Error);
+    assert_eq!(rules[0].receivers, ["0218:40003032949"]);
+    assert!(matches!(&rules[1].condition, Condition::OneOf(v) if v == &["EUR"]));
+    assert_eq!(rules[1].severity, Severity::Warning);
+    let when = rules[1].when.as_ref().unwrap();
+    assert_eq!(
+        (when.field.as_str(), when.equals.as_deref()),
+        ("BT-10", None)
+    );
+}
+
+#[test]
+fn unreadable_rule_files_are_errors() {
+    let missing = std::env::temp_dir().join("einv-rules-missing.yaml");
+    assert!(load_custom_rules(&missing).is_err());
+}
@@ -0,0 +1,75 @@
+use config::AppConfig;
+use lat_einv_core::validation::{Severity, ValidationProfile, LV_B2G, PEPPOL_BIS3};
+
+fn with_rule_pack(pack: &str) -> AppConfig {
+    let mut cfg = AppConfig::default();
+    cfg.sender.rule_pack = Some(pack.to_string());
+    cfg
+}
+
+#[test]
+fn rule_pack_names_select_the_lv_group_in_any_case() {
+    for pack in ["lv", "LV", "Latvia", " latvia "] {
+        let profile = with_rule_pack(pack).validation_profile().unwrap();
+        assert_eq!(profile.name, PEPPOL_BIS3);
+        assert_eq!(
+            profile.groups.iter().filter(|g| *g == "lv").count(),
+            1,
+            "{pack}"
+        );
+    }
+}
+
+#[test]
+fn rule_pack_is_not_added_twice() {
+    let mut cfg = with_rule_pack("LV");
+    cfg.validation.profile = LV_B2G.to_string();
+    let profile = cfg.validation_profile().unwrap();
+    assert_eq!(profile.groups, ValidationProfile::lv_b2g().groups);
+}
+
+#[test]
+fn unknown_rule_packs_are_rejected() {
+    let err = with_rule_pack("ee").validation_profile().unwrap_err();
+    assert!(err.to_string().contains("Unknown rule pack 'ee'"));
+}
+
+#[test]
+fn no_rule_pack_keeps_the_profile() {
+    for pack in [None, Some(String::new())] {
+        let mut cfg = AppConfig::default();
+        cfg.sender.rule_pack = pack;
+        let profile = cfg.validation_profile().unwrap();
+        assert_eq!(profile.groups, ValidationProfile::peppol_bis3().groups);
+    }
+}
+
+#[test]
+fn configured_adjustments_apply_to_user_profiles() {
+    let mut cfg = AppConfig::default();
+    let mut custom = ValidationProfile::peppol_bis3();
+    custom.name = "customer-x".to_string();
+    custom.groups.retain(|g| g != "signature");
+    cfg.validation.profiles.push(custom);
+    cfg.validation.profile = "customer-x".to_string();
+    cfg.validation.disabled_rules.push("BT-115".to_string());
+    cfg.validation
+        .severity_overrides
+        .insert("BR-63".to_string(), Severity::Warning);
+
+    let profile = cfg.validation_profile().unwrap();
+    assert_eq!(profile.name, "customer-x");
+    assert!(!profile.includes("SIG-01", "signature"));
+    assert!(!profile.includes("BT-115", "en16931"));
+    assert_eq!(
+        profile.severity_for("BR-63", Severity::Error),
+        Severity::Warning
+    );
+}
+
+#[test]
+fn unknown_profile_names_fall_back_to_peppol_bis3() {
+    let mut cfg = AppConfig::default();
+    cfg.validation.profile = "missing".to_string();
+    assert_eq!(cfg.validation_profile().unwrap().name, PEPPOL_BIS3);
+}
@@ -0,0 +1,473 @@
+//! ASiC-E (extended Associated Signature Container) packaging
+//!
+//! A container is a zip archive holding a `mimetype` entry first, the data
+//! objects (an invoice, its visual PDF and other attachments), an ODF
+//! manifest with each object's media type and detached XAdES signatures in
+//! `META-INF/signatures*.xml`, whose references carry the objects' digests.
+
+use crate::c14n::escape_attr;
+use crate::xmldsig::trust::TrustStore;
+use crate::xmldsig::{verify_detached_signatures, SignatureVerification, XmlSigner, DSIG_NS};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine as _;
+use roxmltree::Document;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const ASIC_NS: &str = "http://uri.etsi.org/02918/v1.2.1#";
+/// Content of the `mimetype` entry
+pub const ASIC_E_MIMETYPE: &str = "application/vnd.etsi.asic-e+zip";
+
+const MIMETYPE_ENTRY: &str = "mimetype";
+const MANIFEST_ENTRY: &str = "META-INF/manifest.xml";
+const MANIFEST_NS: &str = "urn:oasis:names:tc:opendocument:xmlns:manifest:1.0";
+const DEFAULT_MEDIA_TYPE: &str = "application/octet-stream";
+
+/// How much a container may expand to when read, so that a small archive of
+/// highly compressed entries cannot exhaust memory
+#[derive(Debug, Clone, Copy)]
+pub struct ReadLimits {
+    /// Largest uncompressed size of a single entry
+    pub max_entry_size: u64,
+    /// Largest uncompressed size of all entries together
+    pub max_total_size: u64,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self {
+            max_entry_size: 64 * 1024 * 1024,
+            max_total_size: 256 * 1024 * 1024,
+        }
+    }
+}
+
+impl ReadLimits {
+    pub fn with_max_entry_size(mut self, bytes: u64) -> Self {
+        self.max_entry_size = bytes;
+        self
+    }
+
+    pub fn with_max_total_size(mut self, bytes: u64) -> Self {
+        self.max_total_size = bytes;
+        self
+    }
+}
+
+/// A file in the container other than its metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataObject {
+    /// Path inside the container
+    pub name: String,
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AsicContainer {
+    objects: Vec<DataObject>,
+    /// Paths and media types listed in the manifest
+    manifest: Vec<(String, String)>,
+    /// Signature files, by path
+    signatures: Vec<(String, String)>,
+}
+
+/// Outcome of checking a container against its manifest and signatures
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContainerVerification {
+    pub signatures: Vec<SignatureVerification>,
+    /// Data objects not covered by a valid signature
+    pub unsigned: Vec<String>,
+    /// Differences between the manifest and the container, and unreadable
+    /// signature files
+    pub errors: Vec<String>,
+}
+
+impl ContainerVerification {
+    /// Every signature verifies and chains to a trust anchor, and together
+    /// they cover every data object
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+            && self.unsigned.is_empty()
+            && self.signatures.iter().all(|s| s.is_valid() && s.trusted)
+    }
+}
+
+/// Percent-encode a file name for use as a reference URI
+pub(crate) fn uri_encode(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(byte as char)
+            }
+            _ => {
+                let _ = write!(out, "%{:02X}", byte);
+            }
+        }
+    }
+    out
+}
+
+/// Names of the entries in the central directory starting at `start`, as
+/// stored. The zip reader keeps only the last of the entries sharing a name.
+fn entry_names(data: &[u8], start: u64) -> Vec<&[u8]> {
+    let mut names = Vec::new();
+    let mut pos = start as usize;
+    while let Some(header) = data.get(pos..pos + 46) {
+        if header[..4] != *b"PK\x01\x02" {
+            break;
+        }
+        let len = |at: usize| u16::from_le_bytes([header[at], header[at + 1]]) as usize;
+        let end = pos + 46 + len(28);
+        let Some(name) = data.get(pos + 46..end) else {
+            break;
+        };
+        names.push(name);
+        pos = end + len(30) + len(32);
+    }
+    names
+}
+
+fn uri_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// File name for an attachment, without any directories it names
+fn attachment_name(filename: Option<&str>, index: usize) -> String {
+    filename
+        .and_then(|f| f.rsplit(['/', '\\']).next())
+        .map(str::trim)
+        .filter(|f| !f.is_empty() && *f != "..")
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("attachment-{}", index + 1))
+}
+
+fn parse_manifest(xml: &str) -> Result<Vec<(String, String)>> {
+    let doc = Document::parse(xml).context("Failed to parse manifest")?;
+    Ok(doc
+        .descendants()
+        .filter(|n| n.has_tag_name((MANIFEST_NS, "file-entry")))
+        .filter_map(|n| {
+            let path = n.attribute((MANIFEST_NS, "full-path"))?;
+            let media_type = n.attribute((MANIFEST_NS, "media-type")).unwrap_or_default();
+            (path != "/").then(|| (path.to_string(), media_type.to_string()))
+        })
+        .collect())
+}
+
+impl AsicContainer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Container with an invoice and the attachments embedded in it as
+    /// `EmbeddedDocumentBinaryObject`s, such as its visual PDF
+    pub fn for_invoice(name: &str, xml: &str) -> Result<Self> {
+        let mut container = Self::new();
+        container.add(name, "application/xml", xml.as_bytes().to_vec())?;
+        let doc = Document::parse(xml).context("Failed to parse invoice")?;
+        let embedded = doc
+            .descendants()
+            .filter(|n| n.is_element() && n.tag_name().name() == "EmbeddedDocumentBinaryObject");
+        for (i, object) in embedded.enumerate() {
+            let file_name = attachment_name(object.attribute("filename"), i);
+            let content: String = object
+                .text()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect();
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(content)
+                .with_context(|| format!("Invalid attachment {}", file_name))?;
+            let media_type = object.attribute("mimeCode").unwrap_or(DEFAULT_MEDIA_TYPE);
+            container.add(&file_name, media_type, data)?;
+        }
+        Ok(container)
+    }
+
+    /// Add a data object at path `name`
+    pub fn add(&mut self, name: &str, media_type: &str, data: Vec<u8>) -> Result<()> {
+        if name.is_empty()
+            || name.starts_with('/')
+            || name.split(['/', '\\']).any(|part| part == "..")
+        {
+            bail!("Invalid container path {}", name);
+        }
+        if name == MIMETYPE_ENTRY || name.starts_with("META-INF/") {
+            bail!("{} is reserved for container metadata", name);
+        }
+        if self.object(name).is_some() {
+            bail!("Container already has {}", name);
+        }
+        self.objects.push(DataObject {
+            name: name.to_string(),
+            media_type: media_type.to_string(),
+            data,
+        });
+        self.manifest
+            .push((name.to_string(), media_type.to_string()));
+        Ok(())
+    }
+
+    pub fn objects(&self) -> &[DataObject] {
+        &self.objects
+    }
+
+    pub fn object(&self, name: &str) -> Option<&DataObject> {
+        self.objects.iter().find(|o| o.name == name)
+    }
+
+    /// Signature files by path
+    pub fn signatures(&self) -> &[(String, String)] {
+        &self.signatures
+    }
+
+    /// Add a signature over every data object
+    pub fn sign(&mut self, signer: &XmlSigner) -> Result<()> {
+        let objects: Vec<(&str, &[u8])> = self
+            .objects
+            .iter()
+            .map(|o| (o.name.as_str(), o.data.as_slice()))
+            .collect();
+        let signature = signer.sign_detached(&objects)?;
+        let path = format!("META-INF/signatures{:03}.xml", self.signatures.len() + 1);
+        self.signatures.push((path, signature));
+        Ok(())
+    }
+
+    fn manifest_xml(&self) -> String {
+        let mut out = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <manifest:manifest xmlns:manifest=\"{MANIFEST_NS}\" manifest:version=\"1.2\">\n  \
+             <manifest:file-entry manifest:full-path=\"/\" manifest:media-type=\"{ASIC_E_MIMETYPE}\"/>\n"
+        );
+        for object in &self.objects {
+            out.push_str("  <manifest:file-entry manifest:full-path=\"");
+            escape_attr(&object.name, &mut out);
+            out.push_str("\" manifest:media-type=\"");
+            escape_attr(&object.media_type, &mut out);
+            out.push_str("\"/>\n");
+        }
+        out.push_str("</manifest:manifest>\n");
+        out
+    }
+
+    /// The container as a zip archive
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        // The mimetype entry comes first and uncompressed, so that it can be
+        // recognized at a fixed offset
+        zip.start_file(
+            MIMETYPE_ENTRY,
+            SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+        )?;
+        zip.write_all(ASIC_E_MIMETYPE.as_bytes())?;
+
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for object in &self.objects {
+            zip.start_file(object.name.as_str(), options)?;
+            zip.write_all(&object.data)?;
+        }
+        zip.start_file(MANIFEST_ENTRY, options)?;
+        zip.write_all(self.manifest_xml().as_bytes())?;
+        for (path, signature) in &self.signatures {
+            zip.start_file(path.as_str(), options)?;
+            zip.write_all(signature.as_bytes())?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Self::from_bytes_with(data, ReadLimits::default())
+    }
+
+    /// Read a container, failing once its content exceeds `limits`
+    pub fn from_bytes_with(data: &[u8], limits: ReadLimits) -> Result<Self> {
+        let mut archive = ZipArchive::new(Cursor::new(data)).context("Not a zip archive")?;
+        {
+            let first = archive
+                .by_index(0)
+                .map_err(|_| anyhow!("Not an ASiC-E container (empty archive)"))?;
+            if first.name() != MIMETYPE_ENTRY || first.compression() != CompressionMethod::Stored {
+                bail!("Not an ASiC-E container (mimetype must be the first entry, uncompressed)");
+            }
+        }
+        // Otherwise a second copy of a signed file could replace it unseen
+        let mut seen = HashSet::new();
+        for name in entry_names(data, archive.central_directory_start()) {
+            if !seen.insert(name) {
+                bail!(
+                    "Container has {} more than once",
+                    String::from_utf8_lossy(name)
+                );
+            }
+        }
+
+        let mut container = Self::new();
+        let mut manifest = None;
+        let mut total: u64 = 0;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if entry.is_dir() {
+                continue;
+            }
+            let name = entry.name().to_string();
+            // Read one byte past the limit to tell a full entry from a cut one
+            let mut content = Vec::new();
+            (&mut entry)
+                .take(limits.max_entry_size + 1)
+                .read_to_end(&mut content)
+                .with_context(|| format!("Failed to read {}", name))?;
+            let size = content.len() as u64;
+            if size > limits.max_entry_size {
+                bail!(
+                    "{} is larger than {} bytes uncompressed",
+                    name,
+                    limits.max_entry_size
+                );
+            }
+            total += size;
+            if total > limits.max_total_size {
+                bail!(
+                    "Container content is larger than {} bytes uncompressed",
+                    limits.max_total_size
+                );
+            }
+            if name == MIMETYPE_ENTRY {
+                if content != ASIC_E_MIMETYPE.as_bytes() {
+                    bail!(
+                        "Not an ASiC-E container (mimetype {})",
+                        String::from_utf8_lossy(&content)
+                    );
+                }
+            } else if name == MANIFEST_ENTRY {
+                manifest = Some(String::from_utf8(content).context("Manifest is not UTF-8")?);
+            } else if let Some(meta) = name.strip_prefix("META-INF/") {
+                if meta.contains("signatures") && meta.ends_with(".xml") {
+                    let xml = String::from_utf8(content)
+                        .with_context(|| format!("{} is not UTF-8", name))?;
+                    container.signatures.push((name, xml));
+                }
+            } else {
+                container.objects.push(DataObject {
+                    name,
+                    media_type: DEFAULT_MEDIA_TYPE.to_string(),
+                    data: content,
+                });
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| anyhow!("Container has no {}", MANIFEST_ENTRY))?;
+        container.manifest = parse_manifest(&manifest)?;
+        for object in &mut container.objects {
+            if let Some((_, media_type)) =
+                container.manifest.iter().find(|(p, _)| *p == object.name)
+            {
+                object.media_type = media_type.clone();
+            }
+        }
+        Ok(container)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_bytes()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let data =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_bytes(&data).with_context(|| format!("Invalid container {}", path.display()))
+    }
+
+    /// Check that the manifest lists exactly the data objects, that every
+    /// signature verifies against the objects' contents and that every
+    /// object is signed
+    pub fn verify(&self, trust: &TrustStore) -> ContainerVerification {
+        let mut result = ContainerVerification::default();
+        for object in &self.objects {
+            if !self.manifest.iter().any(|(p, _)| *p == object.name) {
+                result
+                    .errors
+                    .push(format!("{} is not listed in the manifest", object.name));
+            }
+        }
+        for (path, _) in &self.manifest {
+            if self.object(path).is_none() {
+                result
+                    .errors
+                    .push(format!("{} is listed in the manifest but missing", path));
+            }
+        }
+        if self.signatures.is_empty() {
+            result.errors.push("Container is not signed".to_string());
+        }
+
+        let resolve = |uri: &str| self.object(&uri_decode(uri)).map(|o| o.data.clone());
+        let mut signed = HashSet::new();
+        for (path, xml) in &self.signatures {
+            let verifications = match verify_detached_signatures(xml, trust, &resolve) {
+                Ok(v) => v,
+                Err(e) => {
+                    result.errors.push(format!("{}: {}", path, e));
+                    continue;
+                }
+            };
+            // Verifications are in document order of the signatures
+            let doc = Document::parse(xml).ok();
+            let signatures = doc
+                .iter()
+                .flat_map(|d| d.descendants())
+                .filter(|n| n.has_tag_name((DSIG_NS, "Signature")));
+            for (signature, verification) in signatures.zip(&verifications) {
+                if !verification.is_valid() {
+                    continue;
+                }
+                let references = signature
+                    .descendants()
+                    .filter(|n| n.has_tag_name((DSIG_NS, "Reference")))
+                    .filter_map(|n| n.attribute("URI"))
+                    .filter(|uri| !uri.is_empty() && !uri.starts_with('#'));
+                signed.extend(references.map(uri_decode));
+            }
+            if verifications.is_empty() {
+                result
+                    .errors
+                    .push(format!("{} contains no signature", path));
+            }
+            result.signatures.extend(verifications);
+        }
+        result.unsigned = self
+            .objects
+            .iter()
+            .filter(|o| !signed.contains(&o.name))
+            .map(|o| o.name.clone())
+            .collect();
+        result
+    }
+}
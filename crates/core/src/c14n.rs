@@ -0,0 +1,310 @@
+//! Canonical XML 1.0 and Exclusive XML Canonicalization for signature
+//! processing and content hashing
+//!
+//! Works on a parsed `roxmltree` document together with its source text,
+//! which supplies the prefixed names `roxmltree` does not keep. Subtrees can
+//! be left out, as the enveloped-signature transform requires.
+
+use roxmltree::{Document, Node, NodeId, NodeType};
+use std::collections::BTreeMap;
+
+pub const C14N: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315";
+pub const C14N_WITH_COMMENTS: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315#WithComments";
+pub const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+pub const EXC_C14N_WITH_COMMENTS: &str = "http://www.w3.org/2001/10/xml-exc-c14n#WithComments";
+
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Prefix ("" for the default namespace) to URI
+type Namespaces<'a> = BTreeMap<&'a str, &'a str>;
+
+pub struct Canonicalizer<'input> {
+    xml: &'input str,
+    with_comments: bool,
+    exclusive: bool,
+    /// Prefixes handled as in inclusive C14N by the exclusive form
+    inclusive_prefixes: Vec<String>,
+    /// Drop whitespace-only text between elements
+    strip_whitespace: bool,
+    exclude: Vec<NodeId>,
+}
+
+impl<'input> Canonicalizer<'input> {
+    /// `xml` must be the text the nodes passed to this canonicalizer were
+    /// parsed from
+    pub fn new(xml: &'input str) -> Self {
+        Self {
+            xml,
+            with_comments: false,
+            exclusive: false,
+            inclusive_prefixes: Vec::new(),
+            strip_whitespace: false,
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Canonicalizer for a C14N algorithm URI, `None` if unsupported
+    pub fn for_algorithm(xml: &'input str, uri: &str) -> Option<Self> {
+        match uri {
+            C14N => Some(Self::new(xml)),
+            C14N_WITH_COMMENTS => Some(Self::new(xml).with_comments(true)),
+            EXC_C14N => Some(Self::new(xml).with_exclusive(true)),
+            EXC_C14N_WITH_COMMENTS => Some(Self::new(xml).with_exclusive(true).with_comments(true)),
+            _ => None,
+        }
+    }
+
+    pub fn with_comments(mut self, with_comments: bool) -> Self {
+        self.with_comments = with_comments;
+        self
+    }
+
+    /// Exclusive C14N: only namespaces an element visibly uses are rendered
+    pub fn with_exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// The InclusiveNamespaces PrefixList of exclusive C14N ("#default" for
+    /// the default namespace)
+    pub fn with_inclusive_prefixes(mut self, prefixes: &str) -> Self {
+        self.inclusive_prefixes = prefixes
+            .split_whitespace()
+            .map(|p| if p == "#default" { "" } else { p }.to_string())
+            .collect();
+        self
+    }
+
+    /// Leave out whitespace-only text between elements, so indentation does
+    /// not change the output. Not part of C14N; used for content hashes.
+    pub fn with_whitespace_stripped(mut self, strip: bool) -> Self {
+        self.strip_whitespace = strip;
+        self
+    }
+
+    /// Leave `node` and its descendants out of the output
+    pub fn excluding(mut self, node: NodeId) -> Self {
+        self.exclude.push(node);
+        self
+    }
+
+    /// Canonical form of the whole document
+    pub fn document(&self, doc: &Document) -> String {
+        let mut out = String::new();
+        let mut after_root = false;
+        for child in doc.root().children() {
+            let start = out.len();
+            match child.node_type() {
+                NodeType::Element => {
+                    self.element(child, &Namespaces::new(), true, &mut out);
+                    after_root = true;
+                    continue;
+                }
+                NodeType::PI | NodeType::Comment => self.other(child, &mut out),
+                _ => continue,
+            }
+            if out.len() == start {
+                continue;
+            }
+            // Nodes outside the document element are separated by line breaks
+            if after_root {
+                out.insert(start, '\n');
+            } else {
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Canonical form of `node` and its descendants, as a document subset
+    pub fn node(&self, node: Node) -> String {
+        let mut out = String::new();
+        match node.node_type() {
+            NodeType::Root => return self.document(node.document()),
+            NodeType::Element => self.element(node, &Namespaces::new(), true, &mut out),
+            NodeType::Text => escape_text(node.text().unwrap_or_default(), &mut out),
+            _ => self.other(node, &mut out),
+        }
+        out
+    }
+
+    fn is_excluded(&self, node: Node) -> bool {
+        self.exclude.contains(&node.id())
+    }
+
+    fn qname(&self, node: Node) -> &'input str {
+        let start = node.range().start + 1;
+        let tag = &self.xml[start..];
+        let end = tag
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(tag.len());
+        &tag[..end]
+    }
+
+    /// Prefixes of the element and attribute names ("" for an unprefixed
+    /// element, which uses the default namespace)
+    fn utilized_prefixes(&self, node: Node, qname: &'input str) -> Vec<&'input str> {
+        let prefix = |name: &'input str| name.split_once(':').map_or("", |(p, _)| p);
+        let mut prefixes = vec![prefix(qname)];
+        for attr in node.attributes() {
+            let name = &self.xml[attr.range_qname()];
+            if name.contains(':') && !name.starts_with("xml:") {
+                prefixes.push(prefix(name));
+            }
+        }
+        prefixes
+    }
+
+    fn element<'a>(
+        &self,
+        node: Node<'a, 'input>,
+        rendered: &Namespaces<'a>,
+        apex: bool,
+        out: &mut String,
+    ) {
+        if self.is_excluded(node) {
+            return;
+        }
+        let in_scope: Namespaces<'a> = node
+            .namespaces()
+            .filter(|ns| ns.name() != Some("xml"))
+            .map(|ns| (ns.name().unwrap_or(""), ns.uri()))
+            .collect();
+
+        let qname = self.qname(node);
+        out.push('<');
+        out.push_str(qname);
+
+        // Namespace declarations not already in effect in the output
+        let mut rendered_here = rendered.clone();
+        let utilized = self.exclusive.then(|| self.utilized_prefixes(node, qname));
+        let is_rendered = |prefix: &str| {
+            utilized.as_ref().is_none_or(|u| u.contains(&prefix))
+                || self.inclusive_prefixes.iter().any(|p| p == prefix)
+        };
+        if rendered.get("").is_some_and(|uri| !uri.is_empty())
+            && in_scope.get("").is_none_or(|uri| uri.is_empty())
+            && is_rendered("")
+        {
+            out.push_str(" xmlns=\"\"");
+            rendered_here.remove("");
+        }
+        for (prefix, uri) in &in_scope {
+            if rendered.get(prefix) == Some(uri) || uri.is_empty() || !is_rendered(prefix) {
+                continue;
+            }
+            if prefix.is_empty() {
+                out.push_str(" xmlns=\"");
+            } else {
+                out.push_str(" xmlns:");
+                out.push_str(prefix);
+                out.push_str("=\"");
+            }
+            escape_attr(uri, out);
+            out.push('"');
+            rendered_here.insert(prefix, uri);
+        }
+
+        // Attributes by namespace URI, then local name
+        let mut attrs: Vec<(&str, &str, &str, &str)> = node
+            .attributes()
+            .map(|a| {
+                let qname = &self.xml[a.range_qname()];
+                (a.namespace().unwrap_or(""), a.name(), qname, a.value())
+            })
+            .collect();
+        // The apex element also carries xml:* attributes of its ancestors
+        if apex && !self.exclusive {
+            for ancestor in node.ancestors().skip(1).filter(|n| n.is_element()) {
+                for a in ancestor.attributes() {
+                    if a.namespace() == Some(XML_NS)
+                        && !attrs
+                            .iter()
+                            .any(|(ns, name, ..)| *ns == XML_NS && *name == a.name())
+                    {
+                        attrs.push((XML_NS, a.name(), &self.xml[a.range_qname()], a.value()));
+                    }
+                }
+            }
+        }
+        attrs.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        for (_, _, qname, value) in attrs {
+            out.push(' ');
+            out.push_str(qname);
+            out.push_str("=\"");
+            escape_attr(value, out);
+            out.push('"');
+        }
+        out.push('>');
+
+        let strip = self.strip_whitespace && node.children().any(|c| c.is_element());
+        for child in node.children() {
+            match child.node_type() {
+                NodeType::Element => self.element(child, &rendered_here, false, out),
+                NodeType::Text => {
+                    let text = child.text().unwrap_or_default();
+                    let skip = self.is_excluded(child) || (strip && text.trim().is_empty());
+                    if !skip {
+                        escape_text(text, out);
+                    }
+                }
+                _ => self.other(child, out),
+            }
+        }
+
+        out.push_str("</");
+        out.push_str(qname);
+        out.push('>');
+    }
+
+    fn other(&self, node: Node, out: &mut String) {
+        if self.is_excluded(node) {
+            return;
+        }
+        match node.node_type() {
+            NodeType::PI => {
+                if let Some(pi) = node.pi() {
+                    out.push_str("<?");
+                    out.push_str(pi.target);
+                    if let Some(value) = pi.value.filter(|v| !v.is_empty()) {
+                        out.push(' ');
+                        out.push_str(value);
+                    }
+                    out.push_str("?>");
+                }
+            }
+            NodeType::Comment if self.with_comments => {
+                out.push_str("<!--");
+                out.push_str(node.text().unwrap_or_default());
+                out.push_str("-->");
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+pub(crate) fn escape_attr(value: &str, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! PEPPOL code lists used by the validation rules
+//!
+//! The lists are bundled with the crate and can be replaced at runtime from a
+//! local directory containing files with the same names (one code per line,
+//! `#` starts a comment), so they can be updated without recompiling.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CodeList {
+    /// ISO 4217 currency codes
+    Currency,
+    /// ISO 3166-1 alpha-2 country codes
+    Country,
+    /// UNCL1001 invoice type codes
+    InvoiceTypeCode,
+    /// UNCL4461 payment means codes
+    PaymentMeans,
+    /// UN/ECE Recommendation 20 and 21 unit codes
+    UnitOfMeasure,
+    /// UNCL5189 allowance reason codes
+    AllowanceReason,
+    /// Electronic Address Scheme codes
+    ElectronicAddressScheme,
+    /// VATEX VAT exemption reason codes
+    VatExemptionReason,
+}
+
+impl CodeList {
+    pub const ALL: [CodeList; 8] = [
+        CodeList::Currency,
+        CodeList::Country,
+        CodeList::InvoiceTypeCode,
+        CodeList::PaymentMeans,
+        CodeList::UnitOfMeasure,
+        CodeList::AllowanceReason,
+        CodeList::ElectronicAddressScheme,
+        CodeList::VatExemptionReason,
+    ];
+
+    /// File name of the list, both for the bundled copy and for overrides
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            CodeList::Currency => "currency.txt",
+            CodeList::Country => "country.txt",
+            CodeList::InvoiceTypeCode => "uncl1001.txt",
+            CodeList::PaymentMeans => "uncl4461.txt",
+            CodeList::UnitOfMeasure => "unit.txt",
+            CodeList::AllowanceReason => "uncl5189.txt",
+            CodeList::ElectronicAddressScheme => "eas.txt",
+            CodeList::VatExemptionReason => "vatex.txt",
+        }
+    }
+
+    fn bundled(&self) -> &'static str {
+        match self {
+            CodeList::Currency => include_str!("../codelists/currency.txt"),
+            CodeList::Country => include_str!("../codelists/country.txt"),
+            CodeList::InvoiceTypeCode => include_str!("../codelists/uncl1001.txt"),
+            CodeList::PaymentMeans => include_str!("../codelists/uncl4461.txt"),
+            CodeList::UnitOfMeasure => include_str!("../codelists/unit.txt"),
+            CodeList::AllowanceReason => include_str!("../codelists/uncl5189.txt"),
+            CodeList::ElectronicAddressScheme => include_str!("../codelists/eas.txt"),
+            CodeList::VatExemptionReason => include_str!("../codelists/vatex.txt"),
+        }
+    }
+}
+
+fn parse_codes(list: CodeList, text: &str) -> HashSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_whitespace().next())
+        .map(|c| normalize(list, c))
+        .collect()
+}
+
+/// Codes are case sensitive, except VATEX codes which PEPPOL compares
+/// case-insensitively
+fn normalize(list: CodeList, code: &str) -> String {
+    match list {
+        CodeList::VatExemptionReason => code.trim().to_uppercase(),
+        _ => code.trim().to_string(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CodeLists {
+    lists: HashMap<CodeList, HashSet<String>>,
+}
+
+impl CodeLists {
+    /// Code lists bundled with this build
+    pub fn bundled() -> Self {
+        let lists = CodeList::ALL
+            .iter()
+            .map(|list| (*list, parse_codes(*list, list.bundled())))
+            .collect();
+        Self { lists }
+    }
+
+    /// Bundled code lists, with every list that has a file in `dir` replaced
+    /// by the file's contents
+    pub fn with_overrides(dir: &Path) -> Result<Self> {
+        let mut lists = Self::bundled();
+        for list in CodeList::ALL {
+            let path = dir.join(list.file_name());
+            if !path.exists() {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read code list {}", path.display()))?;
+            lists.lists.insert(list, parse_codes(list, &text));
+        }
+        Ok(lists)
+    }
+
+    /// Check whether `code` is in `list`
+    pub fn contains(&self, list: CodeList, code: &str) -> bool {
+        self.lists
+            .get(&list)
+            .map(|codes| codes.contains(&normalize(list, code)))
+            .unwrap_or(false)
+    }
+
+    /// Hash of every code in every list; changes whenever a list is updated
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        for list in CodeList::ALL {
+            let mut codes: Vec<&String> = self.lists.get(&list).into_iter().flatten().collect();
+            codes.sort();
+            hasher.update(list.file_name().as_bytes());
+            for code in codes {
+                hasher.update(b"\n");
+                hasher.update(code.as_bytes());
+            }
+            hasher.update(b"\0");
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+static CURRENT: RwLock<Option<Arc<CodeLists>>> = RwLock::new(None);
+
+/// Code lists used by validation; the bundled lists unless others were installed
+pub fn current() -> Arc<CodeLists> {
+    if let Some(lists) = CURRENT.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return Arc::clone(lists);
+    }
+    let mut slot = CURRENT.write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(slot.get_or_insert_with(|| Arc::new(CodeLists::bundled())))
+}
+
+/// Replace the code lists used by validation
+pub fn install(lists: CodeLists) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(lists));
+}
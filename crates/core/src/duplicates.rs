@@ -0,0 +1,97 @@
+//! Detection of the same invoice appearing more than once
+//!
+//! Two documents are duplicates when supplier, invoice number, issue date and
+//! payable amount agree after normalization, whatever their file names or
+//! formatting.
+
+use crate::parsing::{parse_ubl_invoice, UBLInvoice};
+use crate::sbdh;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DuplicateKey {
+    pub supplier_id: String,
+    pub invoice_number: String,
+    pub issue_date: String,
+    pub total: String,
+}
+
+fn compact(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+impl DuplicateKey {
+    /// Key of a parsed invoice; `None` when it has no invoice number
+    pub fn from_invoice(inv: &UBLInvoice) -> Option<Self> {
+        let invoice_number = compact(&inv.invoice_number);
+        if invoice_number.is_empty() || invoice_number == "UNKNOWN" {
+            return None;
+        }
+        // The VAT number identifies the supplier most reliably across documents
+        let supplier_id = [
+            &inv.supplier_vat_id,
+            &inv.supplier_legal_id,
+            &inv.supplier_id,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|id| compact(id))
+        .find(|id| !id.is_empty())
+        .unwrap_or_else(|| compact(&inv.supplier_name));
+        let total = inv
+            .payable_amount
+            .as_deref()
+            .map(|amount| match amount.trim().parse::<f64>() {
+                Ok(n) => format!("{:.2}", n),
+                Err(_) => compact(amount),
+            })
+            .unwrap_or_default();
+        Some(Self {
+            supplier_id,
+            invoice_number,
+            issue_date: inv.issue_date.trim().to_string(),
+            total,
+        })
+    }
+
+    pub fn from_xml(xml: &str) -> Option<Self> {
+        // An invoice in an SBDH is the same invoice as without one
+        let payload = sbdh::payload(xml);
+        parse_ubl_invoice(payload.as_deref().unwrap_or(xml))
+            .ok()
+            .and_then(|inv| Self::from_invoice(&inv))
+    }
+}
+
+impl fmt::Display for DuplicateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}|{}|{}|{}",
+            self.supplier_id, self.invoice_number, self.issue_date, self.total
+        )
+    }
+}
+
+/// Indices of the other entries sharing each entry's key
+pub fn find_duplicates(keys: &[Option<DuplicateKey>]) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<&DuplicateKey, Vec<usize>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        if let Some(key) = key {
+            groups.entry(key).or_default().push(i);
+        }
+    }
+    keys.iter()
+        .enumerate()
+        .map(|(i, key)| match key.as_ref().and_then(|k| groups.get(k)) {
+            Some(group) => group.iter().copied().filter(|&j| j != i).collect(),
+            None => Vec::new(),
+        })
+        .collect()
+}
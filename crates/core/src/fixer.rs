@@ -0,0 +1,327 @@
+//! Suggested fixes for mechanical validation failures
+//!
+//! Fixes are edits to the original text (not a re-serialization), so
+//! everything the fixer does not touch stays byte-for-byte identical. A fix is
+//! safe when it cannot change the meaning of the document; rounding an amount
+//! or adding an endpoint scheme is proposed but not safe.
+
+use crate::codelists::{self, CodeList};
+use crate::validation::{is_valid_registration_number, is_valid_vat_number};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fix {
+    /// Rule whose issue the fix resolves, if any
+    pub rule_id: Option<String>,
+    pub description: String,
+    pub location: String,
+    pub safe: bool,
+    /// Byte range of the original text replaced by `replacement`
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl Fix {
+    fn new(
+        rule_id: Option<&str>,
+        location: &str,
+        range: Range<usize>,
+        replacement: impl Into<String>,
+        description: String,
+    ) -> Self {
+        Self {
+            rule_id: rule_id.map(str::to_string),
+            description,
+            location: location.to_string(),
+            safe: true,
+            start: range.start,
+            end: range.end,
+            replacement: replacement.into(),
+        }
+    }
+
+    fn unsafe_(mut self) -> Self {
+        self.safe = false;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FixOptions {
+    /// Also apply fixes that change values, such as rounding amounts
+    #[serde(default)]
+    pub include_unsafe: bool,
+    /// Only fixes for these rule IDs; empty means all fixes
+    #[serde(default)]
+    pub rules: Vec<String>,
+}
+
+impl FixOptions {
+    fn selects(&self, fix: &Fix) -> bool {
+        (fix.safe || self.include_unsafe)
+            && (self.rules.is_empty()
+                || fix
+                    .rule_id
+                    .as_ref()
+                    .is_some_and(|id| self.rules.contains(id)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixOutcome {
+    /// Fixes applied to produce `fixed`
+    pub applied: Vec<Fix>,
+    /// Fixes found but not selected by the options
+    pub skipped: Vec<Fix>,
+    pub fixed: String,
+    /// Unified diff between the original and fixed document
+    pub diff: String,
+}
+
+impl FixOutcome {
+    pub fn changed(&self) -> bool {
+        !self.applied.is_empty()
+    }
+}
+
+/// Range of the text content of an element with a single text child
+fn text_range(xml: &str, node: roxmltree::Node) -> Option<Range<usize>> {
+    let mut children = node.children();
+    let text = children.next().filter(|c| c.is_text())?;
+    if children.next().is_some() {
+        return None;
+    }
+    let range = text.range();
+    // CDATA and other unusual content keep their original form
+    (xml.get(range.clone())?.trim() == text.text()?.trim()).then_some(range)
+}
+
+/// Position right after the element name in its start tag
+fn after_tag_name(xml: &str, node: roxmltree::Node) -> Option<usize> {
+    let start = node.range().start;
+    let tag = xml.get(start..)?;
+    let len = tag
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_whitespace() || *c == '>' || *c == '/')
+        .map(|(i, _)| i)?;
+    Some(start + len)
+}
+
+fn path_of(node: roxmltree::Node) -> String {
+    let mut names: Vec<&str> = node
+        .ancestors()
+        .filter(|n| n.is_element())
+        .map(|n| n.tag_name().name())
+        .collect();
+    names.pop(); // The Invoice root
+    names.reverse();
+    names.join("/")
+}
+
+fn currency_fixes(xml: &str, doc: &roxmltree::Document, out: &mut Vec<Fix>) {
+    let lists = codelists::current();
+    let upper_known = |code: &str| {
+        let upper = code.trim().to_uppercase();
+        (upper != code.trim() && lists.contains(CodeList::Currency, &upper)).then_some(upper)
+    };
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        let name = node.tag_name().name();
+        if name == "DocumentCurrencyCode" || name == "TaxCurrencyCode" {
+            let Some(range) = text_range(xml, node) else {
+                continue;
+            };
+            if let Some(upper) = upper_known(&xml[range.clone()]) {
+                let rule = (name == "DocumentCurrencyCode").then_some("BR-CL-04");
+                let description = format!("Uppercase currency code to {}", upper);
+                out.push(Fix::new(rule, &path_of(node), range, upper, description));
+            }
+        }
+        if let Some(attr) = node.attributes().find(|a| a.name() == "currencyID") {
+            if let Some(upper) = upper_known(attr.value()) {
+                let location = format!("{}/@currencyID", path_of(node));
+                let description = format!("Uppercase currencyID to {}", upper);
+                out.push(Fix::new(
+                    None,
+                    &location,
+                    attr.range_value(),
+                    upper,
+                    description,
+                ));
+            }
+        }
+    }
+}
+
+fn identifier_fixes(xml: &str, doc: &roxmltree::Document, out: &mut Vec<Fix>) {
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        if !node.tag_name().name().ends_with("ID") {
+            continue;
+        }
+        let Some(range) = text_range(xml, node) else {
+            continue;
+        };
+        let raw = &xml[range.clone()];
+        let trimmed = raw.trim();
+        if trimmed != raw && !trimmed.is_empty() {
+            let description = format!("Remove whitespace around '{}'", trimmed);
+            out.push(Fix::new(None, &path_of(node), range, trimmed, description));
+        }
+    }
+}
+
+fn endpoint_scheme_fixes(xml: &str, doc: &roxmltree::Document, out: &mut Vec<Fix>) {
+    for node in doc.descendants().filter(|n| n.has_tag_name("EndpointID")) {
+        if node.attribute("schemeID").is_some() {
+            continue;
+        }
+        let rule = match node
+            .ancestors()
+            .map(|a| a.tag_name().name())
+            .find(|n| *n == "AccountingSupplierParty" || *n == "AccountingCustomerParty")
+        {
+            Some("AccountingSupplierParty") => "BR-62",
+            Some(_) => "BR-63",
+            None => continue,
+        };
+        let value = node.text().unwrap_or_default().trim();
+        // Only schemes that the identifier itself proves
+        let scheme = if is_valid_vat_number(value) {
+            "9939"
+        } else if is_valid_registration_number(value) {
+            "0218"
+        } else {
+            continue;
+        };
+        let Some(at) = after_tag_name(xml, node) else {
+            continue;
+        };
+        // A number valid in one scheme may still be meant in another
+        let description = format!("Add schemeID {} for endpoint {}", scheme, value);
+        out.push(
+            Fix::new(
+                Some(rule),
+                &path_of(node),
+                at..at,
+                format!(" schemeID=\"{}\"", scheme),
+                description,
+            )
+            .unsafe_(),
+        );
+    }
+}
+
+/// `amount` rounded half away from zero to two decimals, and whether only
+/// zeros were dropped
+fn round_amount(amount: &str) -> Option<(String, bool)> {
+    let (int, frac) = amount.split_once('.')?;
+    let (sign, digits) = match int.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", int.strip_prefix('+').unwrap_or(int)),
+    };
+    if digits.is_empty() || !(digits.chars().chain(frac.chars())).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let exact = frac[2..].chars().all(|c| c == '0');
+    let mut cents: u128 = format!("{}{}", digits, &frac[..2]).parse().ok()?;
+    if frac.as_bytes()[2] >= b'5' {
+        cents += 1;
+    }
+    let rounded = format!("{}{}.{:02}", sign, cents / 100, cents % 100);
+    Some((rounded, exact))
+}
+
+fn amount_fixes(xml: &str, doc: &roxmltree::Document, out: &mut Vec<Fix>) {
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        if node.attribute("currencyID").is_none() || node.has_tag_name("PriceAmount") {
+            continue;
+        }
+        let Some(range) = text_range(xml, node) else {
+            continue;
+        };
+        let raw = xml[range.clone()].trim();
+        if !crate::validation::has_excess_decimals(raw) {
+            continue;
+        }
+        let Some((rounded, exact)) = round_amount(raw) else {
+            continue;
+        };
+        let fix = if exact {
+            let description = format!("Drop trailing zeros: {} to {}", raw, rounded);
+            Fix::new(Some("BR-DEC"), &path_of(node), range, rounded, description)
+        } else {
+            let description = format!("Round {} to {}", raw, rounded);
+            Fix::new(Some("BR-DEC"), &path_of(node), range, rounded, description).unsafe_()
+        };
+        out.push(fix);
+    }
+}
+
+/// Every fix the fixer can suggest for `xml`, in document order
+pub fn propose_fixes(xml: &str) -> Result<Vec<Fix>> {
+    let doc = roxmltree::Document::parse(xml).context("Failed to parse XML")?;
+    let mut fixes = Vec::new();
+    currency_fixes(xml, &doc, &mut fixes);
+    identifier_fixes(xml, &doc, &mut fixes);
+    endpoint_scheme_fixes(xml, &doc, &mut fixes);
+    amount_fixes(xml, &doc, &mut fixes);
+    fixes.sort_by_key(|f| (f.start, f.end));
+    Ok(fixes)
+}
+
+/// Apply `fixes` to `xml`, skipping any that overlap an earlier fix
+pub fn apply_fixes(xml: &str, fixes: &[Fix]) -> (String, Vec<Fix>) {
+    let mut sorted: Vec<&Fix> = fixes.iter().collect();
+    sorted.sort_by_key(|f| (f.start, f.end));
+    let mut applied: Vec<Fix> = Vec::new();
+    let mut out = String::with_capacity(xml.len());
+    let mut pos = 0;
+    for fix in sorted {
+        if fix.start < pos || fix.end > xml.len() || fix.start > fix.end {
+            continue;
+        }
+        out.push_str(&xml[pos..fix.start]);
+        out.push_str(&fix.replacement);
+        pos = fix.end;
+        applied.push(fix.clone());
+    }
+    out.push_str(&xml[pos..]);
+    (out, applied)
+}
+
+/// Unified diff between two versions of the document named `name`
+pub fn diff(original: &str, fixed: &str, name: &str) -> String {
+    TextDiff::from_lines(original, fixed)
+        .unified_diff()
+        .context_radius(2)
+        .header(name, &format!("{} (fixed)", name))
+        .to_string()
+}
+
+/// Propose fixes for `xml` and apply those selected by `options`
+pub fn fix_document(xml: &str, name: &str, options: &FixOptions) -> Result<FixOutcome> {
+    let (selected, skipped): (Vec<Fix>, Vec<Fix>) = propose_fixes(xml)?
+        .into_iter()
+        .partition(|f| options.selects(f));
+    let (fixed, applied) = apply_fixes(xml, &selected);
+    Ok(FixOutcome {
+        diff: diff(xml, &fixed, name),
+        applied,
+        skipped,
+        fixed,
+    })
+}
+
+/// Where the fixed copy of `path` is written; the original is never replaced
+pub fn fixed_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "invoice".to_string());
+    path.with_file_name(format!("{}.fixed.xml", stem))
+}
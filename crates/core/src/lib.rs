@@ -1,3 +1,10 @@
+pub mod asic;
+pub mod c14n;
+pub mod codelists;
+pub mod duplicates;
+pub mod fixer;
 pub mod models;
 pub mod parsing;
+pub mod sbdh;
 pub mod validation;
+pub mod xmldsig;
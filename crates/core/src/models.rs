@@ -1,11 +1,81 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum DeliveryStatus {
-    Pending,
+/// Where a sent invoice is on its way to the recipient
+///
+/// Jobs start `Queued`, are `InFlight` while being submitted and `Sent` once
+/// the access point accepted them, until it reports them `Delivered`,
+/// `Failed` or `Rejected` by the recipient. Failed jobs are queued again or
+/// parked as a `DeadLetter` until retried by hand; queued jobs can be
+/// `Cancelled`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    Queued,
     InFlight,
+    #[serde(alias = "pending")]
+    Sent,
     Delivered,
     Failed,
+    Rejected,
+    DeadLetter,
+    Cancelled,
+}
+
+impl DeliveryState {
+    pub const ALL: [DeliveryState; 8] = [
+        DeliveryState::Queued,
+        DeliveryState::InFlight,
+        DeliveryState::Sent,
+        DeliveryState::Delivered,
+        DeliveryState::Failed,
+        DeliveryState::Rejected,
+        DeliveryState::DeadLetter,
+        DeliveryState::Cancelled,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryState::Queued => "queued",
+            DeliveryState::InFlight => "in_flight",
+            DeliveryState::Sent => "sent",
+            DeliveryState::Delivered => "delivered",
+            DeliveryState::Failed => "failed",
+            DeliveryState::Rejected => "rejected",
+            DeliveryState::DeadLetter => "dead_letter",
+            DeliveryState::Cancelled => "cancelled",
+        }
+    }
+
+    /// States a job may move to from this one
+    pub fn successors(self) -> &'static [DeliveryState] {
+        use DeliveryState::*;
+        match self {
+            Queued => &[InFlight, Cancelled],
+            // Back to queued when a submission attempt is retried
+            InFlight => &[Sent, Failed, Queued],
+            Sent => &[Delivered, Failed, Rejected],
+            Failed => &[DeadLetter, Queued],
+            // Retried by hand
+            DeadLetter => &[Queued],
+            Delivered | Rejected | Cancelled => &[],
+        }
+    }
+
+    pub fn can_transition_to(self, next: DeliveryState) -> bool {
+        self.successors().contains(&next)
+    }
+
+    /// No further transitions are possible
+    pub fn is_final(self) -> bool {
+        self.successors().is_empty()
+    }
+}
+
+impl fmt::Display for DeliveryState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,3 +1,4 @@
+use crate::c14n::Canonicalizer;
 use anyhow::Result;
 use sha2::{Digest, Sha256};
 
@@ -8,6 +9,19 @@ pub fn compute_sha256_hex(xml: &str) -> String {
     hex::encode(bytes)
 }
 
+/// SHA-256 (hex) of the exclusive canonical form of `xml`, without comments
+/// or indentation. Unlike [`compute_sha256_hex`] it is the same for copies of
+/// a document that differ only in formatting, attribute order, quoting or
+/// namespace declarations.
+pub fn compute_canonical_sha256_hex(xml: &str) -> Result<String> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let canonical = Canonicalizer::new(xml)
+        .with_exclusive(true)
+        .with_whitespace_stripped(true)
+        .document(&doc);
+    Ok(compute_sha256_hex(&canonical))
+}
+
 fn find_element<'a, 'input: 'a>(
     node: roxmltree::Node<'a, 'input>,
     path: &[&str],
@@ -28,31 +42,129 @@ fn find_element<'a, 'input: 'a>(
     None
 }
 
-fn get_text_at_path(doc: &roxmltree::Document, path: &[&str]) -> Option<String> {
+fn find_all_elements<'a, 'input: 'a>(
+    node: roxmltree::Node<'a, 'input>,
+    path: &[&str],
+    out: &mut Vec<roxmltree::Node<'a, 'input>>,
+) {
+    if path.is_empty() {
+        out.push(node);
+        return;
+    }
+    for child in node.children() {
+        if child.is_element() && child.tag_name().name() == path[0] {
+            find_all_elements(child, &path[1..], out);
+        }
+    }
+}
+
+pub(crate) fn get_text_at_path(doc: &roxmltree::Document, path: &[&str]) -> Option<String> {
     find_element(doc.root_element(), path)
         .and_then(|n| n.text())
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
 }
 
+pub(crate) fn get_attr_at_path(
+    doc: &roxmltree::Document,
+    path: &[&str],
+    attr: &str,
+) -> Option<String> {
+    find_element(doc.root_element(), path)
+        .and_then(|n| n.attribute(attr))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Trimmed, non-empty texts of all elements at `path` below the root element
+pub fn get_texts_at_path(doc: &roxmltree::Document, path: &[&str]) -> Vec<String> {
+    let mut nodes = Vec::new();
+    find_all_elements(doc.root_element(), path, &mut nodes);
+    nodes
+        .into_iter()
+        .filter_map(|n| n.text())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn get_attrs_at_path(doc: &roxmltree::Document, path: &[&str], attr: &str) -> Vec<String> {
+    let mut nodes = Vec::new();
+    find_all_elements(doc.root_element(), path, &mut nodes);
+    nodes
+        .into_iter()
+        .filter_map(|n| n.attribute(attr))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|c| c.is_element() && c.tag_name().name() == name)
+        .and_then(|c| c.text())
+        .map(str::trim)
+}
+
+/// Reason codes of the allowances (not charges) found at `path`
+fn get_allowance_reason_codes(doc: &roxmltree::Document, path: &[&str]) -> Vec<String> {
+    let mut nodes = Vec::new();
+    find_all_elements(doc.root_element(), path, &mut nodes);
+    nodes
+        .into_iter()
+        .filter(|n| child_text(*n, "ChargeIndicator") == Some("false"))
+        .filter_map(|n| child_text(n, "AllowanceChargeReasonCode"))
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct UBLInvoice {
     pub invoice_number: String,
+    pub invoice_type_code: Option<String>,
     pub issue_date: String,
     pub due_date: Option<String>,
     pub currency_code: String,
     pub supplier_name: String,
     pub supplier_id: Option<String>,
+    pub supplier_endpoint_scheme: Option<String>,
+    pub supplier_vat_id: Option<String>,
+    pub supplier_legal_id: Option<String>,
+    /// Scheme of the supplier's registration number (BT-30-1)
+    pub supplier_legal_id_scheme: Option<String>,
+    /// Country of the supplier's address (BT-40)
+    pub supplier_country: Option<String>,
     pub customer_name: String,
     pub customer_id: Option<String>,
+    pub customer_endpoint_scheme: Option<String>,
+    pub customer_vat_id: Option<String>,
+    pub customer_legal_id: Option<String>,
+    /// Scheme of the customer's registration number (BT-47-1)
+    pub customer_legal_id_scheme: Option<String>,
+    /// Country of the customer's address (BT-55)
+    pub customer_country: Option<String>,
     pub tax_total: Option<String>,
     pub payable_amount: Option<String>,
+    /// VAT rates (BT-119 / BT-152) as they appear in the document
+    pub vat_rates: Vec<String>,
+    /// VAT exemption reason codes (BT-121)
+    pub vat_exemption_reason_codes: Vec<String>,
+    /// Country codes of all postal addresses and item origins
+    pub country_codes: Vec<String>,
+    /// Payment means codes (BT-81)
+    pub payment_means_codes: Vec<String>,
+    /// Unit codes of invoiced and base quantities (BT-130 / BT-150)
+    pub unit_codes: Vec<String>,
+    /// Document and line level allowance reason codes (BT-98 / BT-140)
+    pub allowance_reason_codes: Vec<String>,
 }
 
 pub fn parse_ubl_invoice(xml: &str) -> Result<UBLInvoice> {
     let doc = roxmltree::Document::parse(xml)?;
 
     let invoice_number = get_text_at_path(&doc, &["ID"]).unwrap_or_else(|| "UNKNOWN".to_string());
+    let invoice_type_code = get_text_at_path(&doc, &["InvoiceTypeCode"]);
     let issue_date = get_text_at_path(&doc, &["IssueDate"]).unwrap_or_default();
     let due_date = get_text_at_path(&doc, &["DueDate"]);
     let currency_code = get_text_at_path(&doc, &["DocumentCurrencyCode"]).unwrap_or_default();
@@ -74,6 +186,49 @@ pub fn parse_ubl_invoice(xml: &str) -> Result<UBLInvoice> {
     })
     .unwrap_or_default();
     let supplier_id = get_text_at_path(&doc, &["AccountingSupplierParty", "Party", "EndpointID"]);
+    let supplier_endpoint_scheme = get_attr_at_path(
+        &doc,
+        &["AccountingSupplierParty", "Party", "EndpointID"],
+        "schemeID",
+    );
+    let supplier_vat_id = get_text_at_path(
+        &doc,
+        &[
+            "AccountingSupplierParty",
+            "Party",
+            "PartyTaxScheme",
+            "CompanyID",
+        ],
+    );
+    let supplier_legal_id = get_text_at_path(
+        &doc,
+        &[
+            "AccountingSupplierParty",
+            "Party",
+            "PartyLegalEntity",
+            "CompanyID",
+        ],
+    );
+    let supplier_legal_id_scheme = get_attr_at_path(
+        &doc,
+        &[
+            "AccountingSupplierParty",
+            "Party",
+            "PartyLegalEntity",
+            "CompanyID",
+        ],
+        "schemeID",
+    );
+    let supplier_country = get_text_at_path(
+        &doc,
+        &[
+            "AccountingSupplierParty",
+            "Party",
+            "PostalAddress",
+            "Country",
+            "IdentificationCode",
+        ],
+    );
 
     let customer_name = get_text_at_path(
         &doc,
@@ -92,20 +247,146 @@ pub fn parse_ubl_invoice(xml: &str) -> Result<UBLInvoice> {
     })
     .unwrap_or_default();
     let customer_id = get_text_at_path(&doc, &["AccountingCustomerParty", "Party", "EndpointID"]);
+    let customer_endpoint_scheme = get_attr_at_path(
+        &doc,
+        &["AccountingCustomerParty", "Party", "EndpointID"],
+        "schemeID",
+    );
+    let customer_vat_id = get_text_at_path(
+        &doc,
+        &[
+            "AccountingCustomerParty",
+            "Party",
+            "PartyTaxScheme",
+            "CompanyID",
+        ],
+    );
+    let customer_legal_id = get_text_at_path(
+        &doc,
+        &[
+            "AccountingCustomerParty",
+            "Party",
+            "PartyLegalEntity",
+            "CompanyID",
+        ],
+    );
+    let customer_legal_id_scheme = get_attr_at_path(
+        &doc,
+        &[
+            "AccountingCustomerParty",
+            "Party",
+            "PartyLegalEntity",
+            "CompanyID",
+        ],
+        "schemeID",
+    );
+    let customer_country = get_text_at_path(
+        &doc,
+        &[
+            "AccountingCustomerParty",
+            "Party",
+            "PostalAddress",
+            "Country",
+            "IdentificationCode",
+        ],
+    );
 
     let tax_total = get_text_at_path(&doc, &["TaxTotal", "TaxAmount"]);
     let payable_amount = get_text_at_path(&doc, &["LegalMonetaryTotal", "PayableAmount"]);
 
+    let mut vat_rates =
+        get_texts_at_path(&doc, &["TaxTotal", "TaxSubtotal", "TaxCategory", "Percent"]);
+    vat_rates.extend(get_texts_at_path(
+        &doc,
+        &["InvoiceLine", "Item", "ClassifiedTaxCategory", "Percent"],
+    ));
+    let vat_exemption_reason_codes = get_texts_at_path(
+        &doc,
+        &[
+            "TaxTotal",
+            "TaxSubtotal",
+            "TaxCategory",
+            "TaxExemptionReasonCode",
+        ],
+    );
+
+    let country_paths: [&[&str]; 5] = [
+        &[
+            "AccountingSupplierParty",
+            "Party",
+            "PostalAddress",
+            "Country",
+            "IdentificationCode",
+        ],
+        &[
+            "AccountingCustomerParty",
+            "Party",
+            "PostalAddress",
+            "Country",
+            "IdentificationCode",
+        ],
+        &[
+            "TaxRepresentativeParty",
+            "PostalAddress",
+            "Country",
+            "IdentificationCode",
+        ],
+        &[
+            "Delivery",
+            "DeliveryLocation",
+            "Address",
+            "Country",
+            "IdentificationCode",
+        ],
+        &["InvoiceLine", "Item", "OriginCountry", "IdentificationCode"],
+    ];
+    let country_codes = country_paths
+        .iter()
+        .flat_map(|path| get_texts_at_path(&doc, path))
+        .collect();
+
+    let payment_means_codes = get_texts_at_path(&doc, &["PaymentMeans", "PaymentMeansCode"]);
+
+    let mut unit_codes = get_attrs_at_path(&doc, &["InvoiceLine", "InvoicedQuantity"], "unitCode");
+    unit_codes.extend(get_attrs_at_path(
+        &doc,
+        &["InvoiceLine", "Price", "BaseQuantity"],
+        "unitCode",
+    ));
+
+    let mut allowance_reason_codes = get_allowance_reason_codes(&doc, &["AllowanceCharge"]);
+    allowance_reason_codes.extend(get_allowance_reason_codes(
+        &doc,
+        &["InvoiceLine", "AllowanceCharge"],
+    ));
+
     Ok(UBLInvoice {
         invoice_number,
+        invoice_type_code,
         issue_date,
         due_date,
         currency_code,
         supplier_name,
         supplier_id,
+        supplier_endpoint_scheme,
+        supplier_vat_id,
+        supplier_legal_id,
+        supplier_legal_id_scheme,
+        supplier_country,
         customer_name,
         customer_id,
+        customer_endpoint_scheme,
+        customer_vat_id,
+        customer_legal_id,
+        customer_legal_id_scheme,
+        customer_country,
         tax_total,
         payable_amount,
+        vat_rates,
+        vat_exemption_reason_codes,
+        country_codes,
+        payment_means_codes,
+        unit_codes,
+        allowance_reason_codes,
     })
 }
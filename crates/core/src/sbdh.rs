@@ -0,0 +1,391 @@
+//! PEPPOL Standard Business Document Header (SBDH)
+//!
+//! Access points exchange documents wrapped in a `StandardBusinessDocument`
+//! whose header names the sending and receiving participants, the document
+//! type and the business process. Headers for outgoing invoices are derived
+//! from the invoice itself; received envelopes are taken apart with
+//! [`unwrap`] or [`payload`].
+
+use crate::c14n::{escape_attr, escape_text};
+use crate::parsing::{get_attr_at_path, get_text_at_path};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+
+pub const SBDH_NS: &str = "http://www.unece.org/cefact/namespaces/StandardBusinessDocumentHeader";
+/// Scheme of PEPPOL participant identifiers (`<EAS code>:<identifier>`)
+pub const PARTICIPANT_SCHEME: &str = "iso6523-actorid-upis";
+pub const DOCUMENT_TYPE_SCHEME: &str = "busdox-docid-qns";
+pub const PROCESS_SCHEME: &str = "cenbii-procid-ubl";
+/// PEPPOL BIS Billing 3.0
+pub const BILLING_PROCESS: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+
+/// An identifier and the scheme (SBDH `Authority`) it belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identifier {
+    pub scheme: String,
+    pub value: String,
+}
+
+impl Identifier {
+    pub fn new(scheme: &str, value: &str) -> Self {
+        Self {
+            scheme: scheme.trim().to_string(),
+            value: value.trim().to_string(),
+        }
+    }
+
+    /// PEPPOL participant identifier for an endpoint ID and its EAS scheme
+    pub fn participant(eas: &str, id: &str) -> Self {
+        Self::new(PARTICIPANT_SCHEME, &format!("{}:{}", eas.trim(), id.trim()))
+    }
+
+    /// Identifiers are compared case-insensitively
+    pub fn matches(&self, other: &Identifier) -> bool {
+        self.scheme.eq_ignore_ascii_case(&other.scheme)
+            && self.value.eq_ignore_ascii_case(&other.value)
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.scheme, self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sbdh {
+    pub sender: Identifier,
+    pub receiver: Identifier,
+    /// Namespace of the document's root element
+    pub standard: String,
+    pub type_version: String,
+    /// Local name of the document's root element
+    pub document_type: String,
+    pub instance_id: String,
+    pub creation_time: DateTime<Utc>,
+    /// PEPPOL document type identifier (`DOCUMENTID` scope)
+    pub document_id: Identifier,
+    /// PEPPOL process identifier (`PROCESSID` scope)
+    pub process_id: Identifier,
+    /// Country of the sender (`COUNTRY_C1` scope)
+    pub country_c1: Option<String>,
+}
+
+/// What a header says about the document it carries
+pub(crate) struct DocumentInfo {
+    pub standard: String,
+    pub document_type: String,
+    pub type_version: String,
+    pub document_id: Identifier,
+    pub process_id: Identifier,
+}
+
+pub(crate) fn document_info(doc: &Document) -> Result<DocumentInfo> {
+    let root = doc.root_element();
+    let standard = root
+        .tag_name()
+        .namespace()
+        .ok_or_else(|| anyhow!("Document root element has no namespace"))?;
+    let document_type = root.tag_name().name();
+    let type_version =
+        get_text_at_path(doc, &["UBLVersionID"]).unwrap_or_else(|| "2.1".to_string());
+    let customization = get_text_at_path(doc, &["CustomizationID"])
+        .ok_or_else(|| anyhow!("CustomizationID is required for the document type identifier"))?;
+    let process =
+        get_text_at_path(doc, &["ProfileID"]).unwrap_or_else(|| BILLING_PROCESS.to_string());
+    Ok(DocumentInfo {
+        standard: standard.to_string(),
+        document_type: document_type.to_string(),
+        document_id: Identifier::new(
+            DOCUMENT_TYPE_SCHEME,
+            &format!(
+                "{}::{}##{}::{}",
+                standard, document_type, customization, type_version
+            ),
+        ),
+        process_id: Identifier::new(PROCESS_SCHEME, &process),
+        type_version,
+    })
+}
+
+fn sbdh_child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name((SBDH_NS, name)))
+}
+
+fn text<'a>(node: Node<'a, '_>) -> &'a str {
+    node.text().unwrap_or_default().trim()
+}
+
+/// xs:dateTime, read as UTC when it has no offset
+fn parse_date_time(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .map(|time| time.and_utc())
+        .with_context(|| format!("Invalid CreationDateAndTime {}", value))
+}
+
+fn push_element(out: &mut String, indent: &str, name: &str, value: &str) {
+    let _ = write!(out, "{indent}<sh:{name}>");
+    escape_text(value, out);
+    let _ = writeln!(out, "</sh:{name}>");
+}
+
+fn push_identifier(out: &mut String, indent: &str, name: &str, id: &Identifier) {
+    let _ = write!(out, "{indent}<sh:{name}><sh:Identifier Authority=\"");
+    escape_attr(&id.scheme, out);
+    out.push_str("\">");
+    escape_text(&id.value, out);
+    let _ = writeln!(out, "</sh:Identifier></sh:{name}>");
+}
+
+fn push_scope(out: &mut String, kind: &str, value: &str, scheme: Option<&str>) {
+    out.push_str("      <sh:Scope>\n");
+    push_element(out, "        ", "Type", kind);
+    push_element(out, "        ", "InstanceIdentifier", value);
+    if let Some(scheme) = scheme {
+        push_element(out, "        ", "Identifier", scheme);
+    }
+    out.push_str("      </sh:Scope>\n");
+}
+
+impl Sbdh {
+    /// Header for sending `xml`, a UBL invoice or credit note, from the
+    /// seller's to the buyer's electronic address
+    pub fn for_invoice(xml: &str) -> Result<Self> {
+        let doc = Document::parse(xml).context("Failed to parse XML")?;
+        let info = document_info(&doc)?;
+        let participant = |party: &str| {
+            let path = [party, "Party", "EndpointID"];
+            match (
+                get_attr_at_path(&doc, &path, "schemeID"),
+                get_text_at_path(&doc, &path),
+            ) {
+                (Some(scheme), Some(id)) => Ok(Identifier::participant(&scheme, &id)),
+                _ => Err(anyhow!("{} has no EndpointID with a schemeID", party)),
+            }
+        };
+        Ok(Self {
+            sender: participant("AccountingSupplierParty")?,
+            receiver: participant("AccountingCustomerParty")?,
+            standard: info.standard,
+            type_version: info.type_version,
+            document_type: info.document_type,
+            instance_id: uuid::Uuid::new_v4().to_string(),
+            creation_time: Utc::now(),
+            document_id: info.document_id,
+            process_id: info.process_id,
+            country_c1: get_text_at_path(
+                &doc,
+                &[
+                    "AccountingSupplierParty",
+                    "Party",
+                    "PostalAddress",
+                    "Country",
+                    "IdentificationCode",
+                ],
+            ),
+        })
+    }
+
+    pub fn with_instance_id(mut self, instance_id: &str) -> Self {
+        self.instance_id = instance_id.to_string();
+        self
+    }
+
+    pub fn with_creation_time(mut self, creation_time: DateTime<Utc>) -> Self {
+        self.creation_time = creation_time;
+        self
+    }
+
+    /// Header of a StandardBusinessDocument
+    pub fn parse(xml: &str) -> Result<Self> {
+        let doc = Document::parse(xml).context("Failed to parse XML")?;
+        let root = doc.root_element();
+        if !root.has_tag_name((SBDH_NS, "StandardBusinessDocument")) {
+            return Err(anyhow!("Not a StandardBusinessDocument"));
+        }
+        let header = sbdh_child(root, "StandardBusinessDocumentHeader")
+            .ok_or_else(|| anyhow!("Missing StandardBusinessDocumentHeader"))?;
+        let participant = |name: &str| {
+            sbdh_child(header, name)
+                .and_then(|n| sbdh_child(n, "Identifier"))
+                .map(|id| Identifier::new(id.attribute("Authority").unwrap_or_default(), text(id)))
+                .ok_or_else(|| anyhow!("Missing {} identifier", name))
+        };
+        let identification = sbdh_child(header, "DocumentIdentification")
+            .ok_or_else(|| anyhow!("Missing DocumentIdentification"))?;
+        let field = |name: &str| {
+            sbdh_child(identification, name)
+                .map(|n| text(n).to_string())
+                .ok_or_else(|| anyhow!("Missing DocumentIdentification/{}", name))
+        };
+        let scopes: Vec<Node> = sbdh_child(header, "BusinessScope")
+            .into_iter()
+            .flat_map(|n| n.children())
+            .filter(|n| n.has_tag_name((SBDH_NS, "Scope")))
+            .collect();
+        let scope = |kind: &str, default_scheme: &str| {
+            scopes
+                .iter()
+                .find(|s| sbdh_child(**s, "Type").map(text) == Some(kind))
+                .map(|s| {
+                    let scheme = sbdh_child(*s, "Identifier").map_or(default_scheme, text);
+                    let value = sbdh_child(*s, "InstanceIdentifier").map_or("", text);
+                    Identifier::new(scheme, value)
+                })
+        };
+
+        Ok(Self {
+            sender: participant("Sender")?,
+            receiver: participant("Receiver")?,
+            standard: field("Standard")?,
+            type_version: field("TypeVersion")?,
+            document_type: field("Type")?,
+            instance_id: field("InstanceIdentifier")?,
+            creation_time: parse_date_time(&field("CreationDateAndTime")?)?,
+            document_id: scope("DOCUMENTID", DOCUMENT_TYPE_SCHEME)
+                .ok_or_else(|| anyhow!("Missing DOCUMENTID scope"))?,
+            process_id: scope("PROCESSID", PROCESS_SCHEME)
+                .ok_or_else(|| anyhow!("Missing PROCESSID scope"))?,
+            country_c1: scope("COUNTRY_C1", "").map(|s| s.value),
+        })
+    }
+
+    /// `document` wrapped in a StandardBusinessDocument with this header
+    pub fn wrap(&self, document: &str) -> String {
+        let mut out = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <sh:StandardBusinessDocument xmlns:sh=\"{SBDH_NS}\">\n  \
+             <sh:StandardBusinessDocumentHeader>\n"
+        );
+        push_element(&mut out, "    ", "HeaderVersion", "1.0");
+        push_identifier(&mut out, "    ", "Sender", &self.sender);
+        push_identifier(&mut out, "    ", "Receiver", &self.receiver);
+        out.push_str("    <sh:DocumentIdentification>\n");
+        push_element(&mut out, "      ", "Standard", &self.standard);
+        push_element(&mut out, "      ", "TypeVersion", &self.type_version);
+        push_element(&mut out, "      ", "InstanceIdentifier", &self.instance_id);
+        push_element(&mut out, "      ", "Type", &self.document_type);
+        push_element(
+            &mut out,
+            "      ",
+            "CreationDateAndTime",
+            &self
+                .creation_time
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        );
+        out.push_str("    </sh:DocumentIdentification>\n    <sh:BusinessScope>\n");
+        push_scope(
+            &mut out,
+            "DOCUMENTID",
+            &self.document_id.value,
+            Some(&self.document_id.scheme),
+        );
+        push_scope(
+            &mut out,
+            "PROCESSID",
+            &self.process_id.value,
+            Some(&self.process_id.scheme),
+        );
+        if let Some(country) = &self.country_c1 {
+            push_scope(&mut out, "COUNTRY_C1", country, None);
+        }
+        out.push_str("    </sh:BusinessScope>\n  </sh:StandardBusinessDocumentHeader>\n");
+        out.push_str(strip_declaration(document).trim());
+        out.push_str("\n</sh:StandardBusinessDocument>\n");
+        out
+    }
+}
+
+/// Wrap a UBL invoice in an SBDH addressed from its seller to its buyer
+pub fn wrap_invoice(xml: &str) -> Result<String> {
+    Ok(Sbdh::for_invoice(xml)?.wrap(xml))
+}
+
+fn strip_declaration(document: &str) -> &str {
+    let document = document.trim_start_matches('\u{feff}').trim_start();
+    match document.strip_prefix("<?xml") {
+        Some(rest) => rest.split_once("?>").map_or(document, |(_, body)| body),
+        None => document,
+    }
+}
+
+/// Length of the start tag at the beginning of `element`
+fn start_tag_len(element: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in element.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    element.len()
+}
+
+/// Whether `start_tag` has an attribute named `name`
+fn has_attribute(start_tag: &str, name: &str) -> bool {
+    start_tag.match_indices(name).any(|(i, _)| {
+        start_tag[..i].ends_with(char::is_whitespace)
+            && start_tag[i + name.len()..].trim_start().starts_with('=')
+    })
+}
+
+/// Source text of `node`, declaring the namespaces it inherits from the
+/// envelope so that it stands on its own
+fn standalone(xml: &str, node: Node) -> String {
+    let element = &xml[node.range()];
+    let start_tag = &element[..start_tag_len(element)];
+    let name_end = start_tag
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(start_tag.len());
+    let mut declarations = String::new();
+    for ns in node.namespaces() {
+        let name = match ns.name() {
+            Some("xml") => continue,
+            Some(prefix) => format!("xmlns:{}", prefix),
+            None => "xmlns".to_string(),
+        };
+        if ns.uri().is_empty() || ns.uri() == SBDH_NS || has_attribute(start_tag, &name) {
+            continue;
+        }
+        let _ = write!(declarations, " {}=\"", name);
+        escape_attr(ns.uri(), &mut declarations);
+        declarations.push('"');
+    }
+    format!(
+        "{}{}{}",
+        &element[..name_end],
+        declarations,
+        &element[name_end..]
+    )
+}
+
+/// The document inside a StandardBusinessDocument; `None` if `xml` is not one
+pub fn payload(xml: &str) -> Option<String> {
+    if !xml.contains(SBDH_NS) {
+        return None;
+    }
+    let doc = Document::parse(xml).ok()?;
+    let root = doc.root_element();
+    if !root.has_tag_name((SBDH_NS, "StandardBusinessDocument")) {
+        return None;
+    }
+    root.children()
+        .find(|n| n.is_element() && !n.has_tag_name((SBDH_NS, "StandardBusinessDocumentHeader")))
+        .map(|body| standalone(xml, body))
+}
+
+/// Header and document of a StandardBusinessDocument
+pub fn unwrap(xml: &str) -> Result<(Sbdh, String)> {
+    let header = Sbdh::parse(xml)?;
+    let document =
+        payload(xml).ok_or_else(|| anyhow!("StandardBusinessDocument carries no document"))?;
+    Ok((header, document))
+}
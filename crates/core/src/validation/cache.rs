@@ -0,0 +1,67 @@
+//! Validation results stored by document hash
+//!
+//! Entries are keyed by the rule set fingerprint and the SHA-256 of the
+//! document, so a result is only reused for identical content validated with
+//! identical rules, code lists and profile. Signed documents are not cached:
+//! whether their signer certificates are valid depends on when they are
+//! validated, which the fingerprint cannot capture.
+
+use super::ValidationReport;
+use crate::parsing::compute_sha256_hex;
+use crate::xmldsig::DSIG_NS;
+use anyhow::Result;
+use std::path::Path;
+
+#[derive(Clone)]
+pub struct ValidationCache {
+    tree: sled::Tree,
+}
+
+impl ValidationCache {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let db = sled::open(path)?;
+        Ok(Self {
+            tree: db.open_tree("validation")?,
+        })
+    }
+
+    fn key(fingerprint: &str, xml: &str) -> String {
+        format!("{}:{}", fingerprint, compute_sha256_hex(xml))
+    }
+
+    fn cacheable(xml: &str) -> bool {
+        !xml.contains(DSIG_NS)
+    }
+
+    pub fn get(&self, fingerprint: &str, xml: &str) -> Option<ValidationReport> {
+        if !Self::cacheable(xml) {
+            return None;
+        }
+        let bytes = self.tree.get(Self::key(fingerprint, xml)).ok()??;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Store `report` for `xml`, unless `xml` is signed
+    pub fn insert(&self, fingerprint: &str, xml: &str, report: &ValidationReport) -> Result<()> {
+        if !Self::cacheable(xml) || !report.signatures.is_empty() {
+            return Ok(());
+        }
+        self.tree
+            .insert(Self::key(fingerprint, xml), serde_json::to_vec(report)?)?;
+        Ok(())
+    }
+
+    /// Remove results produced with any other rule set
+    pub fn retain_fingerprint(&self, fingerprint: &str) -> Result<usize> {
+        let prefix = format!("{}:", fingerprint);
+        let mut removed = 0;
+        for key in self.tree.iter().keys() {
+            let key = key?;
+            if !key.starts_with(prefix.as_bytes()) {
+                self.tree.remove(key)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,193 @@
+use super::registry::{BuiltinRule, Finding, Severity, ValidationContext};
+use crate::codelists::CodeList;
+
+/// Findings for every code in `codes` that is not in `list`
+fn not_in_list(
+    ctx: &ValidationContext,
+    list: CodeList,
+    codes: &[String],
+    location: &str,
+) -> Vec<Finding> {
+    codes
+        .iter()
+        .filter(|code| !ctx.code_lists.contains(list, code))
+        .map(|code| Finding::new().at(location).with_arg(code))
+        .collect()
+}
+
+// BR-CL-01: Invoice type code (UNCL1001)
+fn br_cl_01(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    let codes: Vec<String> = inv.invoice_type_code.iter().cloned().collect();
+    not_in_list(ctx, CodeList::InvoiceTypeCode, &codes, "InvoiceTypeCode")
+}
+
+// BR-CL-04: Invoice currency code (ISO 4217)
+fn br_cl_04(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    if inv.currency_code.is_empty() {
+        return Vec::new();
+    }
+    not_in_list(
+        ctx,
+        CodeList::Currency,
+        std::slice::from_ref(&inv.currency_code),
+        "DocumentCurrencyCode",
+    )
+}
+
+// BR-CL-14: Country codes (ISO 3166-1 alpha-2)
+fn br_cl_14(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    not_in_list(
+        ctx,
+        CodeList::Country,
+        &inv.country_codes,
+        "Country/IdentificationCode",
+    )
+}
+
+// BR-CL-16: Payment means code (UNCL4461)
+fn br_cl_16(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    not_in_list(
+        ctx,
+        CodeList::PaymentMeans,
+        &inv.payment_means_codes,
+        "PaymentMeans/PaymentMeansCode",
+    )
+}
+
+// BR-CL-19: Allowance reason code (UNCL5189)
+fn br_cl_19(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    not_in_list(
+        ctx,
+        CodeList::AllowanceReason,
+        &inv.allowance_reason_codes,
+        "AllowanceCharge/AllowanceChargeReasonCode",
+    )
+}
+
+// BR-CL-22: VAT exemption reason code (VATEX)
+fn br_cl_22(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    not_in_list(
+        ctx,
+        CodeList::VatExemptionReason,
+        &inv.vat_exemption_reason_codes,
+        "TaxCategory/TaxExemptionReasonCode",
+    )
+}
+
+// BR-CL-23: Unit of measure (UN/ECE Rec 20 and 21). A warning, since the
+// bundled list has only the codes in common use
+fn br_cl_23(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    not_in_list(
+        ctx,
+        CodeList::UnitOfMeasure,
+        &inv.unit_codes,
+        "InvoiceLine/InvoicedQuantity/@unitCode",
+    )
+}
+
+// BR-CL-25: Endpoint identifier scheme (EAS)
+fn br_cl_25(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for (scheme, location) in [
+        (
+            &inv.supplier_endpoint_scheme,
+            "AccountingSupplierParty/Party/EndpointID/@schemeID",
+        ),
+        (
+            &inv.customer_endpoint_scheme,
+            "AccountingCustomerParty/Party/EndpointID/@schemeID",
+        ),
+    ] {
+        let codes: Vec<String> = scheme.iter().cloned().collect();
+        out.extend(not_in_list(
+            ctx,
+            CodeList::ElectronicAddressScheme,
+            &codes,
+            location,
+        ));
+    }
+    out
+}
+
+pub(super) const RULES: &[BuiltinRule] = &[
+    BuiltinRule {
+        id: "BR-CL-01",
+        group: "codelists",
+        severity: Severity::Error,
+        message: "Invoice type code '{0}' is not in UNCL1001",
+        check: br_cl_01,
+    },
+    BuiltinRule {
+        id: "BR-CL-04",
+        group: "codelists",
+        severity: Severity::Error,
+        message: "Currency code '{0}' is not in ISO 4217",
+        check: br_cl_04,
+    },
+    BuiltinRule {
+        id: "BR-CL-14",
+        group: "codelists",
+        severity: Severity::Error,
+        message: "Country code '{0}' is not in ISO 3166-1",
+        check: br_cl_14,
+    },
+    BuiltinRule {
+        id: "BR-CL-16",
+        group: "codelists",
+        severity: Severity::Error,
+        message: "Payment means code '{0}' is not in UNCL4461",
+        check: br_cl_16,
+    },
+    BuiltinRule {
+        id: "BR-CL-19",
+        group: "codelists",
+        severity: Severity::Error,
+        message: "Allowance reason code '{0}' is not in UNCL5189",
+        check: br_cl_19,
+    },
+    BuiltinRule {
+        id: "BR-CL-22",
+        group: "codelists",
+        severity: Severity::Error,
+        message: "VAT exemption reason code '{0}' is not in VATEX",
+        check: br_cl_22,
+    },
+    BuiltinRule {
+        id: "BR-CL-23",
+        group: "codelists",
+        severity: Severity::Warning,
+        message: "Unit code '{0}' is not in UN/ECE Recommendation 20 or 21",
+        check: br_cl_23,
+    },
+    BuiltinRule {
+        id: "BR-CL-25",
+        group: "codelists",
+        severity: Severity::Error,
+        message: "Endpoint scheme '{0}' is not in the EAS code list",
+        check: br_cl_25,
+    },
+];
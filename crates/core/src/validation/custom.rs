@@ -0,0 +1,256 @@
+//! Declarative rules defined in configuration
+//!
+//! A rule names a field (a business term such as `BT-13`, or an element path
+//! below the root such as `OrderReference/ID`), a condition the field's values
+//! must satisfy and the message reported when they do not. Rules can apply
+//! only when another field has a given value, and only for some receivers.
+
+use super::registry::{Finding, Severity, ValidationContext, ValidationRule};
+use crate::parsing::get_texts_at_path;
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Element paths of the business terms that custom rules can refer to
+const BUSINESS_TERMS: &[(&str, &str)] = &[
+    ("BT-1", "ID"),
+    ("BT-2", "IssueDate"),
+    ("BT-3", "InvoiceTypeCode"),
+    ("BT-5", "DocumentCurrencyCode"),
+    ("BT-6", "TaxCurrencyCode"),
+    ("BT-9", "DueDate"),
+    ("BT-10", "BuyerReference"),
+    ("BT-11", "ProjectReference/ID"),
+    ("BT-12", "ContractDocumentReference/ID"),
+    ("BT-13", "OrderReference/ID"),
+    ("BT-14", "OrderReference/SalesOrderID"),
+    ("BT-19", "AccountingCost"),
+    ("BT-20", "PaymentTerms/Note"),
+    ("BT-22", "Note"),
+    (
+        "BT-27",
+        "AccountingSupplierParty/Party/PartyLegalEntity/RegistrationName",
+    ),
+    (
+        "BT-29",
+        "AccountingSupplierParty/Party/PartyIdentification/ID",
+    ),
+    (
+        "BT-30",
+        "AccountingSupplierParty/Party/PartyLegalEntity/CompanyID",
+    ),
+    (
+        "BT-31",
+        "AccountingSupplierParty/Party/PartyTaxScheme/CompanyID",
+    ),
+    ("BT-34", "AccountingSupplierParty/Party/EndpointID"),
+    (
+        "BT-44",
+        "AccountingCustomerParty/Party/PartyLegalEntity/RegistrationName",
+    ),
+    (
+        "BT-46",
+        "AccountingCustomerParty/Party/PartyIdentification/ID",
+    ),
+    (
+        "BT-47",
+        "AccountingCustomerParty/Party/PartyLegalEntity/CompanyID",
+    ),
+    (
+        "BT-48",
+        "AccountingCustomerParty/Party/PartyTaxScheme/CompanyID",
+    ),
+    ("BT-49", "AccountingCustomerParty/Party/EndpointID"),
+    ("BT-81", "PaymentMeans/PaymentMeansCode"),
+    ("BT-83", "PaymentMeans/PaymentID"),
+    ("BT-84", "PaymentMeans/PayeeFinancialAccount/ID"),
+    ("BT-106", "LegalMonetaryTotal/LineExtensionAmount"),
+    ("BT-109", "LegalMonetaryTotal/TaxExclusiveAmount"),
+    ("BT-110", "TaxTotal/TaxAmount"),
+    ("BT-112", "LegalMonetaryTotal/TaxInclusiveAmount"),
+    ("BT-115", "LegalMonetaryTotal/PayableAmount"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// The field must be present and non-empty
+    Required,
+    /// Every value must match the regular expression
+    Regex(String),
+    /// Every value must be one of the listed values
+    OneOf(Vec<String>),
+    /// Every value must be a number within the bounds
+    Range { min: Option<f64>, max: Option<f64> },
+}
+
+/// Restricts a rule to documents where another field is present, or has a
+/// given value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldCondition {
+    pub field: String,
+    #[serde(default)]
+    pub equals: Option<String>,
+}
+
+/// A custom rule as written in configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomRuleSpec {
+    pub id: String,
+    pub field: String,
+    pub condition: Condition,
+    /// Message shown on failure; `{0}` is replaced by the offending value
+    pub message: String,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    #[serde(default)]
+    pub when: Option<FieldCondition>,
+    /// Receiver identifiers (endpoint, `scheme:endpoint`, VAT or registration
+    /// number) the rule applies to; empty means all receivers
+    #[serde(default)]
+    pub receivers: Vec<String>,
+}
+
+fn default_severity() -> Severity {
+    Severity::Error
+}
+
+fn resolve_path(field: &str) -> Vec<String> {
+    let path = BUSINESS_TERMS
+        .iter()
+        .find(|(bt, _)| bt.eq_ignore_ascii_case(field.trim()))
+        .map(|(_, path)| *path)
+        .unwrap_or(field);
+    path.split('/')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn field_values(ctx: &ValidationContext, path: &[String]) -> Vec<String> {
+    let Some(doc) = ctx.doc else {
+        return Vec::new();
+    };
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+    get_texts_at_path(doc, &path)
+}
+
+/// A custom rule ready to run
+pub struct CustomRule {
+    spec: CustomRuleSpec,
+    path: Vec<String>,
+    location: String,
+    when_path: Option<Vec<String>>,
+    regex: Option<Regex>,
+}
+
+impl CustomRule {
+    pub fn new(spec: CustomRuleSpec) -> Result<Self> {
+        if spec.id.trim().is_empty() {
+            return Err(anyhow!("Custom rule without id"));
+        }
+        let regex = match &spec.condition {
+            Condition::Regex(pattern) => Some(
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid pattern in custom rule {}", spec.id))?,
+            ),
+            _ => None,
+        };
+        let path = resolve_path(&spec.field);
+        if path.is_empty() {
+            return Err(anyhow!("Custom rule {} has an empty field", spec.id));
+        }
+        Ok(Self {
+            location: path.join("/"),
+            path,
+            when_path: spec.when.as_ref().map(|w| resolve_path(&w.field)),
+            regex,
+            spec,
+        })
+    }
+
+    fn applies_to_receiver(&self, ctx: &ValidationContext) -> bool {
+        if self.spec.receivers.is_empty() {
+            return true;
+        }
+        let Some(inv) = ctx.invoice else {
+            return false;
+        };
+        let mut ids: Vec<String> = [
+            &inv.customer_id,
+            &inv.customer_vat_id,
+            &inv.customer_legal_id,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        if let (Some(scheme), Some(id)) = (&inv.customer_endpoint_scheme, &inv.customer_id) {
+            ids.push(format!("{}:{}", scheme, id));
+        }
+        self.spec
+            .receivers
+            .iter()
+            .any(|r| ids.iter().any(|id| id.eq_ignore_ascii_case(r.trim())))
+    }
+
+    fn applies_when(&self, ctx: &ValidationContext) -> bool {
+        let (Some(when), Some(path)) = (&self.spec.when, &self.when_path) else {
+            return true;
+        };
+        let values = field_values(ctx, path);
+        match &when.equals {
+            Some(expected) => values.iter().any(|v| v == expected),
+            None => !values.is_empty(),
+        }
+    }
+
+    fn value_ok(&self, value: &str) -> bool {
+        match &self.spec.condition {
+            Condition::Required => true,
+            Condition::Regex(_) => self.regex.as_ref().is_some_and(|re| re.is_match(value)),
+            Condition::OneOf(allowed) => allowed.iter().any(|a| a == value),
+            Condition::Range { min, max } => match value.parse::<f64>() {
+                Ok(n) => min.is_none_or(|m| n >= m) && max.is_none_or(|m| n <= m),
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+impl ValidationRule for CustomRule {
+    fn id(&self) -> &str {
+        &self.spec.id
+    }
+
+    fn group(&self) -> &str {
+        "custom"
+    }
+
+    fn message(&self) -> &str {
+        &self.spec.message
+    }
+
+    fn default_severity(&self) -> Severity {
+        self.spec.severity
+    }
+
+    fn check(&self, ctx: &ValidationContext) -> Vec<Finding> {
+        if ctx.doc.is_none() || !self.applies_to_receiver(ctx) || !self.applies_when(ctx) {
+            return Vec::new();
+        }
+        let values = field_values(ctx, &self.path);
+        if values.is_empty() {
+            return match self.spec.condition {
+                Condition::Required => vec![Finding::new().at(&self.location).with_arg("")],
+                _ => Vec::new(),
+            };
+        }
+        values
+            .iter()
+            .filter(|v| !self.value_ok(v))
+            .map(|v| Finding::new().at(&self.location).with_arg(v))
+            .collect()
+    }
+}
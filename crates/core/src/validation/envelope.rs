@@ -0,0 +1,112 @@
+//! Checks on the SBDH a document arrived in
+//!
+//! The header must be readable and address the same parties and document
+//! type as the document it carries. Documents without an SBDH pass.
+
+use super::registry::{BuiltinRule, Finding, Severity, ValidationContext};
+use crate::sbdh::{document_info, Identifier, Sbdh};
+
+fn header<'a>(ctx: &ValidationContext<'a>) -> Option<&'a Sbdh> {
+    ctx.sbdh.and_then(Result::ok)
+}
+
+/// Finding when the header's participant differs from the document's endpoint
+fn participant_mismatch(
+    actual: &Identifier,
+    scheme: Option<&String>,
+    id: Option<&String>,
+    location: &str,
+) -> Vec<Finding> {
+    let (Some(scheme), Some(id)) = (scheme, id) else {
+        return Vec::new();
+    };
+    let expected = Identifier::participant(scheme, id);
+    if actual.matches(&expected) {
+        return Vec::new();
+    }
+    vec![Finding::new()
+        .at(location)
+        .with_arg(actual.to_string())
+        .with_arg(expected.to_string())]
+}
+
+// SBDH-01: The header must be complete and well-formed
+fn sbdh_01(ctx: &ValidationContext) -> Vec<Finding> {
+    match ctx.sbdh {
+        Some(Err(error)) => vec![Finding::new()
+            .at("StandardBusinessDocumentHeader")
+            .with_arg(error.to_string())],
+        _ => Vec::new(),
+    }
+}
+
+// SBDH-02: The sender must be the seller's electronic address
+fn sbdh_02(ctx: &ValidationContext) -> Vec<Finding> {
+    let (Some(header), Some(inv)) = (header(ctx), ctx.invoice) else {
+        return Vec::new();
+    };
+    participant_mismatch(
+        &header.sender,
+        inv.supplier_endpoint_scheme.as_ref(),
+        inv.supplier_id.as_ref(),
+        "StandardBusinessDocumentHeader/Sender",
+    )
+}
+
+// SBDH-03: The receiver must be the buyer's electronic address
+fn sbdh_03(ctx: &ValidationContext) -> Vec<Finding> {
+    let (Some(header), Some(inv)) = (header(ctx), ctx.invoice) else {
+        return Vec::new();
+    };
+    participant_mismatch(
+        &header.receiver,
+        inv.customer_endpoint_scheme.as_ref(),
+        inv.customer_id.as_ref(),
+        "StandardBusinessDocumentHeader/Receiver",
+    )
+}
+
+// SBDH-04: The DOCUMENTID scope must identify the carried document
+fn sbdh_04(ctx: &ValidationContext) -> Vec<Finding> {
+    let (Some(header), Some(doc)) = (header(ctx), ctx.doc) else {
+        return Vec::new();
+    };
+    match document_info(doc) {
+        Ok(info) if !header.document_id.matches(&info.document_id) => vec![Finding::new()
+            .at("StandardBusinessDocumentHeader/BusinessScope")
+            .with_arg(header.document_id.value.clone())
+            .with_arg(info.document_id.value)],
+        _ => Vec::new(),
+    }
+}
+
+pub(super) const RULES: &[BuiltinRule] = &[
+    BuiltinRule {
+        id: "SBDH-01",
+        group: "sbdh",
+        severity: Severity::Error,
+        message: "Invalid SBDH: {0}",
+        check: sbdh_01,
+    },
+    BuiltinRule {
+        id: "SBDH-02",
+        group: "sbdh",
+        severity: Severity::Error,
+        message: "SBDH sender {0} does not match the seller electronic address {1}",
+        check: sbdh_02,
+    },
+    BuiltinRule {
+        id: "SBDH-03",
+        group: "sbdh",
+        severity: Severity::Error,
+        message: "SBDH receiver {0} does not match the buyer electronic address {1}",
+        check: sbdh_03,
+    },
+    BuiltinRule {
+        id: "SBDH-04",
+        group: "sbdh",
+        severity: Severity::Error,
+        message: "SBDH document type {0} does not match the document ({1})",
+        check: sbdh_04,
+    },
+];
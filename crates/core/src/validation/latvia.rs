@@ -0,0 +1,329 @@
+//! Latvian national rules
+//!
+//! Checks LV VAT numbers, Uzņēmumu reģistrs registration numbers, personal
+//! codes (personas kods) of private buyers, the Latvian VAT rates and the
+//! format of e-adrese / PEPPOL endpoints.
+
+use super::registry::{BuiltinRule, Finding, Severity, ValidationContext};
+
+/// VAT rates applicable in Latvia (standard, reduced and zero rated)
+const LV_VAT_RATES: [f64; 4] = [21.0, 12.0, 5.0, 0.0];
+
+/// EAS code for the Latvian unified registration number
+const EAS_LV_REGISTRATION: &str = "0218";
+/// EAS code for the Latvian VAT number
+const EAS_LV_VAT: &str = "9939";
+
+fn digits(value: &str) -> Option<Vec<u32>> {
+    value.chars().map(|c| c.to_digit(10)).collect()
+}
+
+/// Check a Latvian VAT number: `LV` followed by 11 digits that form a valid
+/// registration number or personal code.
+pub fn is_valid_vat_number(value: &str) -> bool {
+    let value = value.trim();
+    match value.strip_prefix("LV") {
+        Some(number) => is_valid_registration_number(number) || is_valid_personal_code(number),
+        None => false,
+    }
+}
+
+/// Check a legal entity registration number (11 digits, first digit 4-9,
+/// weighted modulo 11 checksum).
+pub fn is_valid_registration_number(value: &str) -> bool {
+    const WEIGHTS: [u32; 11] = [9, 1, 4, 8, 3, 10, 2, 5, 7, 6, 1];
+
+    let d = match digits(value.trim()) {
+        Some(d) if d.len() == 11 => d,
+        _ => return false,
+    };
+    if d[0] <= 3 {
+        return false;
+    }
+    let sum: u32 = d.iter().zip(WEIGHTS).map(|(n, w)| n * w).sum();
+    sum % 11 == 3
+}
+
+/// Check a personal code (personas kods), with or without the dash after the
+/// birth date. Codes starting with `32` (issued since July 2017) carry no
+/// birth date or check digit and are only checked for length.
+pub fn is_valid_personal_code(value: &str) -> bool {
+    const WEIGHTS: [u32; 10] = [10, 5, 8, 4, 2, 1, 6, 3, 7, 9];
+
+    let compact: String = value.trim().chars().filter(|c| *c != '-').collect();
+    let d = match digits(&compact) {
+        Some(d) if d.len() == 11 => d,
+        _ => return false,
+    };
+    if d[0] > 3 {
+        return false;
+    }
+    if d[0] == 3 && d[1] == 2 {
+        return true;
+    }
+
+    let day = d[0] * 10 + d[1];
+    let month = d[2] * 10 + d[3];
+    let year = d[4] * 10 + d[5];
+    let century = match d[6] {
+        0 => 1800,
+        1 => 1900,
+        2 => 2000,
+        _ => return false,
+    };
+    if chrono::NaiveDate::from_ymd_opt((century + year) as i32, month, day).is_none() {
+        return false;
+    }
+
+    let sum: u32 = d[..10].iter().zip(WEIGHTS).map(|(n, w)| n * w).sum();
+    (1 + sum) % 11 % 10 == d[10]
+}
+
+/// Check an e-adrese identifier such as `_DEFAULT@40003000000` or
+/// `_PRIVATE@010190-12345`.
+pub fn is_valid_eadrese(value: &str) -> bool {
+    let Some((mailbox, owner)) = value.trim().split_once('@') else {
+        return false;
+    };
+    let mailbox_ok = mailbox.len() > 1
+        && mailbox.starts_with('_')
+        && mailbox[1..]
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    mailbox_ok && (is_valid_registration_number(owner) || is_valid_personal_code(owner))
+}
+
+fn looks_like_personal_code(value: &str) -> bool {
+    let compact: String = value.trim().chars().filter(|c| *c != '-').collect();
+    compact.len() == 11
+        && compact.chars().all(|c| c.is_ascii_digit())
+        && compact.starts_with(['0', '1', '2', '3'])
+}
+
+const SELLER_VAT: &str = "AccountingSupplierParty/Party/PartyTaxScheme/CompanyID";
+const BUYER_VAT: &str = "AccountingCustomerParty/Party/PartyTaxScheme/CompanyID";
+const SELLER_LEGAL_ID: &str = "AccountingSupplierParty/Party/PartyLegalEntity/CompanyID";
+const BUYER_LEGAL_ID: &str = "AccountingCustomerParty/Party/PartyLegalEntity/CompanyID";
+const SELLER_ENDPOINT: &str = "AccountingSupplierParty/Party/EndpointID";
+const BUYER_ENDPOINT: &str = "AccountingCustomerParty/Party/EndpointID";
+
+/// LV-prefixed VAT identifiers of seller and buyer with their locations
+fn lv_vat_ids<'a>(ctx: &ValidationContext<'a>) -> Vec<(&'a str, &'static str)> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    [
+        (&inv.supplier_vat_id, SELLER_VAT),
+        (&inv.customer_vat_id, BUYER_VAT),
+    ]
+    .into_iter()
+    .filter_map(|(id, location)| id.as_deref().map(|id| (id, location)))
+    .filter(|(id, _)| id.starts_with("LV"))
+    .collect()
+}
+
+fn has_lv_vat_format(vat_id: &str) -> bool {
+    let number = &vat_id[2..];
+    number.len() == 11 && number.chars().all(|c| c.is_ascii_digit())
+}
+
+// LV-01: LV VAT identifier format
+fn lv_01(ctx: &ValidationContext) -> Vec<Finding> {
+    lv_vat_ids(ctx)
+        .into_iter()
+        .filter(|(id, _)| !has_lv_vat_format(id))
+        .map(|(id, location)| Finding::new().at(location).with_arg(id))
+        .collect()
+}
+
+// LV-02: LV VAT identifier checksum
+fn lv_02(ctx: &ValidationContext) -> Vec<Finding> {
+    lv_vat_ids(ctx)
+        .into_iter()
+        .filter(|(id, _)| has_lv_vat_format(id) && !is_valid_vat_number(id))
+        .map(|(id, location)| Finding::new().at(location).with_arg(id))
+        .collect()
+}
+
+/// Whether a party's registration number is a Latvian one: the party is in
+/// Latvia or names the Latvian register's scheme
+fn is_latvian_party(country: &Option<String>, scheme: &Option<String>) -> bool {
+    country.as_deref() == Some("LV") || scheme.as_deref() == Some(EAS_LV_REGISTRATION)
+}
+
+// LV-03: Registration numbers of Latvian sellers, and of Latvian buyers unless
+// a private person
+fn lv_03(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    if let Some(reg_no) = &inv.supplier_legal_id {
+        if is_latvian_party(&inv.supplier_country, &inv.supplier_legal_id_scheme)
+            && !is_valid_registration_number(reg_no)
+        {
+            out.push(Finding::new().at(SELLER_LEGAL_ID).with_arg(reg_no));
+        }
+    }
+    if let Some(reg_no) = &inv.customer_legal_id {
+        if is_latvian_party(&inv.customer_country, &inv.customer_legal_id_scheme)
+            && !looks_like_personal_code(reg_no)
+            && reg_no.chars().all(|c| c.is_ascii_digit())
+            && !is_valid_registration_number(reg_no)
+        {
+            out.push(Finding::new().at(BUYER_LEGAL_ID).with_arg(reg_no));
+        }
+    }
+    out
+}
+
+// LV-04: Personal code of a private person buyer
+fn lv_04(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    match &inv.customer_legal_id {
+        Some(code) if looks_like_personal_code(code) && !is_valid_personal_code(code) => {
+            vec![Finding::new().at(BUYER_LEGAL_ID).with_arg(code)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+// LV-05: VAT rates
+fn lv_05(ctx: &ValidationContext) -> Vec<Finding> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    let mut reported: Vec<&str> = Vec::new();
+    let mut out = Vec::new();
+    for rate in &inv.vat_rates {
+        if reported.contains(&rate.as_str()) {
+            continue;
+        }
+        reported.push(rate);
+        let known = rate
+            .parse::<f64>()
+            .map(|r| LV_VAT_RATES.iter().any(|lv| (lv - r).abs() < f64::EPSILON))
+            .unwrap_or(false);
+        if !known {
+            out.push(Finding::new().at("TaxCategory/Percent").with_arg(rate));
+        }
+    }
+    out
+}
+
+/// Endpoints of seller and buyer with their scheme and location
+fn endpoints<'a>(ctx: &ValidationContext<'a>) -> Vec<(&'a str, Option<&'a str>, &'static str)> {
+    let Some(inv) = ctx.invoice else {
+        return Vec::new();
+    };
+    [
+        (
+            &inv.supplier_id,
+            &inv.supplier_endpoint_scheme,
+            SELLER_ENDPOINT,
+        ),
+        (
+            &inv.customer_id,
+            &inv.customer_endpoint_scheme,
+            BUYER_ENDPOINT,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(id, scheme, location)| id.as_deref().map(|id| (id, scheme.as_deref(), location)))
+    .collect()
+}
+
+// LV-06: e-adrese endpoints
+fn lv_06(ctx: &ValidationContext) -> Vec<Finding> {
+    endpoints(ctx)
+        .into_iter()
+        .filter(|(id, scheme, _)| {
+            !matches!(*scheme, Some(EAS_LV_REGISTRATION) | Some(EAS_LV_VAT))
+                && id.contains('@')
+                && !is_valid_eadrese(id)
+        })
+        .map(|(id, _, location)| Finding::new().at(location).with_arg(id))
+        .collect()
+}
+
+// LV-07: Endpoints with the registration number scheme
+fn lv_07(ctx: &ValidationContext) -> Vec<Finding> {
+    endpoints(ctx)
+        .into_iter()
+        .filter(|(id, scheme, _)| {
+            *scheme == Some(EAS_LV_REGISTRATION) && !is_valid_registration_number(id)
+        })
+        .map(|(id, _, location)| Finding::new().at(location).with_arg(id))
+        .collect()
+}
+
+// LV-08: Endpoints with the LV VAT scheme
+fn lv_08(ctx: &ValidationContext) -> Vec<Finding> {
+    endpoints(ctx)
+        .into_iter()
+        .filter(|(id, scheme, _)| *scheme == Some(EAS_LV_VAT) && !is_valid_vat_number(id))
+        .map(|(id, _, location)| Finding::new().at(location).with_arg(id))
+        .collect()
+}
+
+pub(super) const RULES: &[BuiltinRule] = &[
+    BuiltinRule {
+        id: "LV-01",
+        group: "lv",
+        severity: Severity::Error,
+        message: "VAT number '{0}' must be LV followed by 11 digits",
+        check: lv_01,
+    },
+    BuiltinRule {
+        id: "LV-02",
+        group: "lv",
+        severity: Severity::Error,
+        message: "VAT number '{0}' has an invalid checksum",
+        check: lv_02,
+    },
+    BuiltinRule {
+        id: "LV-03",
+        group: "lv",
+        severity: Severity::Error,
+        message: "Registration number '{0}' must be 11 digits with a valid checksum",
+        check: lv_03,
+    },
+    BuiltinRule {
+        id: "LV-04",
+        group: "lv",
+        severity: Severity::Error,
+        message: "Personal code '{0}' has an invalid birth date or checksum",
+        check: lv_04,
+    },
+    BuiltinRule {
+        id: "LV-05",
+        group: "lv",
+        severity: Severity::Error,
+        message: "VAT rate {0}% is not a Latvian VAT rate (21, 12, 5 or 0)",
+        check: lv_05,
+    },
+    BuiltinRule {
+        id: "LV-06",
+        group: "lv",
+        severity: Severity::Error,
+        message:
+            "e-adrese '{0}' must have the form _MAILBOX@<registration number or personal code>",
+        check: lv_06,
+    },
+    BuiltinRule {
+        id: "LV-07",
+        group: "lv",
+        severity: Severity::Error,
+        message: "Endpoint '{0}' (scheme 0218) is not a valid registration number",
+        check: lv_07,
+    },
+    BuiltinRule {
+        id: "LV-08",
+        group: "lv",
+        severity: Severity::Error,
+        message: "Endpoint '{0}' (scheme 9939) is not a valid LV VAT number",
+        check: lv_08,
+    },
+];
@@ -0,0 +1,80 @@
+//! Translated validation messages
+//!
+//! Catalogs map rule IDs to message templates (`RULE-ID = message`, `{0}` for
+//! the offending value). Rules missing from the catalog of the selected
+//! language, such as company-specific ones, keep their own message. The
+//! catalogs also hold the headings of the exported reports (`REPORT-*`).
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Lv,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::En, Language::Lv];
+
+    /// Text of the catalog bundled for this language
+    pub(super) fn catalog(&self) -> &'static str {
+        match self {
+            Language::En => include_str!("../../messages/en.txt"),
+            Language::Lv => include_str!("../../messages/lv.txt"),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::En => write!(f, "en"),
+            Language::Lv => write!(f, "lv"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MessageCatalog {
+    messages: HashMap<String, String>,
+}
+
+impl MessageCatalog {
+    pub fn parse(text: &str) -> Self {
+        let messages = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|l| l.split_once('='))
+            .map(|(id, message)| (id.trim().to_string(), message.trim().to_string()))
+            .collect();
+        Self { messages }
+    }
+
+    /// Catalog bundled with this build
+    pub fn bundled(language: Language) -> &'static MessageCatalog {
+        static CATALOGS: OnceLock<HashMap<Language, MessageCatalog>> = OnceLock::new();
+        &CATALOGS.get_or_init(|| {
+            Language::ALL
+                .iter()
+                .map(|lang| (*lang, Self::parse(lang.catalog())))
+                .collect()
+        })[&language]
+    }
+
+    pub fn get(&self, rule_id: &str) -> Option<&str> {
+        self.messages.get(rule_id).map(String::as_str)
+    }
+}
+
+/// Message template for `rule_id` in `language`, or `fallback`
+pub fn localized<'a>(language: Language, rule_id: &str, fallback: &'a str) -> &'a str {
+    MessageCatalog::bundled(language)
+        .get(rule_id)
+        .unwrap_or(fallback)
+}
@@ -1,17 +1,234 @@
+mod cache;
+mod codes;
+mod custom;
+mod envelope;
+mod latvia;
+mod messages;
+mod profile;
+mod registry;
+mod report;
 mod rules;
+mod signature;
 mod xsd;
 
-pub fn validate(xml: &str) -> Result<(), Vec<String>> {
-    let mut errs = Vec::new();
-    if let Err(e) = xsd::validate_against_xsd(xml) {
-        errs.push(e);
+use crate::codelists;
+use crate::parsing::parse_ubl_invoice;
+use crate::sbdh::{self, Sbdh};
+use crate::xmldsig::{self, SignatureVerification};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
+
+pub use cache::ValidationCache;
+pub use custom::{Condition, CustomRule, CustomRuleSpec, FieldCondition};
+pub use latvia::{
+    is_valid_eadrese, is_valid_personal_code, is_valid_registration_number, is_valid_vat_number,
+};
+pub use messages::{localized, Language, MessageCatalog};
+pub use profile::{rule_pack_group, ValidationProfile, INTERNAL_STRICT, LV_B2G, PEPPOL_BIS3};
+pub use registry::{
+    render_message, BuiltinRule, Finding, RuleRegistry, Severity, ValidationContext,
+    ValidationIssue, ValidationRule,
+};
+pub use report::{to_html, to_json, to_svrl, write_reports, ExportFormat, FileReport};
+pub(crate) use rules::has_excess_decimals;
+
+/// Outcome of validating one document against a profile
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub profile: String,
+    pub issues: Vec<ValidationIssue>,
+    #[serde(default)]
+    pub signatures: Vec<SignatureVerification>,
+}
+
+impl ValidationReport {
+    /// A document is valid when no issue has error severity
+    pub fn is_valid(&self) -> bool {
+        !self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+}
+
+impl RuleRegistry {
+    /// Run every rule selected by `profile` against `xml`, with messages in
+    /// `language`
+    pub fn validate(
+        &self,
+        xml: &str,
+        profile: &ValidationProfile,
+        language: Language,
+    ) -> ValidationReport {
+        // A document in an SBDH is validated by its content, plus the header
+        let payload = sbdh::payload(xml);
+        let header: Option<Result<Sbdh, String>> = payload
+            .as_ref()
+            .map(|_| Sbdh::parse(xml).map_err(|e| e.to_string()));
+        let xml = payload.as_deref().unwrap_or(xml);
+
+        // Invoice rules only run on documents with an Invoice root
+        let invoice = if rules::has_invoice_root(xml) {
+            parse_ubl_invoice(xml).ok()
+        } else {
+            None
+        };
+        let doc = roxmltree::Document::parse(xml).ok();
+        let code_lists = codelists::current();
+        let trust = xmldsig::trust::current();
+        let signatures = if xml.contains(xmldsig::DSIG_NS) {
+            xmldsig::verify_signatures(xml, &trust).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let ctx = ValidationContext {
+            xml,
+            doc: doc.as_ref(),
+            invoice: invoice.as_ref(),
+            code_lists: &code_lists,
+            signatures: &signatures,
+            trust: &trust,
+            sbdh: header.as_ref().map(|h| h.as_ref().map_err(String::as_str)),
+        };
+
+        let mut issues = Vec::new();
+        for rule in self.rules() {
+            if !profile.includes(rule.id(), rule.group()) {
+                continue;
+            }
+            for finding in rule.check(&ctx) {
+                let default = finding.severity.unwrap_or(rule.default_severity());
+                issues.push(ValidationIssue {
+                    rule_id: rule.id().to_string(),
+                    severity: profile.severity_for(rule.id(), default),
+                    message: render_message(
+                        localized(language, rule.id(), rule.message()),
+                        &finding.args,
+                    ),
+                    location: finding.location,
+                    args: finding.args,
+                });
+            }
+        }
+
+        ValidationReport {
+            profile: profile.name.clone(),
+            issues,
+            signatures,
+        }
     }
-    if let Err(mut re) = rules::basic_en16931_checks(xml) {
-        errs.append(&mut re);
+}
+
+static REGISTRY: RwLock<Option<Arc<RuleRegistry>>> = RwLock::new(None);
+
+/// Rules used by `validate`; the built-in rules plus any registered ones
+pub fn registry() -> Arc<RuleRegistry> {
+    if let Some(registry) = REGISTRY.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return Arc::clone(registry);
     }
-    if errs.is_empty() {
+    let mut slot = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(slot.get_or_insert_with(|| Arc::new(RuleRegistry::with_builtin_rules())))
+}
+
+/// Add a rule to the registry used by `validate`, e.g. a company-specific check
+pub fn register_rule(rule: Arc<dyn ValidationRule>) {
+    let mut registry = (*registry()).clone();
+    registry.register(rule);
+    install_registry(registry);
+}
+
+/// Replace the registry used by `validate`
+pub fn install_registry(registry: RuleRegistry) {
+    *REGISTRY.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(registry));
+}
+
+struct InstalledCustomRules {
+    rules: Vec<Arc<dyn ValidationRule>>,
+    /// Serialized specs, part of the rule set fingerprint
+    specs: String,
+}
+
+static CUSTOM_RULES: RwLock<Option<InstalledCustomRules>> = RwLock::new(None);
+
+/// Replace the declarative rules that `validate` runs alongside the registry
+pub fn install_custom_rules(specs: Vec<CustomRuleSpec>) -> Result<()> {
+    let serialized = serde_json::to_string(&specs)?;
+    let rules = specs
+        .into_iter()
+        .map(|spec| CustomRule::new(spec).map(|r| Arc::new(r) as Arc<dyn ValidationRule>))
+        .collect::<Result<Vec<_>>>()?;
+    *CUSTOM_RULES.write().unwrap_or_else(|e| e.into_inner()) = Some(InstalledCustomRules {
+        rules,
+        specs: serialized,
+    });
+    Ok(())
+}
+
+/// Version of what the built-in rules check; bump it when a check changes
+/// without its ID, severity or message changing
+pub const RULESET_VERSION: u32 = 1;
+
+/// Identifies the rules, messages, code lists, trust anchors and profile a
+/// report was produced with, so stored results can be discarded when any of
+/// them change
+pub fn ruleset_fingerprint(profile: &ValidationProfile, language: Language) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(RULESET_VERSION.to_be_bytes());
+    hasher.update(language.catalog().as_bytes());
+    for rule in registry().rules() {
+        hasher.update(
+            format!(
+                "\n{}\0{}\0{}\0{}",
+                rule.id(),
+                rule.group(),
+                rule.default_severity(),
+                rule.message()
+            )
+            .as_bytes(),
+        );
+    }
+    if let Some(custom) = CUSTOM_RULES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+    {
+        hasher.update(custom.specs.as_bytes());
+    }
+    hasher.update(codelists::current().fingerprint().as_bytes());
+    hasher.update(xmldsig::trust::current().fingerprint().as_bytes());
+    hasher.update(
+        serde_json::to_string(profile)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    hex::encode(hasher.finalize())
+}
+
+pub fn validate_with_profile(
+    xml: &str,
+    profile: &ValidationProfile,
+    language: Language,
+) -> ValidationReport {
+    let custom = CUSTOM_RULES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|c| c.rules.clone())
+        .unwrap_or_default();
+    if custom.is_empty() {
+        return registry().validate(xml, profile, language);
+    }
+    let mut registry = (*registry()).clone();
+    for rule in custom {
+        registry.register(rule);
+    }
+    registry.validate(xml, profile, language)
+}
+
+pub fn validate(xml: &str) -> Result<(), Vec<String>> {
+    let report = validate_with_profile(xml, &ValidationProfile::default(), Language::default());
+    if report.is_valid() {
         Ok(())
     } else {
-        Err(errs)
+        Err(report.issues.iter().map(ToString::to_string).collect())
     }
 }
@@ -0,0 +1,137 @@
+use super::registry::Severity;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const PEPPOL_BIS3: &str = "peppol-bis3";
+pub const LV_B2G: &str = "lv-b2g";
+pub const INTERNAL_STRICT: &str = "internal-strict";
+
+/// Rule group of the national rule pack named `pack`, e.g. "lv" for "LV" or
+/// "Latvia"
+pub fn rule_pack_group(pack: &str) -> Result<&'static str> {
+    match pack.trim().to_lowercase().as_str() {
+        "lv" | "latvia" => Ok("lv"),
+        _ => bail!("Unknown rule pack '{}'; expected \"lv\"", pack.trim()),
+    }
+}
+
+/// A named selection of rules with per-rule adjustments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationProfile {
+    pub name: String,
+    /// Rule groups included in the profile
+    pub groups: Vec<String>,
+    /// Individual rules included in addition to the groups
+    #[serde(default)]
+    pub rules: Vec<String>,
+    /// Rules excluded even if their group is included
+    #[serde(default)]
+    pub disabled_rules: Vec<String>,
+    #[serde(default)]
+    pub severity_overrides: BTreeMap<String, Severity>,
+    /// Report warnings as errors
+    #[serde(default)]
+    pub warnings_as_errors: bool,
+}
+
+impl ValidationProfile {
+    fn with_groups(name: &str, groups: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            groups: groups.iter().map(|g| g.to_string()).collect(),
+            rules: Vec::new(),
+            disabled_rules: Vec::new(),
+            severity_overrides: BTreeMap::new(),
+            warnings_as_errors: false,
+        }
+    }
+
+    /// UBL structure, EN16931 core rules, PEPPOL code lists, signatures, SBDH
+    /// headers and custom rules
+    pub fn peppol_bis3() -> Self {
+        Self::with_groups(
+            PEPPOL_BIS3,
+            &["ubl", "en16931", "codelists", "signature", "sbdh", "custom"],
+        )
+    }
+
+    /// PEPPOL BIS 3 plus the Latvian national rules
+    pub fn lv_b2g() -> Self {
+        Self::with_groups(
+            LV_B2G,
+            &[
+                "ubl",
+                "en16931",
+                "codelists",
+                "lv",
+                "signature",
+                "sbdh",
+                "custom",
+            ],
+        )
+    }
+
+    /// Every rule group, with warnings treated as errors
+    pub fn internal_strict() -> Self {
+        let mut profile = Self::with_groups(
+            INTERNAL_STRICT,
+            &[
+                "ubl",
+                "en16931",
+                "codelists",
+                "lv",
+                "signature",
+                "sbdh",
+                "custom",
+            ],
+        );
+        profile.warnings_as_errors = true;
+        profile
+    }
+
+    /// Look up one of the profiles shipped with this crate
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            PEPPOL_BIS3 => Some(Self::peppol_bis3()),
+            LV_B2G => Some(Self::lv_b2g()),
+            INTERNAL_STRICT => Some(Self::internal_strict()),
+            _ => None,
+        }
+    }
+
+    /// Add the rules of the national rule pack `pack`
+    pub fn with_rule_pack(mut self, pack: &str) -> Result<Self> {
+        let group = rule_pack_group(pack)?;
+        if !self.groups.iter().any(|g| g == group) {
+            self.groups.push(group.to_string());
+        }
+        Ok(self)
+    }
+
+    pub fn includes(&self, rule_id: &str, group: &str) -> bool {
+        if self.disabled_rules.iter().any(|r| r == rule_id) {
+            return false;
+        }
+        self.groups.iter().any(|g| g == group) || self.rules.iter().any(|r| r == rule_id)
+    }
+
+    pub fn severity_for(&self, rule_id: &str, default: Severity) -> Severity {
+        let severity = self
+            .severity_overrides
+            .get(rule_id)
+            .copied()
+            .unwrap_or(default);
+        if self.warnings_as_errors && severity == Severity::Warning {
+            Severity::Error
+        } else {
+            severity
+        }
+    }
+}
+
+impl Default for ValidationProfile {
+    fn default() -> Self {
+        Self::peppol_bis3()
+    }
+}
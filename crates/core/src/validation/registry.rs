@@ -0,0 +1,207 @@
+use super::messages::{Language, MessageCatalog};
+use crate::codelists::CodeLists;
+use crate::parsing::UBLInvoice;
+use crate::sbdh::Sbdh;
+use crate::xmldsig::trust::TrustStore;
+use crate::xmldsig::SignatureVerification;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Everything a rule may look at while checking a document
+pub struct ValidationContext<'a> {
+    pub xml: &'a str,
+    /// Parsed XML tree, `None` if the document is not well-formed
+    pub doc: Option<&'a roxmltree::Document<'a>>,
+    /// Parsed invoice, `None` if the document could not be parsed
+    pub invoice: Option<&'a UBLInvoice>,
+    pub code_lists: &'a CodeLists,
+    /// Verification results of the XML signatures in the document
+    pub signatures: &'a [SignatureVerification],
+    /// Trust anchors the signatures were verified against
+    pub trust: &'a TrustStore,
+    /// Header of the SBDH the document arrived in (or why it could not be
+    /// read); `xml` and `doc` are then the document inside it
+    pub sbdh: Option<Result<&'a Sbdh, &'a str>>,
+}
+
+/// A single rule violation; the rule's message template is filled in with
+/// `args` (`{0}`, `{1}`, ...)
+#[derive(Debug, Clone, Default)]
+pub struct Finding {
+    pub location: Option<String>,
+    pub args: Vec<String>,
+    /// Replaces the rule's default severity for this finding
+    pub severity: Option<Severity>,
+}
+
+impl Finding {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn at(mut self, location: &str) -> Self {
+        self.location = Some(location.to_string());
+        self
+    }
+
+    pub fn with_arg(mut self, arg: impl fmt::Display) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub rule_id: String,
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<String>,
+    /// Values filled into the message, kept to render it in another language
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl ValidationIssue {
+    /// The message in `language`; issues without args that need them keep
+    /// the message they were reported with
+    pub fn message_in(&self, language: Language) -> String {
+        match MessageCatalog::bundled(language).get(&self.rule_id) {
+            Some(template) if !self.args.is_empty() || !template.contains("{0}") => {
+                render_message(template, &self.args)
+            }
+            _ => self.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule_id, self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " ({})", location)?;
+        }
+        Ok(())
+    }
+}
+
+pub trait ValidationRule: Send + Sync {
+    /// Unique rule identifier, e.g. "BT-1" or "LV-03"
+    fn id(&self) -> &str;
+    /// Group used by profiles to select rules, e.g. "en16931" or "lv"
+    fn group(&self) -> &str;
+    /// Message template, `{0}`, `{1}`, ... are replaced by the finding's args
+    fn message(&self) -> &str;
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+    fn check(&self, ctx: &ValidationContext) -> Vec<Finding>;
+}
+
+/// Rule implemented by a plain function, used for the built-in rules
+#[derive(Clone, Copy)]
+pub struct BuiltinRule {
+    pub id: &'static str,
+    pub group: &'static str,
+    pub severity: Severity,
+    pub message: &'static str,
+    pub check: fn(&ValidationContext) -> Vec<Finding>,
+}
+
+impl ValidationRule for BuiltinRule {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn group(&self) -> &str {
+        self.group
+    }
+
+    fn message(&self) -> &str {
+        self.message
+    }
+
+    fn default_severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn check(&self, ctx: &ValidationContext) -> Vec<Finding> {
+        (self.check)(ctx)
+    }
+}
+
+/// Fill `{0}`, `{1}`, ... in a message template
+pub fn render_message(template: &str, args: &[String]) -> String {
+    let mut out = template.to_string();
+    for (i, arg) in args.iter().enumerate() {
+        out = out.replace(&format!("{{{}}}", i), arg);
+    }
+    out
+}
+
+#[derive(Clone, Default)]
+pub struct RuleRegistry {
+    rules: Vec<Arc<dyn ValidationRule>>,
+}
+
+impl RuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with all rules shipped with this crate
+    pub fn with_builtin_rules() -> Self {
+        let mut registry = Self::new();
+        for rules in [
+            super::xsd::RULES,
+            super::rules::RULES,
+            super::codes::RULES,
+            super::latvia::RULES,
+            super::signature::RULES,
+            super::envelope::RULES,
+        ] {
+            for rule in rules {
+                registry.register(Arc::new(*rule));
+            }
+        }
+        registry
+    }
+
+    /// Add a rule; a rule with the same ID replaces the existing one
+    pub fn register(&mut self, rule: Arc<dyn ValidationRule>) {
+        self.rules.retain(|r| r.id() != rule.id());
+        self.rules.push(rule);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Arc<dyn ValidationRule>> {
+        self.rules.iter().find(|r| r.id() == id)
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &Arc<dyn ValidationRule>> {
+        self.rules.iter()
+    }
+}
@@ -0,0 +1,338 @@
+//! Export of validation results for handing to suppliers
+
+use super::messages::{localized, Language};
+use super::registry::{render_message, Severity, ValidationIssue};
+use super::ValidationReport;
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Validation outcome of one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReport {
+    pub path: String,
+    #[serde(flatten)]
+    pub report: ValidationReport,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One Schematron Validation Report Language document per file
+    Svrl,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Svrl, ExportFormat::Json, ExportFormat::Html];
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    files: usize,
+    valid: usize,
+    invalid: usize,
+    errors: usize,
+    warnings: usize,
+}
+
+impl Summary {
+    fn of(files: &[FileReport]) -> Self {
+        let count = |severity: Severity| {
+            files
+                .iter()
+                .flat_map(|f| &f.report.issues)
+                .filter(|i| i.severity == severity)
+                .count()
+        };
+        let valid = files.iter().filter(|f| f.report.is_valid()).count();
+        Self {
+            files: files.len(),
+            valid,
+            invalid: files.len() - valid,
+            errors: count(Severity::Error),
+            warnings: count(Severity::Warning),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: &'a str,
+    profile: &'a str,
+    valid: bool,
+    issues: Vec<ValidationIssue>,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    generated_at: String,
+    summary: Summary,
+    files: Vec<JsonFile<'a>>,
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// `issues` with their messages in `language`
+fn localize(issues: &[ValidationIssue], language: Language) -> Vec<ValidationIssue> {
+    issues
+        .iter()
+        .map(|i| ValidationIssue {
+            message: i.message_in(language),
+            ..i.clone()
+        })
+        .collect()
+}
+
+/// Machine-readable report across all files, with messages in `language`
+pub fn to_json(
+    files: &[FileReport],
+    generated_at: DateTime<Utc>,
+    language: Language,
+) -> Result<String> {
+    let report = JsonReport {
+        generated_at: timestamp(generated_at),
+        summary: Summary::of(files),
+        files: files
+            .iter()
+            .map(|f| JsonFile {
+                path: &f.path,
+                profile: &f.report.profile,
+                valid: f.report.is_valid(),
+                issues: localize(&f.report.issues, language),
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+fn svrl_flag(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "fatal",
+        Severity::Warning => "warning",
+        Severity::Info => "information",
+    }
+}
+
+/// SVRL document for one file, in the form produced by the PEPPOL Schematron,
+/// with messages in `language`
+pub fn to_svrl(file: &FileReport, language: Language) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<svrl:schematron-output xmlns:svrl=\"http://purl.oclc.org/dsdl/svrl\" title=\"{}\">",
+        escape(&file.report.profile)
+    );
+    let _ = writeln!(
+        out,
+        "  <svrl:active-pattern document=\"{}\" name=\"{}\"/>",
+        escape(&file.path),
+        escape(&file.report.profile)
+    );
+    for issue in &file.report.issues {
+        let _ = writeln!(
+            out,
+            "  <svrl:failed-assert id=\"{}\" flag=\"{}\" location=\"{}\">",
+            escape(&issue.rule_id),
+            svrl_flag(issue.severity),
+            escape(issue.location.as_deref().unwrap_or("/"))
+        );
+        let _ = writeln!(
+            out,
+            "    <svrl:text>{}</svrl:text>",
+            escape(&issue.message_in(language))
+        );
+        out.push_str("  </svrl:failed-assert>\n");
+    }
+    out.push_str("</svrl:schematron-output>\n");
+    out
+}
+
+const HTML_STYLE: &str =
+    "body { font-family: -apple-system, Segoe UI, sans-serif; margin: 24px; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 24px; }
+th, td { border: 1px solid #ddd; padding: 6px 8px; text-align: left; vertical-align: top; }
+th { background: #f5f5f5; }
+.ok { color: #1e7e34; font-weight: bold; }
+.error { color: #c82333; font-weight: bold; }
+.warning { color: #b8860b; font-weight: bold; }
+.info { color: #555; }
+h2 { font-size: 16px; margin-top: 32px; word-break: break-all; }";
+
+/// Styled summary across all files, in `language`
+pub fn to_html(files: &[FileReport], generated_at: DateTime<Utc>, language: Language) -> String {
+    let text = |key: &str, fallback: &'static str| localized(language, key, fallback);
+    let summary = Summary::of(files);
+    let title = text("REPORT-TITLE", "Validation report");
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n");
+    let _ = writeln!(
+        out,
+        "<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">",
+        language
+    );
+    let _ = writeln!(out, "<title>{}</title>", title);
+    let _ = writeln!(out, "<style>\n{}\n</style>\n</head>\n<body>", HTML_STYLE);
+    let _ = writeln!(out, "<h1>{}</h1>", title);
+    let counts = [
+        timestamp(generated_at),
+        summary.files.to_string(),
+        summary.valid.to_string(),
+        summary.invalid.to_string(),
+        summary.errors.to_string(),
+        summary.warnings.to_string(),
+    ];
+    let _ = writeln!(
+        out,
+        "<p>{}</p>",
+        render_message(
+            text(
+                "REPORT-SUMMARY",
+                "Generated {0}. {1} files: {2} valid, {3} invalid; {4} errors, {5} warnings."
+            ),
+            &counts
+        )
+    );
+
+    let _ = writeln!(
+        out,
+        "<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+        text("REPORT-FILE", "File"),
+        text("REPORT-PROFILE", "Profile"),
+        text("REPORT-RESULT", "Result"),
+        text("REPORT-ERRORS", "Errors"),
+        text("REPORT-WARNINGS", "Warnings")
+    );
+    for file in files {
+        let count = |s: Severity| {
+            file.report
+                .issues
+                .iter()
+                .filter(|i| i.severity == s)
+                .count()
+        };
+        let result = if file.report.is_valid() {
+            format!(
+                "<span class=\"ok\">{}</span>",
+                text("REPORT-VALID", "Valid")
+            )
+        } else {
+            format!(
+                "<span class=\"error\">{}</span>",
+                text("REPORT-INVALID", "Invalid")
+            )
+        };
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&file.path),
+            escape(&file.report.profile),
+            result,
+            count(Severity::Error),
+            count(Severity::Warning)
+        );
+    }
+    out.push_str("</table>\n");
+
+    for file in files.iter().filter(|f| !f.report.issues.is_empty()) {
+        let _ = writeln!(out, "<h2>{}</h2>", escape(&file.path));
+        let _ = writeln!(
+            out,
+            "<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            text("REPORT-RULE", "Rule"),
+            text("REPORT-SEVERITY", "Severity"),
+            text("REPORT-MESSAGE", "Message"),
+            text("REPORT-LOCATION", "Location")
+        );
+        for issue in &file.report.issues {
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&issue.rule_id),
+                issue.severity,
+                severity_label(issue.severity, language),
+                escape(&issue.message_in(language)),
+                escape(issue.location.as_deref().unwrap_or(""))
+            );
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn severity_label(severity: Severity, language: Language) -> &'static str {
+    match severity {
+        Severity::Error => localized(language, "REPORT-ERROR", "error"),
+        Severity::Warning => localized(language, "REPORT-WARNING", "warning"),
+        Severity::Info => localized(language, "REPORT-INFO", "info"),
+    }
+}
+
+/// Name of the SVRL file for `path`, unique among `taken`
+fn svrl_file_name(path: &str, taken: &mut HashSet<String>) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "invoice".to_string());
+    let mut name = format!("{}.svrl.xml", stem);
+    let mut n = 2;
+    while !taken.insert(name.clone()) {
+        name = format!("{}-{}.svrl.xml", stem, n);
+        n += 1;
+    }
+    name
+}
+
+/// Write the reports in `formats` into `dir`, in `language`, and return the
+/// files written
+pub fn write_reports(
+    files: &[FileReport],
+    dir: &Path,
+    formats: &[ExportFormat],
+    language: Language,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create report directory {}", dir.display()))?;
+    let generated_at = Utc::now();
+    let mut written = Vec::new();
+    let mut write = |name: &str, contents: &str| -> Result<()> {
+        let path = dir.join(name);
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write report {}", path.display()))?;
+        written.push(path);
+        Ok(())
+    };
+
+    for format in formats {
+        match format {
+            ExportFormat::Svrl => {
+                let mut taken = HashSet::new();
+                for file in files {
+                    write(
+                        &svrl_file_name(&file.path, &mut taken),
+                        &to_svrl(file, language),
+                    )?;
+                }
+            }
+            ExportFormat::Json => write(
+                "validation-report.json",
+                &to_json(files, generated_at, language)?,
+            )?,
+            ExportFormat::Html => write(
+                "validation-report.html",
+                &to_html(files, generated_at, language),
+            )?,
+        }
+    }
+    Ok(written)
+}
@@ -1,58 +1,201 @@
-use crate::parsing::parse_ubl_invoice;
+use super::registry::{BuiltinRule, Finding, Severity, ValidationContext};
+use crate::parsing::UBLInvoice;
 
-pub fn basic_en16931_checks(xml: &str) -> Result<(), Vec<String>> {
-    let mut errs = Vec::new();
-
-    // Check root element
-    if !xml.contains("<Invoice") && !xml.contains("<invoice") {
-        errs.push("Missing UBL Invoice root element".to_string());
-        return Err(errs);
+fn when(invoice: Option<&UBLInvoice>, failed: fn(&UBLInvoice) -> bool) -> Vec<Finding> {
+    match invoice {
+        Some(inv) if failed(inv) => vec![Finding::new()],
+        _ => Vec::new(),
     }
+}
 
-    // Parse and validate mandatory fields per EN16931
-    let invoice = match parse_ubl_invoice(xml) {
-        Ok(inv) => inv,
-        Err(e) => {
-            errs.push(format!("Failed to parse UBL: {}", e));
-            return Err(errs);
-        }
-    };
+pub(super) fn has_invoice_root(xml: &str) -> bool {
+    xml.contains("<Invoice") || xml.contains("<invoice")
+}
 
-    // BT-1: Invoice number (mandatory)
-    if invoice.invoice_number.is_empty() || invoice.invoice_number == "UNKNOWN" {
-        errs.push("BT-1: Invoice number is mandatory".to_string());
+fn root_element(ctx: &ValidationContext) -> Vec<Finding> {
+    if has_invoice_root(ctx.xml) {
+        Vec::new()
+    } else {
+        vec![Finding::new()]
     }
+}
 
-    // BT-2: Issue date (mandatory)
-    if invoice.issue_date.is_empty() {
-        errs.push("BT-2: Issue date is mandatory".to_string());
+fn parse_error(ctx: &ValidationContext) -> Vec<Finding> {
+    // Documents without an Invoice root are already reported by UBL-01
+    if ctx.invoice.is_some() || !has_invoice_root(ctx.xml) {
+        return Vec::new();
     }
-
-    // BT-5: Invoice currency code (mandatory)
-    if invoice.currency_code.is_empty() {
-        errs.push("BT-5: Currency code is mandatory".to_string());
-    } else if invoice.currency_code.len() != 3 {
-        errs.push("BT-5: Currency code must be 3 characters (ISO 4217)".to_string());
+    match roxmltree::Document::parse(ctx.xml) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![Finding::new().with_arg(e)],
     }
+}
 
-    // BG-4: Seller (mandatory)
-    if invoice.supplier_name.is_empty() {
-        errs.push("BG-4: Seller name is mandatory".to_string());
-    }
+// BT-1: Invoice number (mandatory)
+fn bt_1(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| {
+        inv.invoice_number.is_empty() || inv.invoice_number == "UNKNOWN"
+    })
+}
 
-    // BG-7: Buyer (mandatory)
-    if invoice.customer_name.is_empty() {
-        errs.push("BG-7: Buyer name is mandatory".to_string());
-    }
+// BT-2: Issue date (mandatory)
+fn bt_2(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| inv.issue_date.is_empty())
+}
 
-    // BT-115: Payable amount should be present
-    if invoice.payable_amount.is_none() {
-        errs.push("BT-115: Payable amount should be present".to_string());
-    }
+// BT-5: Invoice currency code (mandatory)
+fn bt_5(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| inv.currency_code.is_empty())
+}
 
-    if errs.is_empty() {
-        Ok(())
-    } else {
-        Err(errs)
-    }
+fn bt_5_length(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| {
+        !inv.currency_code.is_empty() && inv.currency_code.len() != 3
+    })
 }
+
+// BG-4: Seller (mandatory)
+fn bg_4(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| inv.supplier_name.is_empty())
+}
+
+// BG-7: Buyer (mandatory)
+fn bg_7(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| inv.customer_name.is_empty())
+}
+
+// BT-115: Payable amount should be present
+fn bt_115(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| inv.payable_amount.is_none())
+}
+
+// BR-62: Seller electronic address must have a scheme identifier
+fn br_62(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| {
+        inv.supplier_id.is_some() && inv.supplier_endpoint_scheme.is_none()
+    })
+    .into_iter()
+    .map(|f| f.at("AccountingSupplierParty/Party/EndpointID"))
+    .collect()
+}
+
+// BR-63: Buyer electronic address must have a scheme identifier
+fn br_63(ctx: &ValidationContext) -> Vec<Finding> {
+    when(ctx.invoice, |inv| {
+        inv.customer_id.is_some() && inv.customer_endpoint_scheme.is_none()
+    })
+    .into_iter()
+    .map(|f| f.at("AccountingCustomerParty/Party/EndpointID"))
+    .collect()
+}
+
+/// Whether an amount has more than two decimals
+pub(crate) fn has_excess_decimals(amount: &str) -> bool {
+    amount
+        .trim()
+        .split_once('.')
+        .is_some_and(|(_, decimals)| decimals.len() > 2)
+}
+
+// BR-DEC: Amounts have at most two decimals; item prices are exempt
+fn br_dec(ctx: &ValidationContext) -> Vec<Finding> {
+    let (Some(doc), Some(_)) = (ctx.doc, ctx.invoice) else {
+        return Vec::new();
+    };
+    doc.descendants()
+        .filter(|n| n.is_element() && n.attribute("currencyID").is_some())
+        .filter(|n| n.tag_name().name() != "PriceAmount")
+        .filter_map(|n| {
+            let text = n.text()?;
+            has_excess_decimals(text)
+                .then(|| Finding::new().at(n.tag_name().name()).with_arg(text.trim()))
+        })
+        .collect()
+}
+
+pub(super) const RULES: &[BuiltinRule] = &[
+    BuiltinRule {
+        id: "UBL-01",
+        group: "ubl",
+        severity: Severity::Error,
+        message: "Missing UBL Invoice root element",
+        check: root_element,
+    },
+    BuiltinRule {
+        id: "UBL-02",
+        group: "ubl",
+        severity: Severity::Error,
+        message: "Failed to parse UBL: {0}",
+        check: parse_error,
+    },
+    BuiltinRule {
+        id: "BT-1",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Invoice number is mandatory",
+        check: bt_1,
+    },
+    BuiltinRule {
+        id: "BT-2",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Issue date is mandatory",
+        check: bt_2,
+    },
+    BuiltinRule {
+        id: "BT-5",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Currency code is mandatory",
+        check: bt_5,
+    },
+    BuiltinRule {
+        id: "BT-5-LEN",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Currency code must be 3 characters (ISO 4217)",
+        check: bt_5_length,
+    },
+    BuiltinRule {
+        id: "BG-4",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Seller name is mandatory",
+        check: bg_4,
+    },
+    BuiltinRule {
+        id: "BG-7",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Buyer name is mandatory",
+        check: bg_7,
+    },
+    BuiltinRule {
+        id: "BT-115",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Payable amount should be present",
+        check: bt_115,
+    },
+    BuiltinRule {
+        id: "BR-62",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Seller electronic address must have a scheme identifier",
+        check: br_62,
+    },
+    BuiltinRule {
+        id: "BR-63",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Buyer electronic address must have a scheme identifier",
+        check: br_63,
+    },
+    BuiltinRule {
+        id: "BR-DEC",
+        group: "en16931",
+        severity: Severity::Error,
+        message: "Amount {0} has more than two decimals",
+        check: br_dec,
+    },
+];
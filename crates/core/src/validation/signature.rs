@@ -0,0 +1,86 @@
+//! Checks on the XMLDSig/XAdES signatures of signed documents
+//!
+//! Unsigned documents pass; a signature is only required to be sound once it
+//! is present.
+
+use super::registry::{BuiltinRule, Finding, Severity, ValidationContext};
+use crate::xmldsig::SignatureVerification;
+
+fn location(signature: &SignatureVerification) -> String {
+    match &signature.id {
+        Some(id) => format!("Signature[@Id='{}']", id),
+        None => "Signature".to_string(),
+    }
+}
+
+fn signer(signature: &SignatureVerification) -> String {
+    signature
+        .signer
+        .as_ref()
+        .map(|s| s.subject.clone())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// SIG-01: Signature value and reference digests must verify
+fn sig_01(ctx: &ValidationContext) -> Vec<Finding> {
+    ctx.signatures
+        .iter()
+        .filter(|s| !s.is_valid())
+        .map(|s| {
+            Finding::new()
+                .at(&location(s))
+                .with_arg(s.errors.join("; "))
+        })
+        .collect()
+}
+
+// SIG-02: The signer must chain to a trusted authority. Without any trust
+// anchors installed no signer can, so this is only a warning then.
+fn sig_02(ctx: &ValidationContext) -> Vec<Finding> {
+    ctx.signatures
+        .iter()
+        .filter(|s| s.signer.is_some() && !s.trusted)
+        .map(|s| {
+            let finding = Finding::new().at(&location(s)).with_arg(signer(s));
+            if ctx.trust.is_empty() {
+                finding.with_severity(Severity::Warning)
+            } else {
+                finding
+            }
+        })
+        .collect()
+}
+
+// SIG-03: The signer certificate must be valid now, whatever signing time
+// the signature claims
+fn sig_03(ctx: &ValidationContext) -> Vec<Finding> {
+    ctx.signatures
+        .iter()
+        .filter(|s| s.signer.is_some() && !s.certificate_valid)
+        .map(|s| Finding::new().at(&location(s)).with_arg(signer(s)))
+        .collect()
+}
+
+pub(super) const RULES: &[BuiltinRule] = &[
+    BuiltinRule {
+        id: "SIG-01",
+        group: "signature",
+        severity: Severity::Error,
+        message: "Invalid XML signature: {0}",
+        check: sig_01,
+    },
+    BuiltinRule {
+        id: "SIG-02",
+        group: "signature",
+        severity: Severity::Error,
+        message: "Signer {0} is not issued by a trusted authority",
+        check: sig_02,
+    },
+    BuiltinRule {
+        id: "SIG-03",
+        group: "signature",
+        severity: Severity::Warning,
+        message: "Signer certificate {0} is not valid",
+        check: sig_03,
+    },
+];
@@ -1,4 +1,21 @@
+use super::registry::{BuiltinRule, Finding, Severity, ValidationContext};
+
 pub fn validate_against_xsd(_xml: &str) -> Result<(), String> {
     // Placeholder: integrate libxml2/xerces + UBL 2.1 schemas in future
     Ok(())
 }
+
+fn xsd_check(ctx: &ValidationContext) -> Vec<Finding> {
+    match validate_against_xsd(ctx.xml) {
+        Ok(()) => Vec::new(),
+        Err(e) => vec![Finding::new().with_arg(e)],
+    }
+}
+
+pub(super) const RULES: &[BuiltinRule] = &[BuiltinRule {
+    id: "UBL-03",
+    group: "ubl",
+    severity: Severity::Error,
+    message: "{0}",
+    check: xsd_check,
+}];
@@ -0,0 +1,225 @@
+//! XML digital signatures (XMLDSig with XAdES properties) over UBL documents
+
+mod sign;
+pub mod trust;
+mod verify;
+
+pub use sign::{
+    sign_div_envelope, sign_invoice, SigningKey, XmlSigner, DIV_NS, DIV_SENDER_SECTION,
+};
+pub use verify::{verify_detached_signatures, verify_signatures, Resolver, SignatureVerification};
+
+use anyhow::{anyhow, Context, Result};
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Pkcs1v15Sign, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use x509_cert::der::{Decode, Encode};
+use x509_cert::Certificate;
+
+pub const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+pub const XADES_NS: &str = "http://uri.etsi.org/01903/v1.3.2#";
+pub const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+pub const SIGNED_PROPERTIES_TYPE: &str = "http://uri.etsi.org/01903#SignedProperties";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#sha1" => Some(Self::Sha1),
+            "http://www.w3.org/2001/04/xmlenc#sha256" => Some(Self::Sha256),
+            "http://www.w3.org/2001/04/xmlenc#sha512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn uri(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "http://www.w3.org/2000/09/xmldsig#sha1",
+            Self::Sha256 => "http://www.w3.org/2001/04/xmlenc#sha256",
+            Self::Sha512 => "http://www.w3.org/2001/04/xmlenc#sha512",
+        }
+    }
+
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    RsaSha1,
+    RsaSha256,
+    RsaSha512,
+    EcdsaSha256,
+}
+
+impl SignatureAlgorithm {
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#rsa-sha1" => Some(Self::RsaSha1),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Some(Self::RsaSha256),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => Some(Self::RsaSha512),
+            "http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256" => Some(Self::EcdsaSha256),
+            _ => None,
+        }
+    }
+
+    pub fn uri(&self) -> &'static str {
+        match self {
+            Self::RsaSha1 => "http://www.w3.org/2000/09/xmldsig#rsa-sha1",
+            Self::RsaSha256 => "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256",
+            Self::RsaSha512 => "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512",
+            Self::EcdsaSha256 => "http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256",
+        }
+    }
+
+    /// Check an XMLDSig signature value (raw `r || s` for ECDSA) over `message`
+    pub fn verify(&self, key: &PublicKey, message: &[u8], signature: &[u8]) -> Result<()> {
+        match (self, key) {
+            (Self::RsaSha1, PublicKey::Rsa(key)) => key.verify(
+                Pkcs1v15Sign::new::<Sha1>(),
+                &Sha1::digest(message),
+                signature,
+            )?,
+            (Self::RsaSha256, PublicKey::Rsa(key)) => key.verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &Sha256::digest(message),
+                signature,
+            )?,
+            (Self::RsaSha512, PublicKey::Rsa(key)) => key.verify(
+                Pkcs1v15Sign::new::<Sha512>(),
+                &Sha512::digest(message),
+                signature,
+            )?,
+            (Self::EcdsaSha256, PublicKey::P256(key)) => {
+                use p256::ecdsa::signature::Verifier;
+                let signature = p256::ecdsa::Signature::from_slice(signature)?;
+                p256::ecdsa::VerifyingKey::from(key).verify(message, &signature)?
+            }
+            _ => return Err(anyhow!("Key type does not match {}", self.uri())),
+        }
+        Ok(())
+    }
+}
+
+/// Public key of a certificate, for the key types XMLDSig signatures use
+pub enum PublicKey {
+    Rsa(RsaPublicKey),
+    P256(p256::PublicKey),
+}
+
+impl PublicKey {
+    pub fn of(cert: &Certificate) -> Result<Self> {
+        let spki = cert.tbs_certificate.subject_public_key_info.to_der()?;
+        if let Ok(key) = RsaPublicKey::from_public_key_der(&spki) {
+            return Ok(Self::Rsa(key));
+        }
+        if let Ok(key) = p256::PublicKey::from_public_key_der(&spki) {
+            return Ok(Self::P256(key));
+        }
+        Err(anyhow!("Unsupported certificate key type"))
+    }
+}
+
+/// Whether `issuer` signed `cert`
+pub fn is_issued_by(cert: &Certificate, issuer: &Certificate) -> bool {
+    if cert.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+        return false;
+    }
+    let (Ok(key), Ok(tbs)) = (PublicKey::of(issuer), cert.tbs_certificate.to_der()) else {
+        return false;
+    };
+    let signature = cert.signature.raw_bytes();
+    let result = match (cert.signature_algorithm.oid.to_string().as_str(), &key) {
+        ("1.2.840.113549.1.1.5", PublicKey::Rsa(k)) => {
+            k.verify(Pkcs1v15Sign::new::<Sha1>(), &Sha1::digest(&tbs), signature)
+        }
+        ("1.2.840.113549.1.1.11", PublicKey::Rsa(k)) => k.verify(
+            Pkcs1v15Sign::new::<Sha256>(),
+            &Sha256::digest(&tbs),
+            signature,
+        ),
+        ("1.2.840.113549.1.1.12", PublicKey::Rsa(k)) => k.verify(
+            Pkcs1v15Sign::new::<Sha384>(),
+            &Sha384::digest(&tbs),
+            signature,
+        ),
+        ("1.2.840.113549.1.1.13", PublicKey::Rsa(k)) => k.verify(
+            Pkcs1v15Sign::new::<Sha512>(),
+            &Sha512::digest(&tbs),
+            signature,
+        ),
+        ("1.2.840.10045.4.3.2", PublicKey::P256(k)) => {
+            use p256::ecdsa::signature::Verifier;
+            return p256::ecdsa::Signature::from_der(signature).is_ok_and(|sig| {
+                p256::ecdsa::VerifyingKey::from(k)
+                    .verify(&tbs, &sig)
+                    .is_ok()
+            });
+        }
+        _ => return false,
+    };
+    result.is_ok()
+}
+
+/// Details of a certificate shown in validation results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// Serial number in hex
+    pub serial: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// SHA-256 of the DER encoding, in hex
+    pub sha256_fingerprint: String,
+}
+
+impl CertificateInfo {
+    pub fn of(cert: &Certificate) -> Result<Self> {
+        let validity = &cert.tbs_certificate.validity;
+        Ok(Self {
+            subject: cert.tbs_certificate.subject.to_string(),
+            issuer: cert.tbs_certificate.issuer.to_string(),
+            serial: hex::encode(cert.tbs_certificate.serial_number.as_bytes()),
+            not_before: to_utc(validity.not_before.to_unix_duration())?,
+            not_after: to_utc(validity.not_after.to_unix_duration())?,
+            sha256_fingerprint: hex::encode(Sha256::digest(cert.to_der()?)),
+        })
+    }
+
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.not_before <= at && at <= self.not_after
+    }
+}
+
+fn to_utc(since_epoch: std::time::Duration) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp(since_epoch.as_secs() as i64, 0)
+        .ok_or_else(|| anyhow!("Certificate date out of range"))
+}
+
+/// Decode base64 element content, which may be wrapped across lines
+pub fn decode_base64(text: &str) -> Result<Vec<u8>> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(compact)
+        .context("Invalid base64 content")
+}
+
+pub fn certificate_from_der(der: &[u8]) -> Result<Certificate> {
+    Certificate::from_der(der).context("Invalid X.509 certificate")
+}
@@ -0,0 +1,498 @@
+//! Enveloped and detached XMLDSig signatures with XAdES-BES signed properties
+//!
+//! UBL documents carry the signature in `ext:UBLExtensions` and sign the
+//! whole document; other documents, such as DIV envelopes, get it as the
+//! last child of the root element and may sign a single `Id` section.
+//! Detached signatures cover the files of an ASiC container.
+
+use super::{
+    certificate_from_der, DigestAlgorithm, PublicKey, SignatureAlgorithm, DSIG_NS,
+    ENVELOPED_SIGNATURE, SIGNED_PROPERTIES_TYPE, XADES_NS,
+};
+use crate::asic::{uri_encode, ASIC_NS};
+use crate::c14n::{escape_attr, Canonicalizer, EXC_C14N};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine as _;
+use chrono::{DateTime, SecondsFormat, Utc};
+use roxmltree::{Document, Node};
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::path::Path;
+use x509_cert::der::Encode;
+use x509_cert::Certificate;
+
+const UBL_NS_PREFIX: &str = "urn:oasis:names:specification:ubl:schema:xsd:";
+const UBL_EXTENSIONS_NS: &str =
+    "urn:oasis:names:specification:ubl:schema:xsd:CommonExtensionComponents-2";
+/// Namespace of DIV envelopes
+pub const DIV_NS: &str = "http://ivis.eps.gov.lv/XMLSchemas/100001/DIV/v1-0";
+/// Id of the section of a DIV envelope that is signed
+pub const DIV_SENDER_SECTION: &str = "SenderSection";
+
+enum PrivateKey {
+    Rsa(Box<RsaPrivateKey>),
+    P256(p256::ecdsa::SigningKey),
+}
+
+impl PrivateKey {
+    fn matches(&self, cert: &Certificate) -> bool {
+        match (self, PublicKey::of(cert)) {
+            (Self::Rsa(key), Ok(PublicKey::Rsa(public))) => key.to_public_key() == public,
+            (Self::P256(key), Ok(PublicKey::P256(public))) => {
+                *key.verifying_key() == p256::ecdsa::VerifyingKey::from(public)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Private key and certificate chain used to sign documents
+pub struct SigningKey {
+    key: PrivateKey,
+    /// Signer certificate first, then the rest of the chain
+    chain: Vec<Certificate>,
+}
+
+impl SigningKey {
+    /// Key from PKCS#8 DER; `certificates` must include the one for the key
+    pub fn from_pkcs8_der(key: &[u8], certificates: Vec<Certificate>) -> Result<Self> {
+        let key = if let Ok(key) = RsaPrivateKey::from_pkcs8_der(key) {
+            PrivateKey::Rsa(Box::new(key))
+        } else if let Ok(key) = p256::SecretKey::from_pkcs8_der(key) {
+            PrivateKey::P256(key.into())
+        } else {
+            bail!("Unsupported private key; only RSA and P-256 keys can sign");
+        };
+        let mut chain = certificates;
+        let signer = chain
+            .iter()
+            .position(|c| key.matches(c))
+            .ok_or_else(|| anyhow!("No certificate matches the private key"))?;
+        let signer = chain.remove(signer);
+        chain.insert(0, signer);
+        Ok(Self { key, chain })
+    }
+
+    /// First private key in a PKCS#12 (.p12/.pfx) file, with its certificates
+    pub fn from_pkcs12(data: &[u8], password: &str) -> Result<Self> {
+        use p12_keystore::{KeyStore, KeyStoreEntry};
+
+        let store = KeyStore::from_pkcs12(data, password).context("Failed to read PKCS#12")?;
+        let (_, entry) = store
+            .private_key_chain()
+            .ok_or_else(|| anyhow!("PKCS#12 file contains no private key"))?;
+        // Certificates without a local key ID are separate entries
+        let others = store.entries().filter_map(|(_, e)| match e {
+            KeyStoreEntry::Certificate(cert) => Some(cert),
+            _ => None,
+        });
+        let certificates = entry
+            .chain()
+            .iter()
+            .chain(others)
+            .map(|c| certificate_from_der(c.as_der()))
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_pkcs8_der(entry.key(), certificates)
+    }
+
+    pub fn load_pkcs12(path: &Path, password: &str) -> Result<Self> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read certificate {}", path.display()))?;
+        Self::from_pkcs12(&data, password)
+            .with_context(|| format!("Failed to load certificate {}", path.display()))
+    }
+
+    pub fn certificate(&self) -> &Certificate {
+        &self.chain[0]
+    }
+
+    pub fn chain(&self) -> &[Certificate] {
+        &self.chain
+    }
+
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        match self.key {
+            PrivateKey::Rsa(_) => SignatureAlgorithm::RsaSha256,
+            PrivateKey::P256(_) => SignatureAlgorithm::EcdsaSha256,
+        }
+    }
+
+    /// Signature value over `message` as XMLDSig encodes it
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        match &self.key {
+            PrivateKey::Rsa(key) => {
+                Ok(key.sign(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(message))?)
+            }
+            PrivateKey::P256(key) => {
+                use p256::ecdsa::signature::Signer;
+                let signature: p256::ecdsa::Signature = key.try_sign(message)?;
+                Ok(signature.to_bytes().to_vec())
+            }
+        }
+    }
+}
+
+/// A `ds:Reference` to signed content, other than the signed properties
+struct Reference {
+    uri: String,
+    transforms: Vec<&'static str>,
+    digest: String,
+}
+
+/// Builds and inserts an enveloped signature, or a detached one
+pub struct XmlSigner<'k> {
+    key: &'k SigningKey,
+    /// `Id` of the signed element; the whole document when `None`
+    reference: Option<String>,
+    signature_id: Option<String>,
+    signing_time: Option<DateTime<Utc>>,
+    xades: bool,
+}
+
+fn base64(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+fn is_ubl(root: Node) -> bool {
+    root.tag_name()
+        .namespace()
+        .is_some_and(|ns| ns.starts_with(UBL_NS_PREFIX))
+}
+
+/// Length of the closing tag of `node` in the source text
+fn end_tag_len(xml: &str, node: Node) -> Option<usize> {
+    let text = &xml[node.range()];
+    if text.ends_with("/>") {
+        return None;
+    }
+    text.rfind("</").map(|start| text.len() - start)
+}
+
+/// `xml` with `signature` inserted where documents of its kind carry one
+fn insert_signature(xml: &str, doc: &Document, signature: &str) -> Result<String> {
+    let root = doc.root_element();
+    let (at, content) = if is_ubl(root) {
+        let extension = |declaration: &str| {
+            format!(
+                "<ext:UBLExtension{}><ext:ExtensionContent>{}</ext:ExtensionContent></ext:UBLExtension>",
+                declaration, signature
+            )
+        };
+        let declaration = format!(" xmlns:ext=\"{}\"", UBL_EXTENSIONS_NS);
+        let extensions = root
+            .children()
+            .find(|n| n.has_tag_name((UBL_EXTENSIONS_NS, "UBLExtensions")));
+        match extensions {
+            Some(ext) => {
+                let len =
+                    end_tag_len(xml, ext).ok_or_else(|| anyhow!("Empty UBLExtensions element"))?;
+                (ext.range().end - len, extension(&declaration))
+            }
+            // UBLExtensions must be the first child of the document element
+            None => {
+                let first = root
+                    .first_child()
+                    .ok_or_else(|| anyhow!("Empty document element"))?;
+                let extensions = format!(
+                    "<ext:UBLExtensions{}>{}</ext:UBLExtensions>",
+                    declaration,
+                    extension("")
+                );
+                (first.range().start, extensions)
+            }
+        }
+    } else {
+        let len = end_tag_len(xml, root).ok_or_else(|| anyhow!("Empty document element"))?;
+        (root.range().end - len, signature.to_string())
+    };
+    let mut out = String::with_capacity(xml.len() + content.len());
+    out.push_str(&xml[..at]);
+    out.push_str(&content);
+    out.push_str(&xml[at..]);
+    Ok(out)
+}
+
+impl<'k> XmlSigner<'k> {
+    /// Signs the whole document with XAdES-BES properties, at the current time
+    pub fn new(key: &'k SigningKey) -> Self {
+        Self {
+            key,
+            reference: None,
+            signature_id: None,
+            signing_time: None,
+            xades: true,
+        }
+    }
+
+    /// Sign only the element with this `Id` attribute
+    pub fn with_reference_id(mut self, id: impl Into<String>) -> Self {
+        self.reference = Some(id.into());
+        self
+    }
+
+    pub fn with_signature_id(mut self, id: impl Into<String>) -> Self {
+        self.signature_id = Some(id.into());
+        self
+    }
+
+    pub fn with_signing_time(mut self, time: DateTime<Utc>) -> Self {
+        self.signing_time = Some(time);
+        self
+    }
+
+    /// Plain XMLDSig without XAdES signed properties
+    pub fn with_xades(mut self, xades: bool) -> Self {
+        self.xades = xades;
+        self
+    }
+
+    fn signed_properties(&self, id: &str, time: DateTime<Utc>) -> Result<String> {
+        let cert_digest = Sha256::digest(self.key.certificate().to_der()?);
+        Ok(format!(
+            "<xades:SignedProperties Id=\"{id}-signedprops\"><xades:SignedSignatureProperties>\
+             <xades:SigningTime>{}</xades:SigningTime>\
+             <xades:SigningCertificateV2><xades:Cert><xades:CertDigest>\
+             <ds:DigestMethod Algorithm=\"{}\"/><ds:DigestValue>{}</ds:DigestValue>\
+             </xades:CertDigest></xades:Cert></xades:SigningCertificateV2>\
+             </xades:SignedSignatureProperties></xades:SignedProperties>",
+            time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            DigestAlgorithm::Sha256.uri(),
+            base64(&cert_digest),
+        ))
+    }
+
+    fn signed_info(&self, id: &str, references: &[Reference], properties_digest: &str) -> String {
+        let mut out = format!(
+            "<ds:SignedInfo><ds:CanonicalizationMethod Algorithm=\"{}\"/>\
+             <ds:SignatureMethod Algorithm=\"{}\"/>",
+            EXC_C14N,
+            self.key.algorithm().uri()
+        );
+        for (i, reference) in references.iter().enumerate() {
+            let _ = write!(out, "<ds:Reference Id=\"{id}-ref{i}\" URI=\"");
+            escape_attr(&reference.uri, &mut out);
+            out.push_str("\">");
+            if !reference.transforms.is_empty() {
+                out.push_str("<ds:Transforms>");
+                for transform in &reference.transforms {
+                    let _ = write!(out, "<ds:Transform Algorithm=\"{}\"/>", transform);
+                }
+                out.push_str("</ds:Transforms>");
+            }
+            let _ = write!(
+                out,
+                "<ds:DigestMethod Algorithm=\"{}\"/><ds:DigestValue>{}</ds:DigestValue></ds:Reference>",
+                DigestAlgorithm::Sha256.uri(),
+                reference.digest
+            );
+        }
+        if self.xades {
+            let _ = write!(
+                out,
+                "<ds:Reference Type=\"{SIGNED_PROPERTIES_TYPE}\" URI=\"#{id}-signedprops\">\
+                 <ds:Transforms><ds:Transform Algorithm=\"{EXC_C14N}\"/></ds:Transforms>\
+                 <ds:DigestMethod Algorithm=\"{}\"/><ds:DigestValue>{}</ds:DigestValue></ds:Reference>",
+                DigestAlgorithm::Sha256.uri(),
+                properties_digest
+            );
+        }
+        out.push_str("</ds:SignedInfo>");
+        out
+    }
+
+    fn signature(
+        &self,
+        id: &str,
+        signed_info: &str,
+        value: &str,
+        properties: &str,
+    ) -> Result<String> {
+        let mut out = format!(
+            "<ds:Signature xmlns:ds=\"{DSIG_NS}\" Id=\"{id}\">{signed_info}\
+             <ds:SignatureValue>{value}</ds:SignatureValue><ds:KeyInfo><ds:X509Data>"
+        );
+        for cert in self.key.chain() {
+            let _ = write!(
+                out,
+                "<ds:X509Certificate>{}</ds:X509Certificate>",
+                base64(&cert.to_der()?)
+            );
+        }
+        out.push_str("</ds:X509Data></ds:KeyInfo>");
+        if self.xades {
+            let _ = write!(
+                out,
+                "<ds:Object><xades:QualifyingProperties xmlns:xades=\"{XADES_NS}\" Target=\"#{id}\">\
+                 {properties}</xades:QualifyingProperties></ds:Object>"
+            );
+        }
+        out.push_str("</ds:Signature>");
+        Ok(out)
+    }
+
+    fn signature_id(&self, time: DateTime<Utc>, seed: &[u8]) -> String {
+        match &self.signature_id {
+            Some(id) => id.clone(),
+            None => {
+                let mut hasher = Sha256::new();
+                hasher.update(time.timestamp_nanos_opt().unwrap_or_default().to_be_bytes());
+                hasher.update(seed);
+                format!("sig-{}", &hex::encode(hasher.finalize())[..16])
+            }
+        }
+    }
+
+    /// Digest the signed properties and sign SignedInfo, each as they appear
+    /// once `place` has put the signature into its document
+    fn complete(
+        &self,
+        id: &str,
+        references: &[Reference],
+        properties: &str,
+        place: impl Fn(&str) -> Result<String>,
+    ) -> Result<String> {
+        let find = |doc: &'_ Document, ns: &str, name: &str| {
+            doc.descendants()
+                .find(|n| {
+                    n.has_tag_name((ns, name))
+                        && n.ancestors()
+                            .find(|a| a.has_tag_name((DSIG_NS, "Signature")))
+                            .and_then(|a| a.attribute("Id"))
+                            == Some(id)
+                })
+                .map(|n| n.id())
+                .ok_or_else(|| anyhow!("{} not found after insertion", name))
+        };
+
+        let properties_digest = if self.xades {
+            let draft =
+                self.signature(id, &self.signed_info(id, references, ""), "", properties)?;
+            let draft = place(&draft)?;
+            let doc = Document::parse(&draft).context("Failed to parse signed XML")?;
+            let props = doc.get_node(find(&doc, XADES_NS, "SignedProperties")?);
+            let canonical = Canonicalizer::new(&draft)
+                .with_exclusive(true)
+                .node(props.ok_or_else(|| anyhow!("SignedProperties not found"))?);
+            base64(&Sha256::digest(canonical.as_bytes()))
+        } else {
+            String::new()
+        };
+
+        // Exclusive C14N keeps SignedInfo and the signed content independent
+        // of the namespaces of whatever message they end up embedded in
+        let signed_info = self.signed_info(id, references, &properties_digest);
+        let with_digests = place(&self.signature(id, &signed_info, "", properties)?)?;
+        let doc = Document::parse(&with_digests).context("Failed to parse signed XML")?;
+        let node = doc
+            .get_node(find(&doc, DSIG_NS, "SignedInfo")?)
+            .ok_or_else(|| anyhow!("SignedInfo not found"))?;
+        let canonical = Canonicalizer::new(&with_digests)
+            .with_exclusive(true)
+            .node(node);
+        let value = base64(&self.key.sign(canonical.as_bytes())?);
+
+        place(&self.signature(id, &signed_info, &value, properties)?)
+    }
+
+    /// `xml` with an enveloped signature added
+    pub fn sign(&self, xml: &str) -> Result<String> {
+        let doc = Document::parse(xml).context("Failed to parse XML")?;
+        let time = self.signing_time.unwrap_or_else(Utc::now);
+        let id = self.signature_id(time, xml.as_bytes());
+        let properties = if self.xades {
+            self.signed_properties(&id, time)?
+        } else {
+            String::new()
+        };
+        let place = |signature: &str| insert_signature(xml, &doc, signature);
+
+        // The content digest does not depend on the digest and signature
+        // values, so it is computed on the document with a draft signature
+        let mut reference = match &self.reference {
+            Some(id) => Reference {
+                uri: format!("#{}", id),
+                transforms: vec![EXC_C14N],
+                digest: String::new(),
+            },
+            None => Reference {
+                uri: String::new(),
+                transforms: vec![ENVELOPED_SIGNATURE, EXC_C14N],
+                digest: String::new(),
+            },
+        };
+        let draft = self.signature(
+            &id,
+            &self.signed_info(&id, std::slice::from_ref(&reference), ""),
+            "",
+            &properties,
+        )?;
+        let draft = place(&draft)?;
+        let draft_doc = Document::parse(&draft).context("Failed to parse signed XML")?;
+        let signature = draft_doc
+            .descendants()
+            .find(|n| n.has_tag_name((DSIG_NS, "Signature")) && n.attribute("Id") == Some(&id))
+            .ok_or_else(|| anyhow!("Signature not found after insertion"))?;
+        let canonicalizer = Canonicalizer::new(&draft).with_exclusive(true);
+        let content = match &self.reference {
+            Some(reference) => {
+                let target = draft_doc
+                    .descendants()
+                    .find(|n| n.attribute("Id") == Some(reference.as_str()))
+                    .ok_or_else(|| anyhow!("No element with Id {}", reference))?;
+                canonicalizer.node(target)
+            }
+            None => canonicalizer.excluding(signature.id()).document(&draft_doc),
+        };
+        reference.digest = base64(&Sha256::digest(content.as_bytes()));
+
+        self.complete(&id, &[reference], &properties, place)
+    }
+
+    /// Detached signature over `objects` (file name and content), as an
+    /// `asic:XAdESSignatures` document for an ASiC container
+    pub fn sign_detached(&self, objects: &[(&str, &[u8])]) -> Result<String> {
+        if objects.is_empty() {
+            bail!("Nothing to sign");
+        }
+        let time = self.signing_time.unwrap_or_else(Utc::now);
+        let mut seed = Vec::new();
+        for (name, data) in objects {
+            seed.extend_from_slice(name.as_bytes());
+            seed.extend_from_slice(&Sha256::digest(data));
+        }
+        let id = self.signature_id(time, &seed);
+        let properties = if self.xades {
+            self.signed_properties(&id, time)?
+        } else {
+            String::new()
+        };
+        let references: Vec<Reference> = objects
+            .iter()
+            .map(|(name, data)| Reference {
+                uri: uri_encode(name),
+                transforms: Vec::new(),
+                digest: base64(&Sha256::digest(data)),
+            })
+            .collect();
+        self.complete(&id, &references, &properties, |signature| {
+            Ok(format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <asic:XAdESSignatures xmlns:asic=\"{ASIC_NS}\">{signature}</asic:XAdESSignatures>"
+            ))
+        })
+    }
+}
+
+/// Sign a UBL invoice as a whole, placing the signature in `UBLExtensions`
+pub fn sign_invoice(xml: &str, key: &SigningKey) -> Result<String> {
+    XmlSigner::new(key).sign(xml)
+}
+
+/// Sign the `SenderSection` of a DIV envelope
+pub fn sign_div_envelope(xml: &str, key: &SigningKey) -> Result<String> {
+    XmlSigner::new(key)
+        .with_reference_id(DIV_SENDER_SECTION)
+        .sign(xml)
+}
@@ -0,0 +1,154 @@
+//! Certificate authorities trusted to issue signing certificates
+//!
+//! Anchors are bundled with the crate (`trust/anchors.pem`) and can be
+//! extended at runtime with PEM or DER certificates from a local directory.
+
+use super::{is_issued_by, CertificateInfo};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use x509_cert::der::Encode;
+use x509_cert::ext::pkix::{BasicConstraints, KeyUsage};
+use x509_cert::Certificate;
+
+/// Longest chain of intermediates followed from the signer to an anchor
+const MAX_CHAIN_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+pub struct TrustStore {
+    anchors: Vec<Certificate>,
+}
+
+fn parse_certificates(data: &[u8]) -> Result<Vec<Certificate>> {
+    if data.windows(10).any(|w| w == b"-----BEGIN") {
+        Ok(Certificate::load_pem_chain(data)?)
+    } else {
+        Ok(vec![super::certificate_from_der(data)?])
+    }
+}
+
+fn is_valid_at(cert: &Certificate, at: DateTime<Utc>) -> bool {
+    CertificateInfo::of(cert).is_ok_and(|info| info.is_valid_at(at))
+}
+
+/// Whether `cert` may issue certificates: it is a CA by its basic
+/// constraints and, if it restricts its key usage, allows keyCertSign
+fn is_ca(cert: &Certificate) -> bool {
+    let tbs = &cert.tbs_certificate;
+    let ca = matches!(tbs.get::<BasicConstraints>(), Ok(Some((_, c))) if c.ca);
+    let signs_certificates = match tbs.get::<KeyUsage>() {
+        Ok(Some((_, usage))) => usage.key_cert_sign(),
+        Ok(None) => true,
+        Err(_) => false,
+    };
+    ca && signs_certificates
+}
+
+/// Whether `issuer` is a CA valid at `at` that signed `cert`
+fn issued(cert: &Certificate, issuer: &Certificate, at: DateTime<Utc>) -> bool {
+    is_ca(issuer) && is_valid_at(issuer, at) && is_issued_by(cert, issuer)
+}
+
+impl TrustStore {
+    /// Anchors bundled with this build
+    pub fn bundled() -> Self {
+        let anchors =
+            parse_certificates(include_bytes!("../../trust/anchors.pem")).unwrap_or_default();
+        Self { anchors }
+    }
+
+    /// Bundled anchors plus every `.pem`, `.crt` and `.cer` file in `dir`
+    pub fn with_dir(dir: &Path) -> Result<Self> {
+        let mut store = Self::bundled();
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read trust anchors from {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let is_cert = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| ["pem", "crt", "cer"].contains(&e.to_ascii_lowercase().as_str()));
+            if !is_cert {
+                continue;
+            }
+            let data = std::fs::read(&path)?;
+            let certs = parse_certificates(&data)
+                .with_context(|| format!("Invalid trust anchor {}", path.display()))?;
+            store.anchors.extend(certs);
+        }
+        Ok(store)
+    }
+
+    pub fn add(&mut self, anchor: Certificate) {
+        self.anchors.push(anchor);
+    }
+
+    pub fn anchors(&self) -> &[Certificate] {
+        &self.anchors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchors.is_empty()
+    }
+
+    /// Whether `cert` is an anchor or chains to one through `intermediates`.
+    /// Every certificate in the chain must be valid now, and every issuer a
+    /// CA allowed to sign certificates.
+    pub fn is_trusted(&self, cert: &Certificate, intermediates: &[Certificate]) -> bool {
+        let now = Utc::now();
+        let mut current = cert;
+        for _ in 0..MAX_CHAIN_DEPTH {
+            if !is_valid_at(current, now) {
+                return false;
+            }
+            if self.anchors.iter().any(|a| a == current) {
+                return true;
+            }
+            if self.anchors.iter().any(|a| issued(current, a, now)) {
+                return true;
+            }
+            match intermediates
+                .iter()
+                .find(|c| *c != current && issued(current, c, now))
+            {
+                Some(issuer) => current = issuer,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Hash of all anchors; changes whenever an anchor is added or removed
+    pub fn fingerprint(&self) -> String {
+        let mut ders: Vec<Vec<u8>> = self
+            .anchors
+            .iter()
+            .filter_map(|a| a.to_der().ok())
+            .collect();
+        ders.sort();
+        let mut hasher = Sha256::new();
+        for der in ders {
+            hasher.update(Sha256::digest(der));
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+static CURRENT: RwLock<Option<Arc<TrustStore>>> = RwLock::new(None);
+
+/// Trust anchors used by signature verification; the bundled ones unless
+/// others were installed
+pub fn current() -> Arc<TrustStore> {
+    if let Some(store) = CURRENT.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return Arc::clone(store);
+    }
+    let mut slot = CURRENT.write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(slot.get_or_insert_with(|| Arc::new(TrustStore::bundled())))
+}
+
+/// Replace the trust anchors used by signature verification
+pub fn install(store: TrustStore) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(store));
+}
@@ -0,0 +1,361 @@
+use super::trust::TrustStore;
+use super::{
+    certificate_from_der, decode_base64, CertificateInfo, DigestAlgorithm, PublicKey,
+    SignatureAlgorithm, DIV_NS, DIV_SENDER_SECTION, DSIG_NS, ENVELOPED_SIGNATURE, XADES_NS,
+};
+use crate::c14n::{Canonicalizer, EXC_C14N};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use x509_cert::der::Encode;
+use x509_cert::Certificate;
+
+/// Outcome of checking one `ds:Signature` element
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignatureVerification {
+    /// `Id` attribute of the signature element
+    pub id: Option<String>,
+    pub signer: Option<CertificateInfo>,
+    /// XAdES SigningTime, if the signature claims one
+    pub signing_time: Option<DateTime<Utc>>,
+    /// Whether the signature carries XAdES signed properties
+    pub xades: bool,
+    /// SignatureValue matches SignedInfo under the signer's key
+    pub signature_valid: bool,
+    /// Every reference digest matches the referenced content, and the
+    /// references cover the document
+    pub references_valid: bool,
+    /// The signer chains to a trust anchor
+    pub trusted: bool,
+    /// The signer certificate is valid now; the XAdES signing time is only
+    /// claimed by the signer, so it is not relied on
+    pub certificate_valid: bool,
+    pub errors: Vec<String>,
+}
+
+impl SignatureVerification {
+    pub fn is_valid(&self) -> bool {
+        self.signature_valid && self.references_valid
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, ns: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|c| c.tag_name().namespace() == Some(ns) && c.tag_name().name() == name)
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    ns: &'a str,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |c| c.tag_name().namespace() == Some(ns) && c.tag_name().name() == name)
+}
+
+fn descendant<'a, 'input>(
+    node: Node<'a, 'input>,
+    ns: &str,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.descendants()
+        .find(|c| c.tag_name().namespace() == Some(ns) && c.tag_name().name() == name)
+}
+
+fn algorithm<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str> {
+    child(node, DSIG_NS, name)
+        .and_then(|n| n.attribute("Algorithm"))
+        .ok_or_else(|| anyhow!("Missing {}", name))
+}
+
+/// The one element whose `Id` attribute is `id`
+///
+/// A document with several such elements is rejected, since the verified
+/// element need not be the one a reader of the document looks at.
+fn element_by_id<'a, 'input>(doc: &'a Document<'input>, id: &str) -> Result<Node<'a, 'input>> {
+    let mut matches = doc
+        .descendants()
+        .filter(|n| n.is_element() && n.attribute("Id") == Some(id));
+    let element = matches
+        .next()
+        .ok_or_else(|| anyhow!("Reference #{} not found", id))?;
+    if matches.next().is_some() {
+        return Err(anyhow!("Id {} is used by more than one element", id));
+    }
+    Ok(element)
+}
+
+/// Canonicalizer for a CanonicalizationMethod or Transform element, with the
+/// exclusive C14N InclusiveNamespaces prefix list applied
+fn canonicalization<'x>(xml: &'x str, method: Node) -> Result<Canonicalizer<'x>> {
+    let uri = method.attribute("Algorithm").unwrap_or_default();
+    let canonicalizer = Canonicalizer::for_algorithm(xml, uri)
+        .ok_or_else(|| anyhow!("Unsupported canonicalization {}", uri))?;
+    Ok(match child(method, EXC_C14N, "InclusiveNamespaces") {
+        Some(inclusive) => {
+            canonicalizer.with_inclusive_prefixes(inclusive.attribute("PrefixList").unwrap_or(""))
+        }
+        None => canonicalizer,
+    })
+}
+
+/// Content of a file referenced by a detached signature
+pub type Resolver<'r> = &'r dyn Fn(&str) -> Option<Vec<u8>>;
+
+/// Canonical form of a same-document reference after its transforms
+fn same_document_content(
+    xml: &str,
+    doc: &Document,
+    signature: Node,
+    reference: Node,
+    uri: &str,
+) -> Result<String> {
+    let target = match uri.strip_prefix('#') {
+        Some(id) => element_by_id(doc, id)?,
+        None => doc.root(),
+    };
+
+    let mut canonicalizer = Canonicalizer::new(xml);
+    let mut enveloped = false;
+    if let Some(transforms) = child(reference, DSIG_NS, "Transforms") {
+        for transform in children(transforms, DSIG_NS, "Transform") {
+            match transform.attribute("Algorithm").unwrap_or_default() {
+                ENVELOPED_SIGNATURE => enveloped = true,
+                t if Canonicalizer::for_algorithm(xml, t).is_some() => {
+                    canonicalizer = canonicalization(xml, transform)?
+                }
+                t => return Err(anyhow!("Unsupported transform {}", t)),
+            }
+        }
+    }
+    // A same-document reference to the whole document leaves comments out
+    if uri.is_empty() {
+        canonicalizer = canonicalizer.with_comments(false);
+    }
+    if enveloped {
+        canonicalizer = canonicalizer.excluding(signature.id());
+    }
+    Ok(canonicalizer.node(target))
+}
+
+fn is_enveloped(reference: Node) -> bool {
+    child(reference, DSIG_NS, "Transforms").is_some_and(|transforms| {
+        children(transforms, DSIG_NS, "Transform")
+            .any(|t| t.attribute("Algorithm") == Some(ENVELOPED_SIGNATURE))
+    })
+}
+
+/// Whether `reference` covers the signed document: the whole document
+/// without the signature, its root element, the sender section of the DIV
+/// envelope holding the signature or, for detached signatures, another file
+fn covers_document(doc: &Document, signature: Node, reference: Node) -> bool {
+    let uri = reference.attribute("URI").unwrap_or("");
+    if uri.is_empty() {
+        return is_enveloped(reference);
+    }
+    match uri.strip_prefix('#') {
+        Some(DIV_SENDER_SECTION) => is_div_sender_section(doc, signature),
+        Some(id) => doc.root_element().attribute("Id") == Some(id),
+        None => true,
+    }
+}
+
+/// Whether the `SenderSection` is a section of a DIV envelope that also holds
+/// `signature`. Elsewhere it is just an element that happens to have that Id.
+fn is_div_sender_section(doc: &Document, signature: Node) -> bool {
+    let Ok(section) = element_by_id(doc, DIV_SENDER_SECTION) else {
+        return false;
+    };
+    section.parent_element().is_some_and(|envelope| {
+        envelope.has_tag_name((DIV_NS, "Envelope")) && signature.ancestors().any(|a| a == envelope)
+    })
+}
+
+fn check_reference(
+    xml: &str,
+    doc: &Document,
+    signature: Node,
+    reference: Node,
+    resolve: Resolver,
+) -> Result<()> {
+    let uri = reference.attribute("URI").unwrap_or("");
+    let content = if uri.is_empty() || uri.starts_with('#') {
+        same_document_content(xml, doc, signature, reference, uri)?.into_bytes()
+    } else {
+        // Other files are digested as they are
+        if child(reference, DSIG_NS, "Transforms").is_some() {
+            return Err(anyhow!(
+                "Transforms of external reference {} are not supported",
+                uri
+            ));
+        }
+        resolve(uri).ok_or_else(|| anyhow!("Referenced file {} not found", uri))?
+    };
+
+    let digest_uri = algorithm(reference, "DigestMethod")?;
+    let digest = DigestAlgorithm::from_uri(digest_uri)
+        .ok_or_else(|| anyhow!("Unsupported digest {}", digest_uri))?;
+    let expected = child(reference, DSIG_NS, "DigestValue")
+        .and_then(|n| n.text())
+        .ok_or_else(|| anyhow!("Missing DigestValue"))?;
+    if digest.digest(&content) != decode_base64(expected)? {
+        let name = if uri.is_empty() { "document" } else { uri };
+        return Err(anyhow!("Digest of {} does not match", name));
+    }
+    Ok(())
+}
+
+/// Check the XAdES signing certificate digest against `signer`
+fn check_signing_certificate(properties: Node, signer: &Certificate) -> Result<()> {
+    let cert_digest = ["SigningCertificateV2", "SigningCertificate"]
+        .iter()
+        .find_map(|name| descendant(properties, XADES_NS, name))
+        .and_then(|n| descendant(n, XADES_NS, "CertDigest"));
+    let cert_digest =
+        cert_digest.ok_or_else(|| anyhow!("XAdES signing certificate digest is missing"))?;
+    let digest_uri = algorithm(cert_digest, "DigestMethod")?;
+    let digest = DigestAlgorithm::from_uri(digest_uri)
+        .ok_or_else(|| anyhow!("Unsupported digest {}", digest_uri))?;
+    let expected = child(cert_digest, DSIG_NS, "DigestValue")
+        .and_then(|n| n.text())
+        .ok_or_else(|| anyhow!("Missing certificate DigestValue"))?;
+    if digest.digest(&signer.to_der()?) != decode_base64(expected)? {
+        return Err(anyhow!(
+            "XAdES signing certificate does not match the signer"
+        ));
+    }
+    Ok(())
+}
+
+fn verify_signature(
+    xml: &str,
+    doc: &Document,
+    signature: Node,
+    trust: &TrustStore,
+    resolve: Resolver,
+) -> SignatureVerification {
+    let mut result = SignatureVerification {
+        id: signature.attribute("Id").map(str::to_string),
+        ..Default::default()
+    };
+    if let Err(e) = verify_into(xml, doc, signature, trust, resolve, &mut result) {
+        result.errors.push(e.to_string());
+    }
+    result
+}
+
+fn verify_into(
+    xml: &str,
+    doc: &Document,
+    signature: Node,
+    trust: &TrustStore,
+    resolve: Resolver,
+    result: &mut SignatureVerification,
+) -> Result<()> {
+    let signed_info =
+        child(signature, DSIG_NS, "SignedInfo").ok_or_else(|| anyhow!("Missing SignedInfo"))?;
+
+    result.references_valid = true;
+    let mut covered = false;
+    for reference in children(signed_info, DSIG_NS, "Reference") {
+        match check_reference(xml, doc, signature, reference, resolve) {
+            Ok(()) => covered |= covers_document(doc, signature, reference),
+            Err(e) => {
+                result.references_valid = false;
+                result.errors.push(e.to_string());
+            }
+        }
+    }
+    // An empty SignedInfo, or one signing only its own properties, proves
+    // nothing about the document
+    if !covered {
+        result.references_valid = false;
+        result
+            .errors
+            .push("No reference covers the signed document".to_string());
+    }
+
+    let certificates: Vec<Certificate> = child(signature, DSIG_NS, "KeyInfo")
+        .into_iter()
+        .flat_map(|k| k.descendants())
+        .filter(|n| n.has_tag_name((DSIG_NS, "X509Certificate")))
+        .filter_map(|n| n.text())
+        .map(|text| certificate_from_der(&decode_base64(text)?))
+        .collect::<Result<_>>()?;
+    if certificates.is_empty() {
+        return Err(anyhow!("No X509Certificate in KeyInfo"));
+    }
+
+    let c14n_method = child(signed_info, DSIG_NS, "CanonicalizationMethod")
+        .ok_or_else(|| anyhow!("Missing CanonicalizationMethod"))?;
+    let canonical = canonicalization(xml, c14n_method)?.node(signed_info);
+    let method_uri = algorithm(signed_info, "SignatureMethod")?;
+    let method = SignatureAlgorithm::from_uri(method_uri)
+        .ok_or_else(|| anyhow!("Unsupported signature method {}", method_uri))?;
+    let value = child(signature, DSIG_NS, "SignatureValue")
+        .and_then(|n| n.text())
+        .ok_or_else(|| anyhow!("Missing SignatureValue"))?;
+    let value = decode_base64(value)?;
+
+    // KeyInfo may carry the whole chain; the signer is whichever key verifies
+    let signer = certificates.iter().find(|cert| {
+        PublicKey::of(cert)
+            .is_ok_and(|key| method.verify(&key, canonical.as_bytes(), &value).is_ok())
+    });
+    result.signature_valid = signer.is_some();
+    if signer.is_none() {
+        result
+            .errors
+            .push("SignatureValue does not match any certificate in KeyInfo".to_string());
+    }
+    let signer = signer.unwrap_or(&certificates[0]);
+    let info = CertificateInfo::of(signer)?;
+
+    if let Some(properties) = descendant(signature, XADES_NS, "SignedProperties") {
+        result.xades = true;
+        let id = properties.attribute("Id").unwrap_or_default();
+        let referenced = children(signed_info, DSIG_NS, "Reference")
+            .any(|r| !id.is_empty() && r.attribute("URI") == Some(&format!("#{}", id)));
+        if !referenced {
+            result.references_valid = false;
+            result
+                .errors
+                .push("XAdES SignedProperties are not covered by the signature".to_string());
+        }
+        if let Some(time) = descendant(properties, XADES_NS, "SigningTime").and_then(|n| n.text()) {
+            let time = DateTime::parse_from_rfc3339(time.trim())
+                .with_context(|| format!("Invalid SigningTime {}", time.trim()))?;
+            result.signing_time = Some(time.with_timezone(&Utc));
+        }
+        if let Err(e) = check_signing_certificate(properties, signer) {
+            result.references_valid = false;
+            result.errors.push(e.to_string());
+        }
+    }
+
+    result.certificate_valid = info.is_valid_at(Utc::now());
+    result.trusted = trust.is_trusted(signer, &certificates);
+    result.signer = Some(info);
+    Ok(())
+}
+
+/// Verify every XMLDSig signature in `xml` against the trust anchors in `trust`
+pub fn verify_signatures(xml: &str, trust: &TrustStore) -> Result<Vec<SignatureVerification>> {
+    verify_detached_signatures(xml, trust, &|_| None)
+}
+
+/// Like [`verify_signatures`], with references to other files (by URI as
+/// written in the signature) resolved by `resolve`
+pub fn verify_detached_signatures(
+    xml: &str,
+    trust: &TrustStore,
+    resolve: Resolver,
+) -> Result<Vec<SignatureVerification>> {
+    let doc = Document::parse(xml).context("Failed to parse XML")?;
+    Ok(doc
+        .descendants()
+        .filter(|n| n.has_tag_name((DSIG_NS, "Signature")))
+        .map(|signature| verify_signature(xml, &doc, signature, trust, resolve))
+        .collect())
+}
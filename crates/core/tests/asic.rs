@@ -0,0 +1,246 @@
+use lat_einv_core::asic::{AsicContainer, ReadLimits, ASIC_E_MIMETYPE};
+use lat_einv_core::xmldsig::trust::TrustStore;
+use lat_einv_core::xmldsig::{SigningKey, XmlSigner};
+use p12_keystore::{KeyStore, KeyStoreEntry, PrivateKeyChain};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+use std::io::{Cursor, Write};
+use x509_cert::der::Decode;
+
+const PASSWORD: &str = "test-password";
+
+// "%PDF-1.4 test" in base64
+const INVOICE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+  <cac:AdditionalDocumentReference>
+    <cbc:ID>INV-001</cbc:ID>
+    <cac:Attachment>
+      <cbc:EmbeddedDocumentBinaryObject mimeCode="application/pdf" filename="INV 001.pdf">JVBERi0xLjQg
+dGVzdA==</cbc:EmbeddedDocumentBinaryObject>
+    </cac:Attachment>
+  </cac:AdditionalDocumentReference>
+  <cac:LegalMonetaryTotal>
+    <cbc:PayableAmount currencyID="EUR">121.00</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+</Invoice>"#;
+
+/// Signing key and a trust store holding its issuer
+fn signing_key() -> (SigningKey, TrustStore) {
+    let ca_key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "Test Root CA");
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = params.self_signed(&ca_key).unwrap();
+
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "SIA Test");
+    let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+
+    let certificates = [&cert, &ca]
+        .into_iter()
+        .map(|c| p12_keystore::Certificate::from_der(c.der()).unwrap());
+    let entry = PrivateKeyChain::new(key.serialize_der(), [1u8; 20], certificates);
+    let mut store = KeyStore::new();
+    store.add_entry("signer", KeyStoreEntry::PrivateKeyChain(entry));
+    let p12 = store.writer(PASSWORD).write().unwrap();
+
+    let mut trust = TrustStore::default();
+    trust.add(x509_cert::Certificate::from_der(ca.der()).unwrap());
+    (SigningKey::from_pkcs12(&p12, PASSWORD).unwrap(), trust)
+}
+
+fn signed_container(key: &SigningKey) -> AsicContainer {
+    let mut container = AsicContainer::for_invoice("INV-001.xml", INVOICE).unwrap();
+    container.sign(&XmlSigner::new(key)).unwrap();
+    container
+}
+
+/// Copy of a zip archive with `name` replaced by `data`, or dropped
+fn rezip(bytes: &[u8], name: &str, data: Option<&[u8]>) -> Vec<u8> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+    let mut out = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).unwrap();
+        if entry.name() != name {
+            out.raw_copy_file(entry).unwrap();
+            continue;
+        }
+        if let Some(data) = data {
+            let options =
+                zip::write::SimpleFileOptions::default().compression_method(entry.compression());
+            out.start_file(name, options).unwrap();
+            out.write_all(data).unwrap();
+        }
+    }
+    out.finish().unwrap().into_inner()
+}
+
+#[test]
+fn invoice_container_roundtrips_and_verifies() {
+    let (key, trust) = signing_key();
+    let container = signed_container(&key);
+    let bytes = container.to_bytes().unwrap();
+    assert_eq!(&bytes[30..38], b"mimetype");
+    assert_eq!(
+        &bytes[38..38 + ASIC_E_MIMETYPE.len()],
+        ASIC_E_MIMETYPE.as_bytes()
+    );
+
+    let read = AsicContainer::from_bytes(&bytes).unwrap();
+    assert_eq!(read.objects(), container.objects());
+    let pdf = read.object("INV 001.pdf").unwrap();
+    assert_eq!(pdf.media_type, "application/pdf");
+    assert_eq!(pdf.data, b"%PDF-1.4 test");
+    assert_eq!(read.signatures()[0].0, "META-INF/signatures001.xml");
+    assert!(read.signatures()[0].1.contains("URI=\"INV%20001.pdf\""));
+
+    let result = read.verify(&trust);
+    assert!(result.is_valid(), "{:?}", result);
+    assert_eq!(result.signatures.len(), 1);
+    assert!(result.signatures[0].trusted);
+}
+
+#[test]
+fn modified_object_fails_digest_check() {
+    let (key, trust) = signing_key();
+    let bytes = signed_container(&key).to_bytes().unwrap();
+    let tampered = rezip(&bytes, "INV 001.pdf", Some(b"%PDF-1.4 fake"));
+
+    let result = AsicContainer::from_bytes(&tampered).unwrap().verify(&trust);
+    assert!(!result.is_valid());
+    assert!(!result.signatures[0].references_valid);
+    assert_eq!(result.unsigned.len(), 2);
+}
+
+#[test]
+fn manifest_must_match_contents() {
+    let (key, trust) = signing_key();
+    let bytes = signed_container(&key).to_bytes().unwrap();
+    let missing = rezip(&bytes, "INV 001.pdf", None);
+
+    let result = AsicContainer::from_bytes(&missing).unwrap().verify(&trust);
+    assert!(!result.is_valid());
+    assert!(result
+        .errors
+        .iter()
+        .any(|e| e == "INV 001.pdf is listed in the manifest but missing"));
+
+    let without_manifest = rezip(&bytes, "META-INF/manifest.xml", None);
+    assert!(AsicContainer::from_bytes(&without_manifest).is_err());
+    let other_type = rezip(&bytes, "mimetype", Some(b"application/zip"));
+    assert!(AsicContainer::from_bytes(&other_type).is_err());
+}
+
+#[test]
+fn objects_added_after_signing_are_unsigned() {
+    let (key, trust) = signing_key();
+    let mut container = signed_container(&key);
+    container
+        .add("notes.txt", "text/plain", b"late".to_vec())
+        .unwrap();
+    assert!(container
+        .add("META-INF/x.xml", "text/xml", Vec::new())
+        .is_err());
+    assert!(container.add("../x", "text/plain", Vec::new()).is_err());
+
+    let result = container.verify(&trust);
+    assert!(result.signatures[0].is_valid());
+    assert_eq!(result.unsigned, ["notes.txt"]);
+    assert!(!result.is_valid());
+}
+
+/// Archive with the entries `(name, stored, data)` in order
+fn zip_of(entries: &[(&str, bool, &[u8])]) -> Vec<u8> {
+    let mut out = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, stored, data) in entries {
+        let method = if *stored {
+            zip::CompressionMethod::Stored
+        } else {
+            zip::CompressionMethod::Deflated
+        };
+        let options = zip::write::SimpleFileOptions::default().compression_method(method);
+        out.start_file(*name, options).unwrap();
+        out.write_all(data).unwrap();
+    }
+    out.finish().unwrap().into_inner()
+}
+
+#[test]
+fn untrusted_signers_do_not_make_a_valid_container() {
+    let (key, _) = signing_key();
+    let container = signed_container(&key);
+    let result = container.verify(&TrustStore::default());
+    assert!(result.signatures[0].is_valid());
+    assert!(!result.signatures[0].trusted);
+    assert!(result.unsigned.is_empty() && result.errors.is_empty());
+    assert!(!result.is_valid());
+}
+
+#[test]
+fn entries_may_not_repeat() {
+    let manifest = br#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0"/>"#;
+    let mut bytes = zip_of(&[
+        ("mimetype", true, ASIC_E_MIMETYPE.as_bytes()),
+        ("META-INF/manifest.xml", false, manifest),
+        ("INV-001.xml", false, b"signed"),
+        ("INV-002.xml", false, b"swapped in"),
+    ]);
+    // Rename the second file in its local and central headers
+    for i in 0..bytes.len() - 11 {
+        if &bytes[i..i + 11] == b"INV-002.xml" {
+            bytes[i + 6] = b'1';
+        }
+    }
+    let err = AsicContainer::from_bytes(&bytes).unwrap_err();
+    assert_eq!(err.to_string(), "Container has INV-001.xml more than once");
+}
+
+#[test]
+fn mimetype_must_come_first_uncompressed() {
+    let manifest = br#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0"/>"#;
+    let mimetype = ASIC_E_MIMETYPE.as_bytes();
+    let valid = zip_of(&[
+        ("mimetype", true, mimetype),
+        ("META-INF/manifest.xml", false, manifest),
+    ]);
+    assert!(AsicContainer::from_bytes(&valid).is_ok());
+
+    let compressed = zip_of(&[
+        ("mimetype", false, mimetype),
+        ("META-INF/manifest.xml", false, manifest),
+    ]);
+    assert!(AsicContainer::from_bytes(&compressed).is_err());
+    let second = zip_of(&[
+        ("META-INF/manifest.xml", false, manifest),
+        ("mimetype", true, mimetype),
+    ]);
+    assert!(AsicContainer::from_bytes(&second).is_err());
+    assert!(AsicContainer::from_bytes(&zip_of(&[])).is_err());
+}
+
+#[test]
+fn expansion_is_limited() {
+    let (key, _) = signing_key();
+    let bytes = signed_container(&key).to_bytes().unwrap();
+    // A megabyte of zeros compresses to about a kilobyte
+    let zeros = vec![0u8; 1024 * 1024];
+    let bomb = rezip(&bytes, "INV 001.pdf", Some(&zeros));
+    assert!(bomb.len() < 64 * 1024);
+
+    let limits = ReadLimits::default().with_max_entry_size(512 * 1024);
+    let err = AsicContainer::from_bytes_with(&bomb, limits).unwrap_err();
+    assert!(err.to_string().starts_with("INV 001.pdf is larger than"));
+
+    let limits = ReadLimits::default().with_max_total_size(1024 * 1024);
+    let err = AsicContainer::from_bytes_with(&bomb, limits).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Container content is larger than"));
+
+    assert!(AsicContainer::from_bytes(&bomb).is_ok());
+}
@@ -0,0 +1,63 @@
+use lat_einv_core::c14n::Canonicalizer;
+use lat_einv_core::parsing::{compute_canonical_sha256_hex, compute_sha256_hex};
+use roxmltree::Document;
+
+const INVOICE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+  <cbc:Note>Two  spaces</cbc:Note>
+  <cac:LegalMonetaryTotal>
+    <cbc:PayableAmount currencyID="EUR" unitCode="X">121.00</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+</Invoice>"#;
+
+#[test]
+fn reformatted_invoice_has_same_canonical_hash() {
+    let resaved = r#"<Invoice xmlns:cbc='urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2'
+         xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2"><!-- re-saved -->
+<cbc:ID>INV-001</cbc:ID><cbc:Note>Two  spaces</cbc:Note>
+<cac:LegalMonetaryTotal xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2">
+<cbc:PayableAmount unitCode='X' currencyID='EUR'>121.00</cbc:PayableAmount></cac:LegalMonetaryTotal></Invoice>
+"#;
+    assert_ne!(compute_sha256_hex(INVOICE), compute_sha256_hex(resaved));
+    assert_eq!(
+        compute_canonical_sha256_hex(INVOICE).unwrap(),
+        compute_canonical_sha256_hex(resaved).unwrap()
+    );
+}
+
+#[test]
+fn content_changes_alter_canonical_hash() {
+    let hash = compute_canonical_sha256_hex(INVOICE).unwrap();
+    for changed in [
+        INVOICE.replace("121.00", "121.01"),
+        INVOICE.replace("Two  spaces", "Two spaces"),
+        INVOICE.replace("currencyID=\"EUR\"", "currencyID=\"USD\""),
+    ] {
+        assert_ne!(compute_canonical_sha256_hex(&changed).unwrap(), hash);
+    }
+    assert!(compute_canonical_sha256_hex("<Invoice>").is_err());
+}
+
+#[test]
+fn exclusive_form_renders_only_used_namespaces() {
+    let xml = r#"<a:root xmlns:a="urn:a" xmlns:b="urn:b" xmlns="urn:d" xml:lang="lv"><child b:x="1"><a:leaf/></child></a:root>"#;
+    let doc = Document::parse(xml).unwrap();
+    let child = doc.descendants().find(|n| n.has_tag_name("child")).unwrap();
+
+    assert_eq!(
+        Canonicalizer::new(xml).node(child),
+        r#"<child xmlns="urn:d" xmlns:a="urn:a" xmlns:b="urn:b" xml:lang="lv" b:x="1"><a:leaf></a:leaf></child>"#
+    );
+    assert_eq!(
+        Canonicalizer::new(xml).with_exclusive(true).node(child),
+        r#"<child xmlns="urn:d" xmlns:b="urn:b" b:x="1"><a:leaf xmlns:a="urn:a"></a:leaf></child>"#
+    );
+    assert_eq!(
+        Canonicalizer::new(xml)
+            .with_exclusive(true)
+            .with_inclusive_prefixes("a")
+            .node(child),
+        r#"<child xmlns="urn:d" xmlns:a="urn:a" xmlns:b="urn:b" b:x="1"><a:leaf></a:leaf></child>"#
+    );
+}
@@ -0,0 +1,84 @@
+use lat_einv_core::validation::{
+    ruleset_fingerprint, validate_with_profile, Language, ValidationCache, ValidationProfile,
+};
+
+const INVOICE: &str = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+</Invoice>"#;
+
+const SIGNED: &str = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+  <ds:Signature xmlns:ds="http://www.w3.org/2000/09/xmldsig#"/>
+</Invoice>"#;
+
+fn open() -> (ValidationCache, std::path::PathBuf) {
+    let dir = std::env::temp_dir().join(format!("einv-cache-{}", uuid::Uuid::new_v4()));
+    (ValidationCache::open(&dir).unwrap(), dir)
+}
+
+#[test]
+fn results_are_reused_for_the_same_content_and_rules() {
+    let (cache, dir) = open();
+    let profile = ValidationProfile::peppol_bis3();
+    let fingerprint = ruleset_fingerprint(&profile, Language::En);
+    let report = validate_with_profile(INVOICE, &profile, Language::En);
+    cache.insert(&fingerprint, INVOICE, &report).unwrap();
+
+    let cached = cache.get(&fingerprint, INVOICE).unwrap();
+    assert_eq!(cached.issues.len(), report.issues.len());
+    assert!(cache
+        .get(&fingerprint, &INVOICE.replace("001", "002"))
+        .is_none());
+    assert!(cache.get("other", INVOICE).is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn results_of_other_rule_sets_are_pruned() {
+    let (cache, dir) = open();
+    let report = validate_with_profile(INVOICE, &ValidationProfile::peppol_bis3(), Language::En);
+    cache.insert("old", INVOICE, &report).unwrap();
+    cache.insert("new", INVOICE, &report).unwrap();
+
+    assert_eq!(cache.retain_fingerprint("new").unwrap(), 1);
+    assert!(cache.get("old", INVOICE).is_none());
+    assert!(cache.get("new", INVOICE).is_some());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn signed_documents_are_not_cached() {
+    let (cache, dir) = open();
+    let profile = ValidationProfile::peppol_bis3();
+    let fingerprint = ruleset_fingerprint(&profile, Language::En);
+    let report = validate_with_profile(SIGNED, &profile, Language::En);
+    cache.insert(&fingerprint, SIGNED, &report).unwrap();
+    assert!(cache.get(&fingerprint, SIGNED).is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fingerprint_changes_with_the_profile() {
+    let peppol = ruleset_fingerprint(&ValidationProfile::peppol_bis3(), Language::En);
+    assert_eq!(
+        peppol,
+        ruleset_fingerprint(&ValidationProfile::peppol_bis3(), Language::En)
+    );
+    assert_ne!(
+        peppol,
+        ruleset_fingerprint(&ValidationProfile::lv_b2g(), Language::En)
+    );
+
+    let mut adjusted = ValidationProfile::peppol_bis3();
+    adjusted.disabled_rules.push("BT-115".to_string());
+    assert_ne!(peppol, ruleset_fingerprint(&adjusted, Language::En));
+}
+
+#[test]
+fn fingerprint_changes_with_the_message_language() {
+    let profile = ValidationProfile::peppol_bis3();
+    assert_ne!(
+        ruleset_fingerprint(&profile, Language::En),
+        ruleset_fingerprint(&profile, Language::Lv)
+    );
+}
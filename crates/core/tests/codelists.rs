@@ -0,0 +1,93 @@
+use lat_einv_core::codelists::{CodeList, CodeLists};
+use lat_einv_core::validation::{validate_with_profile, Language, Severity, ValidationProfile};
+use std::path::PathBuf;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("einv-codelists-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn bundled_lists_contain_the_codes_in_use() {
+    let lists = CodeLists::bundled();
+    assert!(lists.contains(CodeList::Currency, "EUR"));
+    assert!(lists.contains(CodeList::Country, "LV"));
+    assert!(lists.contains(CodeList::InvoiceTypeCode, "380"));
+    assert!(lists.contains(CodeList::PaymentMeans, "30"));
+    assert!(lists.contains(CodeList::UnitOfMeasure, "C62"));
+    assert!(lists.contains(CodeList::ElectronicAddressScheme, "0218"));
+    assert!(lists.contains(CodeList::ElectronicAddressScheme, "9939"));
+
+    assert!(!lists.contains(CodeList::Currency, "XYZ"));
+    assert!(!lists.contains(CodeList::Currency, "eur"));
+    assert!(!lists.contains(CodeList::Country, "XX"));
+    assert!(!lists.contains(CodeList::InvoiceTypeCode, "999"));
+}
+
+#[test]
+fn vatex_codes_are_compared_case_insensitively() {
+    let lists = CodeLists::bundled();
+    assert!(lists.contains(CodeList::VatExemptionReason, "VATEX-EU-132"));
+    assert!(lists.contains(CodeList::VatExemptionReason, "vatex-eu-132"));
+    assert!(!lists.contains(CodeList::VatExemptionReason, "VATEX-EU-999"));
+}
+
+#[test]
+fn override_files_replace_only_their_list() {
+    let dir = temp_dir();
+    std::fs::write(
+        dir.join(CodeList::UnitOfMeasure.file_name()),
+        "# full list\nKTM kilometre\nC62 one\n",
+    )
+    .unwrap();
+
+    let lists = CodeLists::with_overrides(&dir).unwrap();
+    assert!(lists.contains(CodeList::UnitOfMeasure, "KTM"));
+    assert!(!lists.contains(CodeList::UnitOfMeasure, "HUR"));
+    assert!(lists.contains(CodeList::Currency, "EUR"));
+    assert_ne!(lists.fingerprint(), CodeLists::bundled().fingerprint());
+    assert_eq!(
+        CodeLists::bundled().fingerprint(),
+        CodeLists::bundled().fingerprint()
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn invoice(currency: &str, unit: &str) -> String {
+    format!(
+        r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+  <cbc:IssueDate>2026-10-01</cbc:IssueDate>
+  <cbc:InvoiceTypeCode>380</cbc:InvoiceTypeCode>
+  <cbc:DocumentCurrencyCode>{currency}</cbc:DocumentCurrencyCode>
+  <cac:InvoiceLine>
+    <cbc:InvoicedQuantity unitCode="{unit}">1</cbc:InvoicedQuantity>
+  </cac:InvoiceLine>
+</Invoice>"#
+    )
+}
+
+fn code_list_issues(xml: &str) -> Vec<(String, Severity)> {
+    validate_with_profile(xml, &ValidationProfile::peppol_bis3(), Language::En)
+        .issues
+        .into_iter()
+        .filter(|i| i.rule_id.starts_with("BR-CL-"))
+        .map(|i| (i.rule_id, i.severity))
+        .collect()
+}
+
+#[test]
+fn unknown_codes_are_reported() {
+    assert!(code_list_issues(&invoice("EUR", "C62")).is_empty());
+    assert_eq!(
+        code_list_issues(&invoice("EUX", "C62")),
+        [("BR-CL-04".to_string(), Severity::Error)]
+    );
+}
+
+#[test]
+fn unit_codes_missing_from_the_bundled_list_are_warnings() {
+    let issues = code_list_issues(&invoice("EUR", "KTM"));
+    assert_eq!(issues, [("BR-CL-23".to_string(), Severity::Warning)]);
+}
@@ -0,0 +1,143 @@
+use lat_einv_core::validation::{
+    Condition, CustomRule, CustomRuleSpec, FieldCondition, Language, RuleRegistry, Severity,
+    ValidationProfile,
+};
+use std::sync::Arc;
+
+fn invoice(order: Option<&str>, currency: &str, buyer: &str) -> String {
+    let order = order
+        .map(|id| format!("<cac:OrderReference><cbc:ID>{id}</cbc:ID></cac:OrderReference>"))
+        .unwrap_or_default();
+    format!(
+        r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+  <cbc:DocumentCurrencyCode>{currency}</cbc:DocumentCurrencyCode>
+  {order}
+  <cac:AccountingCustomerParty>
+    <cac:Party>
+      <cbc:EndpointID schemeID="0218">{buyer}</cbc:EndpointID>
+    </cac:Party>
+  </cac:AccountingCustomerParty>
+  <cac:LegalMonetaryTotal>
+    <cbc:PayableAmount currencyID="EUR">121.00</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+</Invoice>"#
+    )
+}
+
+fn spec(field: &str, condition: Condition) -> CustomRuleSpec {
+    CustomRuleSpec {
+        id: "ACME-01".to_string(),
+        field: field.to_string(),
+        condition,
+        message: "Value '{0}' is not accepted".to_string(),
+        severity: Severity::Error,
+        when: None,
+        receivers: Vec::new(),
+    }
+}
+
+/// Messages reported by the custom rule `spec` for `xml`
+fn findings(spec: CustomRuleSpec, xml: &str) -> Vec<String> {
+    let mut registry = RuleRegistry::new();
+    registry.register(Arc::new(CustomRule::new(spec).unwrap()));
+    registry
+        .validate(xml, &ValidationProfile::peppol_bis3(), Language::En)
+        .issues
+        .into_iter()
+        .map(|i| i.message)
+        .collect()
+}
+
+#[test]
+fn required_fields_are_found_by_business_term_or_path() {
+    let with_order = invoice(Some("PO-1"), "EUR", "40003032949");
+    let without_order = invoice(None, "EUR", "40003032949");
+    for field in ["BT-13", "bt-13", "OrderReference/ID"] {
+        assert!(findings(spec(field, Condition::Required), &with_order).is_empty());
+        assert_eq!(
+            findings(spec(field, Condition::Required), &without_order),
+            ["Value '' is not accepted"]
+        );
+    }
+}
+
+#[test]
+fn values_are_checked_against_the_condition() {
+    let xml = invoice(Some("PO-1"), "EUR", "40003032949");
+    let regex = Condition::Regex("^PO-[0-9]{4}$".to_string());
+    assert_eq!(
+        findings(spec("BT-13", regex), &xml),
+        ["Value 'PO-1' is not accepted"]
+    );
+
+    let one_of = Condition::OneOf(vec!["EUR".to_string()]);
+    assert!(findings(spec("BT-5", one_of), &xml).is_empty());
+
+    let range = Condition::Range {
+        min: Some(0.0),
+        max: Some(100.0),
+    };
+    assert_eq!(
+        findings(spec("BT-115", range), &xml),
+        ["Value '121.00' is not accepted"]
+    );
+
+    // Only `required` reports missing fields
+    let absent = Condition::OneOf(vec!["X".to_string()]);
+    assert!(findings(spec("BT-10", absent), &xml).is_empty());
+}
+
+#[test]
+fn rules_apply_only_when_their_condition_holds() {
+    let mut usd_only = spec("BT-13", Condition::Required);
+    usd_only.when = Some(FieldCondition {
+        field: "BT-5".to_string(),
+        equals: Some("USD".to_string()),
+    });
+    assert!(findings(usd_only.clone(), &invoice(None, "EUR", "40003032949")).is_empty());
+    assert_eq!(
+        findings(usd_only, &invoice(None, "USD", "40003032949")).len(),
+        1
+    );
+}
+
+#[test]
+fn rules_apply_only_to_their_receivers() {
+    let mut acme = spec("BT-13", Condition::Required);
+    acme.receivers = vec!["0218:40003032949".to_string()];
+    assert_eq!(
+        findings(acme.clone(), &invoice(None, "EUR", "40003032949")).len(),
+        1
+    );
+    assert!(findings(acme, &invoice(None, "EUR", "40003245752")).is_empty());
+}
+
+#[test]
+fn custom_rules_keep_their_severity_and_group() {
+    let mut warning = spec("BT-13", Condition::Required);
+    warning.severity = Severity::Warning;
+    let rule = CustomRule::new(warning).unwrap();
+    let mut registry = RuleRegistry::new();
+    registry.register(Arc::new(rule));
+
+    let xml = invoice(None, "EUR", "40003032949");
+    let report = registry.validate(&xml, &ValidationProfile::peppol_bis3(), Language::En);
+    assert_eq!(report.issues[0].severity, Severity::Warning);
+
+    let mut without_custom = ValidationProfile::peppol_bis3();
+    without_custom.groups.retain(|g| g != "custom");
+    assert!(registry
+        .validate(&xml, &without_custom, Language::En)
+        .issues
+        .is_empty());
+}
+
+#[test]
+fn invalid_specs_are_rejected() {
+    assert!(CustomRule::new(spec("BT-13", Condition::Regex("(".to_string()))).is_err());
+    assert!(CustomRule::new(spec(" / ", Condition::Required)).is_err());
+    let mut no_id = spec("BT-13", Condition::Required);
+    no_id.id = " ".to_string();
+    assert!(CustomRule::new(no_id).is_err());
+}
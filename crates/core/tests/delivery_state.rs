@@ -0,0 +1,40 @@
+use lat_einv_core::models::DeliveryState::{self, *};
+
+#[test]
+fn jobs_move_forward_and_retry_after_failure() {
+    assert!(Queued.can_transition_to(InFlight));
+    assert!(InFlight.can_transition_to(Sent));
+    assert!(Sent.can_transition_to(Delivered));
+    assert!(Sent.can_transition_to(Rejected));
+    assert!(InFlight.can_transition_to(Queued));
+    assert!(Failed.can_transition_to(Queued));
+    assert!(Failed.can_transition_to(DeadLetter));
+    assert!(DeadLetter.can_transition_to(Queued));
+    assert!(Queued.can_transition_to(Cancelled));
+
+    assert!(!Queued.can_transition_to(Sent));
+    assert!(!Sent.can_transition_to(InFlight));
+    assert!(!Failed.can_transition_to(Delivered));
+    assert!(!InFlight.can_transition_to(Cancelled));
+    assert!(!DeadLetter.can_transition_to(Sent));
+    assert!(DeliveryState::ALL.iter().all(|s| !s.can_transition_to(*s)));
+}
+
+#[test]
+fn delivered_rejected_and_cancelled_are_final() {
+    let finals: Vec<_> = DeliveryState::ALL
+        .into_iter()
+        .filter(|s| s.is_final())
+        .collect();
+    assert_eq!(finals, [Delivered, Rejected, Cancelled]);
+}
+
+#[test]
+fn states_serialize_as_stored_names() {
+    for state in DeliveryState::ALL {
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(json, format!("\"{}\"", state));
+    }
+    let legacy: DeliveryState = serde_json::from_str("\"pending\"").unwrap();
+    assert_eq!(legacy, Sent);
+}
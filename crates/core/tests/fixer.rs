@@ -0,0 +1,150 @@
+use lat_einv_core::fixer::{apply_fixes, fix_document, fixed_path, propose_fixes, Fix, FixOptions};
+use std::path::Path;
+
+const INVOICE: &str = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID> INV-001 </cbc:ID>
+  <cbc:DocumentCurrencyCode>eur</cbc:DocumentCurrencyCode>
+  <cac:AccountingSupplierParty>
+    <cac:Party>
+      <cbc:EndpointID>LV40003032949</cbc:EndpointID>
+    </cac:Party>
+  </cac:AccountingSupplierParty>
+  <cac:AccountingCustomerParty>
+    <cac:Party>
+      <cbc:EndpointID>40003245752</cbc:EndpointID>
+    </cac:Party>
+  </cac:AccountingCustomerParty>
+  <cac:LegalMonetaryTotal>
+    <cbc:TaxExclusiveAmount currencyID="eur">100.000</cbc:TaxExclusiveAmount>
+    <cbc:PayableAmount currencyID="EUR">121.005</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+</Invoice>"#;
+
+fn fix(start: usize, end: usize, replacement: &str) -> Fix {
+    Fix {
+        rule_id: None,
+        description: String::new(),
+        location: String::new(),
+        safe: true,
+        start,
+        end,
+        replacement: replacement.to_string(),
+    }
+}
+
+#[test]
+fn fixes_are_proposed_in_document_order() {
+    let fixes: Vec<(Option<String>, String, bool)> = propose_fixes(INVOICE)
+        .unwrap()
+        .into_iter()
+        .map(|f| (f.rule_id, f.replacement, f.safe))
+        .collect();
+    let expected = [
+        (None, "INV-001", true),
+        (Some("BR-CL-04"), "EUR", true),
+        (Some("BR-62"), " schemeID=\"9939\"", false),
+        (Some("BR-63"), " schemeID=\"0218\"", false),
+        (None, "EUR", true),
+        (Some("BR-DEC"), "100.00", true),
+        (Some("BR-DEC"), "121.01", false),
+    ];
+    let expected: Vec<_> = expected
+        .into_iter()
+        .map(|(rule, replacement, safe)| (rule.map(str::to_string), replacement.to_string(), safe))
+        .collect();
+    assert_eq!(fixes, expected);
+}
+
+#[test]
+fn only_safe_fixes_are_applied_by_default() {
+    let outcome = fix_document(INVOICE, "a.xml", &FixOptions::default()).unwrap();
+    assert_eq!(outcome.applied.len(), 4);
+    assert_eq!(outcome.skipped.len(), 3);
+    assert!(outcome.skipped.iter().all(|f| !f.safe));
+
+    let fixed = &outcome.fixed;
+    assert!(fixed.contains("<cbc:ID>INV-001</cbc:ID>"));
+    assert!(fixed.contains("<cbc:DocumentCurrencyCode>EUR</cbc:DocumentCurrencyCode>"));
+    assert!(fixed.contains(r#"<cbc:TaxExclusiveAmount currencyID="EUR">100.00<"#));
+    assert!(fixed.contains(">121.005<"));
+    assert!(fixed.contains("<cbc:EndpointID>40003245752</cbc:EndpointID>"));
+    assert!(outcome.diff.contains("-  <cbc:ID> INV-001 </cbc:ID>"));
+    assert!(outcome.diff.contains("+  <cbc:ID>INV-001</cbc:ID>"));
+
+    // Everything else is untouched
+    assert_eq!(fixed.len(), INVOICE.len() - 2 - 1);
+}
+
+#[test]
+fn unsafe_fixes_round_and_add_schemes_on_request() {
+    let options = FixOptions {
+        include_unsafe: true,
+        rules: Vec::new(),
+    };
+    let outcome = fix_document(INVOICE, "a.xml", &options).unwrap();
+    assert!(outcome.skipped.is_empty());
+    assert!(outcome.fixed.contains(r#"currencyID="EUR">121.01<"#));
+    assert!(outcome
+        .fixed
+        .contains(r#"<cbc:EndpointID schemeID="9939">LV40003032949<"#));
+    assert!(outcome
+        .fixed
+        .contains(r#"<cbc:EndpointID schemeID="0218">40003245752<"#));
+}
+
+#[test]
+fn rounding_is_half_away_from_zero() {
+    let amount = |value: &str| {
+        let xml = format!(
+            r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2"><cbc:PayableAmount currencyID="EUR">{value}</cbc:PayableAmount></Invoice>"#
+        );
+        propose_fixes(&xml)
+            .unwrap()
+            .pop()
+            .map(|f| (f.replacement, f.safe))
+    };
+    assert_eq!(amount("-0.125"), Some(("-0.13".to_string(), false)));
+    assert_eq!(amount("0.994"), Some(("0.99".to_string(), false)));
+    assert_eq!(amount("9.995"), Some(("10.00".to_string(), false)));
+    assert_eq!(amount("1.5000"), Some(("1.50".to_string(), true)));
+    assert_eq!(amount("1.50"), None);
+}
+
+#[test]
+fn fixes_can_be_limited_to_rules() {
+    let options = FixOptions {
+        include_unsafe: true,
+        rules: vec!["BR-63".to_string()],
+    };
+    let outcome = fix_document(INVOICE, "a.xml", &options).unwrap();
+    assert_eq!(outcome.applied.len(), 1);
+    assert_eq!(outcome.applied[0].rule_id.as_deref(), Some("BR-63"));
+}
+
+#[test]
+fn overlapping_and_invalid_fixes_are_not_applied() {
+    let xml = "abcdef";
+    let fixes = [
+        fix(3, 5, "X"),
+        fix(1, 4, "Y"),
+        fix(4, 6, "Z"),
+        fix(6, 6, "!"),
+        fix(5, 9, "out of range"),
+    ];
+    let (fixed, applied) = apply_fixes(xml, &fixes);
+    assert_eq!(fixed, "aYZ!");
+    let ranges: Vec<_> = applied.iter().map(|f| (f.start, f.end)).collect();
+    assert_eq!(ranges, [(1, 4), (4, 6), (6, 6)]);
+
+    // Insertions at the same position are all kept, in order
+    let (fixed, _) = apply_fixes(xml, &[fix(2, 2, "1"), fix(2, 2, "2")]);
+    assert_eq!(fixed, "ab12cdef");
+}
+
+#[test]
+fn fixed_copies_are_written_beside_the_original() {
+    assert_eq!(
+        fixed_path(Path::new("in/invoice.xml")),
+        Path::new("in/invoice.fixed.xml")
+    );
+}
@@ -0,0 +1,245 @@
+use lat_einv_core::validation::{
+    is_valid_eadrese, is_valid_personal_code, is_valid_registration_number, is_valid_vat_number,
+    validate_with_profile, Language, ValidationProfile,
+};
+
+#[test]
+fn registration_numbers_need_a_valid_checksum() {
+    assert!(is_valid_registration_number("40003032949"));
+    assert!(is_valid_registration_number("40003245752"));
+    assert!(is_valid_registration_number(" 90000010008 "));
+
+    assert!(!is_valid_registration_number("40003032948"));
+    assert!(!is_valid_registration_number("40003000000"));
+    assert!(!is_valid_registration_number("4000303294"));
+    assert!(!is_valid_registration_number("4000303294X"));
+    // Only legal entities start with 4-9
+    assert!(!is_valid_registration_number("10003032949"));
+}
+
+#[test]
+fn personal_codes_need_a_birth_date_and_checksum() {
+    assert!(is_valid_personal_code("010190-12349"));
+    assert!(is_valid_personal_code("01019012349"));
+    assert!(is_valid_personal_code("311299-12345"));
+    assert!(is_valid_personal_code("150785-11110"));
+    // 29 February of a leap year
+    assert!(is_valid_personal_code("290200-21239"));
+
+    assert!(!is_valid_personal_code("010190-12340"));
+    // 29 February 1800 and 2001, 31 April
+    assert!(!is_valid_personal_code("290200-00008"));
+    assert!(!is_valid_personal_code("290201-21239"));
+    assert!(!is_valid_personal_code("310490-12349"));
+    assert!(!is_valid_personal_code("010190-32349"));
+    assert!(!is_valid_personal_code("010190-1234"));
+}
+
+#[test]
+fn personal_code_check_digit_wraps_from_ten_to_zero() {
+    // 1 + weighted sum is 10 modulo 11, so the check digit is 0
+    assert!(is_valid_personal_code("010190-10010"));
+    assert!(!is_valid_personal_code("010190-10011"));
+}
+
+#[test]
+fn new_personal_codes_are_only_checked_for_length() {
+    assert!(is_valid_personal_code("321234-56789"));
+    assert!(is_valid_personal_code("32000000000"));
+    assert!(!is_valid_personal_code("32123456789-0"));
+    assert!(!is_valid_personal_code("331234-56789"));
+}
+
+#[test]
+fn vat_numbers_are_lv_and_a_registration_number_or_personal_code() {
+    assert!(is_valid_vat_number("LV40003032949"));
+    assert!(is_valid_vat_number("LV01019012349"));
+
+    assert!(!is_valid_vat_number("40003032949"));
+    assert!(!is_valid_vat_number("LV40003032948"));
+    assert!(!is_valid_vat_number("EE40003032949"));
+    assert!(!is_valid_vat_number("lv40003032949"));
+}
+
+#[test]
+fn eadrese_has_a_mailbox_and_a_valid_owner() {
+    assert!(is_valid_eadrese("_DEFAULT@40003032949"));
+    assert!(is_valid_eadrese("_PRIVATE@010190-12349"));
+    assert!(is_valid_eadrese("_INVOICES_2@40003245752"));
+
+    assert!(!is_valid_eadrese("DEFAULT@40003032949"));
+    assert!(!is_valid_eadrese("_@40003032949"));
+    assert!(!is_valid_eadrese("_default@40003032949"));
+    assert!(!is_valid_eadrese("_DEFAULT@40003032948"));
+    assert!(!is_valid_eadrese("_DEFAULT40003032949"));
+}
+
+struct Parties<'a> {
+    seller_vat: &'a str,
+    seller_reg_no: &'a str,
+    buyer_country: &'a str,
+    buyer_id: &'a str,
+    buyer_endpoint: (&'a str, &'a str),
+    rate: &'a str,
+}
+
+const VALID: Parties = Parties {
+    seller_vat: "LV40003032949",
+    seller_reg_no: "40003032949",
+    buyer_country: "LV",
+    buyer_id: "010190-12349",
+    buyer_endpoint: ("0218", "40003245752"),
+    rate: "21",
+};
+
+fn invoice(p: &Parties) -> String {
+    format!(
+        r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+  <cac:AccountingSupplierParty>
+    <cac:Party>
+      <cbc:EndpointID>_DEFAULT@40003032949</cbc:EndpointID>
+      <cac:PostalAddress><cac:Country><cbc:IdentificationCode>LV</cbc:IdentificationCode></cac:Country></cac:PostalAddress>
+      <cac:PartyTaxScheme><cbc:CompanyID>{}</cbc:CompanyID></cac:PartyTaxScheme>
+      <cac:PartyLegalEntity><cbc:CompanyID>{}</cbc:CompanyID></cac:PartyLegalEntity>
+    </cac:Party>
+  </cac:AccountingSupplierParty>
+  <cac:AccountingCustomerParty>
+    <cac:Party>
+      <cbc:EndpointID schemeID="{}">{}</cbc:EndpointID>
+      <cac:PostalAddress><cac:Country><cbc:IdentificationCode>{}</cbc:IdentificationCode></cac:Country></cac:PostalAddress>
+      <cac:PartyLegalEntity><cbc:CompanyID>{}</cbc:CompanyID></cac:PartyLegalEntity>
+    </cac:Party>
+  </cac:AccountingCustomerParty>
+  <cac:TaxTotal>
+    <cac:TaxSubtotal><cac:TaxCategory><cbc:Percent>{}</cbc:Percent></cac:TaxCategory></cac:TaxSubtotal>
+  </cac:TaxTotal>
+</Invoice>"#,
+        p.seller_vat,
+        p.seller_reg_no,
+        p.buyer_endpoint.0,
+        p.buyer_endpoint.1,
+        p.buyer_country,
+        p.buyer_id,
+        p.rate
+    )
+}
+
+/// IDs of the Latvian rules that report `xml`
+fn lv_findings(xml: &str) -> Vec<String> {
+    validate_with_profile(xml, &ValidationProfile::lv_b2g(), Language::En)
+        .issues
+        .into_iter()
+        .map(|i| i.rule_id)
+        .filter(|id| id.starts_with("LV-"))
+        .collect()
+}
+
+#[test]
+fn valid_latvian_invoice_passes_the_lv_rules() {
+    assert!(lv_findings(&invoice(&VALID)).is_empty());
+}
+
+#[test]
+fn invalid_latvian_identifiers_are_reported_by_their_rule() {
+    let cases = [
+        (
+            Parties {
+                seller_vat: "LV4000303294",
+                ..VALID
+            },
+            "LV-01",
+        ),
+        (
+            Parties {
+                seller_vat: "LV40003032948",
+                ..VALID
+            },
+            "LV-02",
+        ),
+        (
+            Parties {
+                seller_reg_no: "40003032948",
+                ..VALID
+            },
+            "LV-03",
+        ),
+        (
+            Parties {
+                buyer_id: "010190-12340",
+                ..VALID
+            },
+            "LV-04",
+        ),
+        (
+            Parties {
+                rate: "18",
+                ..VALID
+            },
+            "LV-05",
+        ),
+        (
+            Parties {
+                buyer_endpoint: ("", "_DEFAULT@40003245753"),
+                ..VALID
+            },
+            "LV-06",
+        ),
+        (
+            Parties {
+                buyer_endpoint: ("0218", "40003245753"),
+                ..VALID
+            },
+            "LV-07",
+        ),
+        (
+            Parties {
+                buyer_endpoint: ("9939", "LV40003245753"),
+                ..VALID
+            },
+            "LV-08",
+        ),
+    ];
+    for (parties, rule) in cases {
+        assert_eq!(lv_findings(&invoice(&parties)), [rule]);
+    }
+}
+
+#[test]
+fn foreign_registration_numbers_are_not_checked() {
+    // An Estonian registry code
+    let foreign = Parties {
+        buyer_country: "EE",
+        buyer_id: "10137319",
+        buyer_endpoint: ("0191", "10137319"),
+        ..VALID
+    };
+    assert!(lv_findings(&invoice(&foreign)).is_empty());
+
+    let latvian = Parties {
+        buyer_id: "40003245753",
+        ..VALID
+    };
+    assert_eq!(lv_findings(&invoice(&latvian)), ["LV-03"]);
+    // The Latvian register's scheme marks a Latvian number wherever the
+    // buyer is
+    let xml = invoice(&Parties {
+        buyer_country: "EE",
+        ..latvian
+    })
+    .replace(
+        "<cbc:CompanyID>40003245753",
+        r#"<cbc:CompanyID schemeID="0218">40003245753"#,
+    );
+    assert_eq!(lv_findings(&xml), ["LV-03"]);
+}
+
+#[test]
+fn lv_rules_only_run_in_profiles_with_the_lv_group() {
+    let xml = invoice(&Parties {
+        seller_vat: "LV40003032948",
+        ..VALID
+    });
+    let report = validate_with_profile(&xml, &ValidationProfile::peppol_bis3(), Language::En);
+    assert!(report.issues.iter().all(|i| !i.rule_id.starts_with("LV-")));
+}
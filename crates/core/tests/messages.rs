@@ -0,0 +1,110 @@
+use lat_einv_core::validation::{
+    localized, validate_with_profile, Language, MessageCatalog, RuleRegistry, ValidationIssue,
+    ValidationProfile,
+};
+
+#[test]
+fn catalogs_skip_comments_and_keep_equals_signs_in_messages() {
+    let catalog = MessageCatalog::parse(
+        "# comment\n\n  BT-1 = Invoice number is mandatory  \nBR-CO-10 = Sum {0} = lines\nno separator\n",
+    );
+    assert_eq!(catalog.get("BT-1"), Some("Invoice number is mandatory"));
+    assert_eq!(catalog.get("BR-CO-10"), Some("Sum {0} = lines"));
+    assert_eq!(catalog.get("# comment"), None);
+    assert_eq!(catalog.get("no separator"), None);
+}
+
+#[test]
+fn every_builtin_rule_is_translated_with_its_placeholders() {
+    let en = MessageCatalog::bundled(Language::En);
+    let lv = MessageCatalog::bundled(Language::Lv);
+    for rule in RuleRegistry::with_builtin_rules().rules() {
+        let id = rule.id();
+        let (Some(en_message), Some(lv_message)) = (en.get(id), lv.get(id)) else {
+            panic!("{id} is missing from a catalog");
+        };
+        assert_eq!(
+            en_message.contains("{0}"),
+            lv_message.contains("{0}"),
+            "{id} placeholders differ"
+        );
+    }
+}
+
+#[test]
+fn languages_serialize_as_their_codes() {
+    assert_eq!(serde_json::to_string(&Language::Lv).unwrap(), "\"lv\"");
+    assert_eq!(
+        serde_json::from_str::<Language>("\"en\"").unwrap(),
+        Language::En
+    );
+    assert_eq!(Language::Lv.to_string(), "lv");
+}
+
+#[test]
+fn reports_use_the_given_language() {
+    let xml = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2"/>"#;
+    let message = |language| {
+        validate_with_profile(xml, &ValidationProfile::peppol_bis3(), language)
+            .issues
+            .into_iter()
+            .find(|i| i.rule_id == "BT-1")
+            .unwrap()
+            .message
+    };
+
+    assert_eq!(message(Language::En), "Invoice number is mandatory");
+    assert_eq!(message(Language::Lv), "Rēķina numurs ir obligāts");
+    // Rules missing from the catalog keep their own message
+    assert_eq!(
+        localized(Language::Lv, "ACME-01", "Order reference"),
+        "Order reference"
+    );
+    assert_eq!(
+        localized(Language::En, "BT-1", "fallback"),
+        "Invoice number is mandatory"
+    );
+}
+
+#[test]
+fn issues_are_rendered_again_in_another_language() {
+    let xml = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2"><cbc:DocumentCurrencyCode xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">EUX</cbc:DocumentCurrencyCode></Invoice>"#;
+    let report = validate_with_profile(xml, &ValidationProfile::peppol_bis3(), Language::En);
+    let issue = report
+        .issues
+        .iter()
+        .find(|i| i.rule_id == "BR-CL-04")
+        .unwrap();
+    assert_eq!(issue.message, "Currency code 'EUX' is not in ISO 4217");
+    let lv = MessageCatalog::bundled(Language::Lv)
+        .get("BR-CL-04")
+        .unwrap();
+    assert_eq!(issue.message_in(Language::Lv), lv.replace("{0}", "EUX"));
+    assert_eq!(issue.message_in(Language::En), issue.message);
+
+    // Issues stored without their args keep their message
+    let stored = ValidationIssue {
+        args: Vec::new(),
+        ..issue.clone()
+    };
+    assert_eq!(stored.message_in(Language::Lv), issue.message);
+}
+
+#[test]
+fn catalogs_have_the_same_entries() {
+    let ids = |language| {
+        let text = match language {
+            Language::En => include_str!("../messages/en.txt"),
+            Language::Lv => include_str!("../messages/lv.txt"),
+        };
+        let mut ids: Vec<String> = text
+            .lines()
+            .filter(|l| !l.trim_start().starts_with('#'))
+            .filter_map(|l| l.split_once('='))
+            .map(|(id, _)| id.trim().to_string())
+            .collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(ids(Language::En), ids(Language::Lv));
+}
@@ -0,0 +1,137 @@
+use lat_einv_core::validation::{
+    rule_pack_group, Finding, Language, RuleRegistry, Severity, ValidationContext,
+    ValidationProfile, ValidationRule,
+};
+use std::sync::Arc;
+
+const INVOICE: &str = r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+</Invoice>"#;
+
+/// Requires an order reference, as a company-specific rule would
+struct OrderReferenceRequired;
+
+impl ValidationRule for OrderReferenceRequired {
+    fn id(&self) -> &str {
+        "ACME-01"
+    }
+
+    fn group(&self) -> &str {
+        "acme"
+    }
+
+    fn message(&self) -> &str {
+        "Order reference is required for {0}"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, ctx: &ValidationContext) -> Vec<Finding> {
+        if ctx.xml.contains("OrderReference") {
+            Vec::new()
+        } else {
+            vec![Finding::new().at("OrderReference").with_arg("ACME")]
+        }
+    }
+}
+
+fn profile(groups: &[&str]) -> ValidationProfile {
+    let mut profile = ValidationProfile::peppol_bis3();
+    profile.name = "test".to_string();
+    profile.groups = groups.iter().map(|g| g.to_string()).collect();
+    profile
+}
+
+fn rule_ids(registry: &RuleRegistry, profile: &ValidationProfile) -> Vec<String> {
+    registry
+        .validate(INVOICE, profile, Language::En)
+        .issues
+        .into_iter()
+        .map(|i| i.rule_id)
+        .collect()
+}
+
+#[test]
+fn profiles_select_rules_by_group() {
+    let registry = RuleRegistry::with_builtin_rules();
+    let ids = rule_ids(&registry, &profile(&["en16931"]));
+    assert!(ids.contains(&"BT-2".to_string()));
+    assert!(ids.iter().all(|id| !id.starts_with("BR-CL-")));
+
+    assert!(rule_ids(&registry, &profile(&[])).is_empty());
+}
+
+#[test]
+fn profiles_add_single_rules_and_disable_others() {
+    let registry = RuleRegistry::with_builtin_rules();
+    let mut only_bt2 = profile(&[]);
+    only_bt2.rules.push("BT-2".to_string());
+    assert_eq!(rule_ids(&registry, &only_bt2), ["BT-2"]);
+
+    let mut without_bt2 = profile(&["en16931"]);
+    without_bt2.disabled_rules.push("BT-2".to_string());
+    assert!(!rule_ids(&registry, &without_bt2).contains(&"BT-2".to_string()));
+}
+
+#[test]
+fn severities_can_be_overridden_and_warnings_made_errors() {
+    let mut registry = RuleRegistry::with_builtin_rules();
+    registry.register(Arc::new(OrderReferenceRequired));
+
+    let mut acme = profile(&["acme"]);
+    let report = registry.validate(INVOICE, &acme, Language::En);
+    assert_eq!(report.issues[0].severity, Severity::Warning);
+    assert_eq!(
+        report.issues[0].message,
+        "Order reference is required for ACME"
+    );
+    assert!(report.is_valid());
+
+    acme.warnings_as_errors = true;
+    assert!(!registry.validate(INVOICE, &acme, Language::En).is_valid());
+
+    acme.warnings_as_errors = false;
+    acme.severity_overrides
+        .insert("ACME-01".to_string(), Severity::Info);
+    let report = registry.validate(INVOICE, &acme, Language::En);
+    assert_eq!(report.issues[0].severity, Severity::Info);
+}
+
+#[test]
+fn registering_a_rule_id_again_replaces_the_rule() {
+    let mut registry = RuleRegistry::with_builtin_rules();
+    let count = registry.rules().count();
+    registry.register(Arc::new(OrderReferenceRequired));
+    registry.register(Arc::new(OrderReferenceRequired));
+    assert_eq!(registry.rules().count(), count + 1);
+    assert_eq!(registry.get("ACME-01").unwrap().group(), "acme");
+}
+
+#[test]
+fn builtin_profiles_are_found_by_name() {
+    for name in ["peppol-bis3", "lv-b2g", "internal-strict"] {
+        assert_eq!(ValidationProfile::builtin(name).unwrap().name, name);
+    }
+    assert!(ValidationProfile::builtin("PEPPOL-BIS3").is_none());
+    assert!(ValidationProfile::lv_b2g().includes("LV-01", "lv"));
+    assert!(!ValidationProfile::peppol_bis3().includes("LV-01", "lv"));
+    assert!(ValidationProfile::internal_strict().warnings_as_errors);
+}
+
+#[test]
+fn rule_packs_name_the_lv_group() {
+    assert_eq!(rule_pack_group("lv").unwrap(), "lv");
+    assert_eq!(rule_pack_group("LV").unwrap(), "lv");
+    assert_eq!(rule_pack_group("Latvia").unwrap(), "lv");
+    assert!(rule_pack_group("latvija").is_err());
+    assert!(rule_pack_group("").is_err());
+
+    let profile = ValidationProfile::peppol_bis3()
+        .with_rule_pack("LV")
+        .unwrap()
+        .with_rule_pack("latvia")
+        .unwrap();
+    assert_eq!(profile.groups.iter().filter(|g| *g == "lv").count(), 1);
+}
@@ -0,0 +1,145 @@
+use chrono::{TimeZone, Utc};
+use lat_einv_core::validation::{
+    to_html, to_json, to_svrl, write_reports, ExportFormat, FileReport, Language, Severity,
+    ValidationIssue, ValidationReport,
+};
+
+fn issue(rule_id: &str, severity: Severity, message: &str, args: &[&str]) -> ValidationIssue {
+    ValidationIssue {
+        rule_id: rule_id.to_string(),
+        severity,
+        message: message.to_string(),
+        location: Some("LegalMonetaryTotal/PayableAmount".to_string()),
+        args: args.iter().map(|a| a.to_string()).collect(),
+    }
+}
+
+fn files() -> Vec<FileReport> {
+    vec![
+        FileReport {
+            path: "in/a.xml".to_string(),
+            report: ValidationReport {
+                profile: "peppol-bis3".to_string(),
+                issues: vec![
+                    // A company-specific rule, which keeps its message
+                    issue(
+                        "ACME-01",
+                        Severity::Error,
+                        "Payable amount <missing> & \"x\"",
+                        &[],
+                    ),
+                    issue(
+                        "BR-CL-23",
+                        Severity::Warning,
+                        "Unit code 'KTM' is not in UN/ECE Recommendation 20 or 21",
+                        &["KTM"],
+                    ),
+                ],
+                signatures: Vec::new(),
+            },
+        },
+        FileReport {
+            path: "other/a.xml".to_string(),
+            report: ValidationReport {
+                profile: "peppol-bis3".to_string(),
+                issues: vec![issue(
+                    "BR-CL-23",
+                    Severity::Warning,
+                    "Unit code 'KTM' is not in UN/ECE Recommendation 20 or 21",
+                    &["KTM"],
+                )],
+                signatures: Vec::new(),
+            },
+        },
+    ]
+}
+
+#[test]
+fn json_report_summarizes_all_files() {
+    let at = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&to_json(&files(), at, Language::En).unwrap()).unwrap();
+    assert_eq!(json["generated_at"], "2026-10-01T12:00:00Z");
+    assert_eq!(
+        json["summary"],
+        serde_json::json!({"files": 2, "valid": 1, "invalid": 1, "errors": 1, "warnings": 2})
+    );
+    assert_eq!(json["files"][0]["valid"], false);
+    assert_eq!(json["files"][1]["valid"], true);
+    assert_eq!(json["files"][0]["issues"][0]["rule_id"], "ACME-01");
+    assert_eq!(json["files"][0]["issues"][0]["severity"], "error");
+}
+
+#[test]
+fn svrl_reports_each_issue_as_an_escaped_failed_assert() {
+    let svrl = to_svrl(&files()[0], Language::En);
+    let doc = roxmltree::Document::parse(&svrl).unwrap();
+    let asserts: Vec<_> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name(("http://purl.oclc.org/dsdl/svrl", "failed-assert")))
+        .collect();
+    assert_eq!(asserts.len(), 2);
+    assert_eq!(asserts[0].attribute("id"), Some("ACME-01"));
+    assert_eq!(asserts[0].attribute("flag"), Some("fatal"));
+    assert_eq!(asserts[1].attribute("flag"), Some("warning"));
+    assert_eq!(
+        asserts[0].first_element_child().unwrap().text(),
+        Some("Payable amount <missing> & \"x\"")
+    );
+}
+
+#[test]
+fn html_report_escapes_messages() {
+    let at = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+    let html = to_html(&files(), at, Language::En);
+    assert!(html.contains("2 files: 1 valid, 1 invalid; 1 errors, 2 warnings"));
+    assert!(html.contains("Payable amount &lt;missing&gt; &amp;"));
+    assert!(!html.contains("<missing>"));
+}
+
+#[test]
+fn reports_are_rendered_in_the_given_language() {
+    let at = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+    let unit_code = "Mērvienības kods 'KTM' nav UN/ECE 20. vai 21. rekomendācijā";
+
+    let svrl = to_svrl(&files()[0], Language::Lv);
+    let doc = roxmltree::Document::parse(&svrl).unwrap();
+    let texts: Vec<_> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name(("http://purl.oclc.org/dsdl/svrl", "text")))
+        .map(|n| n.text().unwrap())
+        .collect();
+    assert_eq!(texts, ["Payable amount <missing> & \"x\"", unit_code]);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&to_json(&files(), at, Language::Lv).unwrap()).unwrap();
+    assert_eq!(json["files"][1]["issues"][0]["message"], unit_code);
+
+    let html = to_html(&files(), at, Language::Lv);
+    assert!(html.contains("<html lang=\"lv\">"));
+    assert!(html.contains("<h1>Validācijas pārskats</h1>"));
+    assert!(html.contains("Faili: 2, derīgi: 1, nederīgi: 1; kļūdas: 1, brīdinājumi: 2."));
+    assert!(html.contains(&unit_code.replace('\'', "&apos;")));
+}
+
+#[test]
+fn reports_are_written_with_unique_svrl_names() {
+    let dir = std::env::temp_dir().join(format!("einv-reports-{}", uuid::Uuid::new_v4()));
+    let written = write_reports(&files(), &dir, &ExportFormat::ALL, Language::En).unwrap();
+    let mut names: Vec<String> = written
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "a-2.svrl.xml",
+            "a.svrl.xml",
+            "validation-report.html",
+            "validation-report.json"
+        ]
+    );
+    assert!(written.iter().all(|p| p.exists()));
+    std::fs::remove_dir_all(dir).unwrap();
+}
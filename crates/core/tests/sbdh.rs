@@ -0,0 +1,103 @@
+use chrono::{TimeZone, Utc};
+use lat_einv_core::duplicates::DuplicateKey;
+use lat_einv_core::sbdh::{self, Identifier, Sbdh, BILLING_PROCESS};
+use lat_einv_core::validation::{validate_with_profile, Language, ValidationProfile};
+
+const INVOICE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:CustomizationID>urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0</cbc:CustomizationID>
+  <cbc:ProfileID>urn:fdc:peppol.eu:2017:poacc:billing:01:1.0</cbc:ProfileID>
+  <cbc:ID>INV-001</cbc:ID>
+  <cbc:IssueDate>2026-10-01</cbc:IssueDate>
+  <cbc:DocumentCurrencyCode>EUR</cbc:DocumentCurrencyCode>
+  <cac:AccountingSupplierParty>
+    <cac:Party>
+      <cbc:EndpointID schemeID="9939">LV40003521600</cbc:EndpointID>
+      <cac:PartyName><cbc:Name>Piegādātājs SIA</cbc:Name></cac:PartyName>
+      <cac:PostalAddress><cac:Country><cbc:IdentificationCode>LV</cbc:IdentificationCode></cac:Country></cac:PostalAddress>
+    </cac:Party>
+  </cac:AccountingSupplierParty>
+  <cac:AccountingCustomerParty>
+    <cac:Party>
+      <cbc:EndpointID schemeID="0218">40003521601</cbc:EndpointID>
+      <cac:PartyName><cbc:Name>Pircējs</cbc:Name></cac:PartyName>
+    </cac:Party>
+  </cac:AccountingCustomerParty>
+  <cac:LegalMonetaryTotal>
+    <cbc:PayableAmount currencyID="EUR">121.00</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+</Invoice>"#;
+
+fn sbdh_rule_ids(xml: &str) -> Vec<String> {
+    validate_with_profile(xml, &ValidationProfile::peppol_bis3(), Language::En)
+        .issues
+        .into_iter()
+        .map(|i| i.rule_id)
+        .filter(|id| id.starts_with("SBDH"))
+        .collect()
+}
+
+#[test]
+fn header_is_derived_from_invoice() {
+    let header = Sbdh::for_invoice(INVOICE).unwrap();
+    assert_eq!(
+        header.sender,
+        Identifier::participant("9939", "LV40003521600")
+    );
+    assert_eq!(
+        header.receiver.to_string(),
+        "iso6523-actorid-upis::0218:40003521601"
+    );
+    assert_eq!(header.document_type, "Invoice");
+    assert_eq!(
+        header.document_id.value,
+        "urn:oasis:names:specification:ubl:schema:xsd:Invoice-2::Invoice##urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0::2.1"
+    );
+    assert_eq!(header.process_id.value, BILLING_PROCESS);
+    assert_eq!(header.country_c1.as_deref(), Some("LV"));
+}
+
+#[test]
+fn wrapped_invoice_unwraps_to_same_header_and_invoice() {
+    let header = Sbdh::for_invoice(INVOICE)
+        .unwrap()
+        .with_instance_id("5f1c0b7e-0000-4000-8000-000000000001")
+        .with_creation_time(Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap());
+    let wrapped = header.wrap(INVOICE);
+    assert_eq!(wrapped.matches("<?xml").count(), 1);
+
+    let (parsed, payload) = sbdh::unwrap(&wrapped).unwrap();
+    assert_eq!(parsed, header);
+    assert_eq!(payload, INVOICE.split_once("?>\n").unwrap().1);
+    assert!(sbdh::payload(INVOICE).is_none());
+    assert_eq!(
+        DuplicateKey::from_xml(&wrapped),
+        DuplicateKey::from_xml(INVOICE)
+    );
+}
+
+#[test]
+fn payload_keeps_namespaces_declared_on_envelope() {
+    let xml = format!(
+        r#"<StandardBusinessDocument xmlns="{}" xmlns:cbc="urn:cbc"><StandardBusinessDocumentHeader/><inv:Invoice xmlns:inv="urn:inv"><cbc:ID>1</cbc:ID></inv:Invoice></StandardBusinessDocument>"#,
+        sbdh::SBDH_NS
+    );
+    assert_eq!(
+        sbdh::payload(&xml).unwrap(),
+        r#"<inv:Invoice xmlns:cbc="urn:cbc" xmlns:inv="urn:inv"><cbc:ID>1</cbc:ID></inv:Invoice>"#
+    );
+}
+
+#[test]
+fn header_is_checked_against_invoice() {
+    let wrapped = sbdh::wrap_invoice(INVOICE).unwrap();
+    assert!(sbdh_rule_ids(&wrapped).is_empty());
+    let report = validate_with_profile(&wrapped, &ValidationProfile::peppol_bis3(), Language::En);
+    assert!(!report.issues.iter().any(|i| i.rule_id == "UBL-01"));
+
+    let misaddressed = wrapped.replace(">0218:40003521601<", ">0218:40003521602<");
+    assert_eq!(sbdh_rule_ids(&misaddressed), ["SBDH-03"]);
+
+    let incomplete = wrapped.replace("<sh:Type>Invoice</sh:Type>", "");
+    assert_eq!(sbdh_rule_ids(&incomplete), ["SBDH-01"]);
+}
@@ -0,0 +1,453 @@
+use base64::Engine as _;
+use chrono::{TimeZone, Utc};
+use lat_einv_core::validation::{validate_with_profile, Language, Severity, ValidationProfile};
+use lat_einv_core::xmldsig::trust::{self, TrustStore};
+use lat_einv_core::xmldsig::{
+    sign_div_envelope, sign_invoice, verify_signatures, SigningKey, XmlSigner, DSIG_NS,
+};
+use p12_keystore::{KeyStore, KeyStoreEntry, PrivateKeyChain};
+use rcgen::{
+    date_time_ymd, BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, KeyUsagePurpose,
+    PKCS_RSA_SHA256,
+};
+use x509_cert::der::{Decode, Encode};
+
+const PASSWORD: &str = "test-password";
+
+const INVOICE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>INV-001</cbc:ID>
+  <cbc:IssueDate>2026-10-01</cbc:IssueDate>
+  <cbc:DocumentCurrencyCode>EUR</cbc:DocumentCurrencyCode>
+  <cac:LegalMonetaryTotal>
+    <cbc:PayableAmount currencyID="EUR">121.00</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+</Invoice>"#;
+
+const ENVELOPE: &str = r#"<Envelope xmlns="http://ivis.eps.gov.lv/XMLSchemas/100001/DIV/v1-0">
+  <SenderDocument Id="SenderSection">
+    <DocumentMetadata>
+      <GeneralMetadata>
+        <Title>E-invoice: INV-001</Title>
+      </GeneralMetadata>
+    </DocumentMetadata>
+  </SenderDocument>
+  <ServerTransportMetadata/>
+</Envelope>"#;
+
+struct Issuer {
+    cert: rcgen::Certificate,
+    key: KeyPair,
+}
+
+fn ca(name: &str) -> Issuer {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let cert = params.self_signed(&key).unwrap();
+    Issuer { cert, key }
+}
+
+fn issue(issuer: &Issuer, key: &KeyPair, name: &str, years: (i32, i32)) -> rcgen::Certificate {
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.not_before = date_time_ymd(years.0, 1, 1);
+    params.not_after = date_time_ymd(years.1, 1, 1);
+    params.signed_by(key, &issuer.cert, &issuer.key).unwrap()
+}
+
+/// Intermediate issued by `issuer`; a CA only if `ca`, limited to `usages`
+/// if any
+fn intermediate(
+    issuer: &Issuer,
+    name: &str,
+    years: (i32, i32),
+    ca: bool,
+    usages: Vec<KeyUsagePurpose>,
+) -> Issuer {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.not_before = date_time_ymd(years.0, 1, 1);
+    params.not_after = date_time_ymd(years.1, 1, 1);
+    if ca {
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    }
+    params.key_usages = usages;
+    let cert = params.signed_by(&key, &issuer.cert, &issuer.key).unwrap();
+    Issuer { cert, key }
+}
+
+fn pkcs12(key: &KeyPair, chain: &[&rcgen::Certificate]) -> Vec<u8> {
+    let certificates = chain
+        .iter()
+        .map(|c| p12_keystore::Certificate::from_der(c.der()).unwrap());
+    let entry = PrivateKeyChain::new(key.serialize_der(), [1u8; 20], certificates);
+    let mut store = KeyStore::new();
+    store.add_entry("signer", KeyStoreEntry::PrivateKeyChain(entry));
+    store.writer(PASSWORD).write().unwrap()
+}
+
+fn trusting(issuer: &Issuer) -> TrustStore {
+    let mut store = TrustStore::default();
+    store.add(x509_cert::Certificate::from_der(issuer.cert.der()).unwrap());
+    store
+}
+
+/// Signing key issued by `issuer`, loaded through PKCS#12
+fn signer(issuer: &Issuer, years: (i32, i32)) -> SigningKey {
+    let key = KeyPair::generate().unwrap();
+    let cert = issue(issuer, &key, "SIA Test", years);
+    SigningKey::from_pkcs12(&pkcs12(&key, &[&cert, &issuer.cert]), PASSWORD).unwrap()
+}
+
+/// Signature by `key` over a SignedInfo with `references`, written in
+/// canonical form
+fn forged_signature(key: &SigningKey, references: &str) -> String {
+    let b64 = |data: &[u8]| base64::engine::general_purpose::STANDARD.encode(data);
+    let signed_info = format!(
+        "<ds:SignedInfo xmlns:ds=\"{DSIG_NS}\">\
+         <ds:CanonicalizationMethod Algorithm=\"http://www.w3.org/2001/10/xml-exc-c14n#\"></ds:CanonicalizationMethod>\
+         <ds:SignatureMethod Algorithm=\"{}\"></ds:SignatureMethod>{references}</ds:SignedInfo>",
+        key.algorithm().uri()
+    );
+    format!(
+        "<ds:Signature xmlns:ds=\"{DSIG_NS}\">{signed_info}\
+         <ds:SignatureValue>{}</ds:SignatureValue><ds:KeyInfo><ds:X509Data>\
+         <ds:X509Certificate>{}</ds:X509Certificate></ds:X509Data></ds:KeyInfo></ds:Signature>",
+        b64(&key.sign(signed_info.as_bytes()).unwrap()),
+        b64(&key.certificate().to_der().unwrap()),
+    )
+}
+
+#[test]
+fn signed_invoice_verifies() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let signed = sign_invoice(INVOICE, &key).unwrap();
+    assert!(signed.contains("<ext:UBLExtensions"));
+
+    let results = verify_signatures(&signed, &trusting(&root)).unwrap();
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert!(result.signature_valid, "{:?}", result.errors);
+    assert!(result.references_valid, "{:?}", result.errors);
+    assert!(result.trusted);
+    assert!(result.certificate_valid);
+    assert!(result.xades);
+    assert!(result.signing_time.is_some());
+    assert!(result.signer.as_ref().unwrap().subject.contains("SIA Test"));
+}
+
+#[test]
+fn rsa_key_signs() {
+    let root = ca("Test Root CA");
+    let rsa = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+    let der = rsa::pkcs8::EncodePrivateKey::to_pkcs8_der(&rsa).unwrap();
+    let key =
+        KeyPair::from_pkcs8_der_and_sign_algo(&der.as_bytes().into(), &PKCS_RSA_SHA256).unwrap();
+    let cert = issue(&root, &key, "SIA RSA", (2020, 2040));
+    let key = SigningKey::from_pkcs12(&pkcs12(&key, &[&cert]), PASSWORD).unwrap();
+
+    let signed = sign_invoice(INVOICE, &key).unwrap();
+    assert!(signed.contains("xmldsig-more#rsa-sha256"));
+    let result = &verify_signatures(&signed, &trusting(&root)).unwrap()[0];
+    assert!(result.is_valid(), "{:?}", result.errors);
+    assert!(result.trusted);
+}
+
+#[test]
+fn modified_invoice_fails_verification() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let signed = sign_invoice(INVOICE, &key).unwrap();
+    let tampered = signed.replace("121.00", "12.00");
+
+    let result = &verify_signatures(&tampered, &trusting(&root)).unwrap()[0];
+    assert!(result.signature_valid);
+    assert!(!result.references_valid);
+    assert!(!result.is_valid());
+}
+
+#[test]
+fn signature_is_added_to_existing_extensions() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let invoice = INVOICE.replace(
+        "\n  <cbc:ID>",
+        "\n  <ext:UBLExtensions xmlns:ext=\"urn:oasis:names:specification:ubl:schema:xsd:CommonExtensionComponents-2\"><ext:UBLExtension><ext:ExtensionContent/></ext:UBLExtension></ext:UBLExtensions>\n  <cbc:ID>",
+    );
+    let signed = sign_invoice(&invoice, &key).unwrap();
+    assert_eq!(signed.matches("<ext:UBLExtensions").count(), 1);
+    assert_eq!(signed.matches("<ext:UBLExtension ").count(), 1);
+
+    let result = &verify_signatures(&signed, &trusting(&root)).unwrap()[0];
+    assert!(result.is_valid(), "{:?}", result.errors);
+}
+
+#[test]
+fn div_envelope_signs_sender_section() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let signed = sign_div_envelope(ENVELOPE, &key).unwrap();
+    assert!(signed.contains("URI=\"#SenderSection\""));
+    assert!(signed.trim_end().ends_with("</ds:Signature></Envelope>"));
+
+    let trust = trusting(&root);
+    let result = &verify_signatures(&signed, &trust).unwrap()[0];
+    assert!(result.is_valid(), "{:?}", result.errors);
+
+    // Only the sender section is covered by the signature
+    let outside = signed.replace(
+        "<ServerTransportMetadata/>",
+        "<ServerTransportMetadata>x</ServerTransportMetadata>",
+    );
+    assert!(verify_signatures(&outside, &trust).unwrap()[0].is_valid());
+    let inside = signed.replace("INV-001", "INV-002");
+    assert!(!verify_signatures(&inside, &trust).unwrap()[0].is_valid());
+}
+
+#[test]
+fn signed_envelope_verifies_inside_soap_message() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let signed = sign_div_envelope(ENVELOPE, &key).unwrap();
+    let body = signed.trim_start_matches(|c| c != '<');
+    let message = format!(
+        "<soap:Envelope xmlns:soap=\"http://www.w3.org/2003/05/soap-envelope\" \
+         xmlns:ns=\"http://vraa.gov.lv/div/uui/2011/11\"><soap:Body>\
+         <ns:SendMessageInput>{body}</ns:SendMessageInput></soap:Body></soap:Envelope>"
+    );
+
+    let result = &verify_signatures(&message, &trusting(&root)).unwrap()[0];
+    assert!(result.is_valid(), "{:?}", result.errors);
+}
+
+#[test]
+fn untrusted_and_expired_signers_are_reported() {
+    let root = ca("Test Root CA");
+    let other = ca("Other CA");
+    let expired = signer(&root, (2010, 2015));
+    let signed = XmlSigner::new(&expired)
+        .with_signature_id("sig-expired")
+        .sign(INVOICE)
+        .unwrap();
+
+    let result = &verify_signatures(&signed, &trusting(&other)).unwrap()[0];
+    assert_eq!(result.id.as_deref(), Some("sig-expired"));
+    assert!(result.is_valid(), "{:?}", result.errors);
+    assert!(!result.trusted);
+    assert!(!result.certificate_valid);
+}
+
+/// Whether a signature by a key issued by `intermediate` (under `root`) is
+/// trusted
+fn trusted_through(root: &Issuer, intermediate: &Issuer) -> bool {
+    let key = KeyPair::generate().unwrap();
+    let cert = issue(intermediate, &key, "SIA Test", (2020, 2040));
+    let key =
+        SigningKey::from_pkcs12(&pkcs12(&key, &[&cert, &intermediate.cert]), PASSWORD).unwrap();
+    let signed = sign_invoice(INVOICE, &key).unwrap();
+    let result = &verify_signatures(&signed, &trusting(root)).unwrap()[0];
+    assert!(result.is_valid(), "{:?}", result.errors);
+    result.trusted
+}
+
+#[test]
+fn signers_chain_through_valid_intermediate_cas() {
+    let root = ca("Test Root CA");
+    let issuing = intermediate(
+        &root,
+        "Issuing CA",
+        (2020, 2040),
+        true,
+        vec![KeyUsagePurpose::KeyCertSign],
+    );
+    assert!(trusted_through(&root, &issuing));
+    let unrestricted = intermediate(&root, "Issuing CA", (2020, 2040), true, Vec::new());
+    assert!(trusted_through(&root, &unrestricted));
+}
+
+#[test]
+fn end_entities_cannot_issue_certificates() {
+    let root = ca("Test Root CA");
+    let leaf = intermediate(&root, "SIA Leaf", (2020, 2040), false, Vec::new());
+    assert!(!trusted_through(&root, &leaf));
+}
+
+#[test]
+fn issuers_must_be_allowed_to_sign_certificates() {
+    let root = ca("Test Root CA");
+    let issuing = intermediate(
+        &root,
+        "Issuing CA",
+        (2020, 2040),
+        true,
+        vec![KeyUsagePurpose::DigitalSignature],
+    );
+    assert!(!trusted_through(&root, &issuing));
+}
+
+#[test]
+fn expired_links_break_the_chain() {
+    let root = ca("Test Root CA");
+    let expired = intermediate(
+        &root,
+        "Issuing CA",
+        (2010, 2015),
+        true,
+        vec![KeyUsagePurpose::KeyCertSign],
+    );
+    assert!(!trusted_through(&root, &expired));
+
+    let key = signer(&root, (2010, 2015));
+    let signed = sign_invoice(INVOICE, &key).unwrap();
+    assert!(!verify_signatures(&signed, &trusting(&root)).unwrap()[0].trusted);
+}
+
+// The only test that installs trust anchors, which are global
+#[test]
+fn untrusted_signers_are_warnings_until_anchors_are_installed() {
+    let root = ca("Test Root CA");
+    let other = ca("Other CA");
+    let signed = sign_invoice(INVOICE, &signer(&root, (2020, 2040))).unwrap();
+    let sig_02 = || {
+        validate_with_profile(&signed, &ValidationProfile::peppol_bis3(), Language::En)
+            .issues
+            .into_iter()
+            .find(|i| i.rule_id == "SIG-02")
+            .map(|i| i.severity)
+    };
+
+    trust::install(TrustStore::default());
+    assert_eq!(sig_02(), Some(Severity::Warning));
+    trust::install(trusting(&other));
+    assert_eq!(sig_02(), Some(Severity::Error));
+    trust::install(trusting(&root));
+    assert_eq!(sig_02(), None);
+}
+
+#[test]
+fn wrong_pkcs12_password_is_rejected() {
+    let root = ca("Test Root CA");
+    let key = KeyPair::generate().unwrap();
+    let cert = issue(&root, &key, "SIA Test", (2020, 2040));
+    let p12 = pkcs12(&key, &[&cert]);
+    assert!(SigningKey::from_pkcs12(&p12, "wrong").is_err());
+}
+
+#[test]
+fn signature_without_references_is_invalid() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let signed = INVOICE.replace(
+        "</Invoice>",
+        &format!("{}</Invoice>", forged_signature(&key, "")),
+    );
+
+    let result = &verify_signatures(&signed, &trusting(&root)).unwrap()[0];
+    assert!(result.signature_valid, "{:?}", result.errors);
+    assert!(!result.references_valid);
+    assert!(result
+        .errors
+        .contains(&"No reference covers the signed document".to_string()));
+}
+
+#[test]
+fn signature_over_part_of_the_document_is_invalid() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let invoice = INVOICE.replace(
+        "\n  <cac:LegalMonetaryTotal>",
+        "\n  <cac:AdditionalDocumentReference Id=\"part\"><cbc:ID>A</cbc:ID></cac:AdditionalDocumentReference>\n  <cac:LegalMonetaryTotal>",
+    );
+    let signed = XmlSigner::new(&key)
+        .with_reference_id("part")
+        .sign(&invoice)
+        .unwrap();
+
+    let result = &verify_signatures(&signed, &trusting(&root)).unwrap()[0];
+    assert!(result.signature_valid, "{:?}", result.errors);
+    assert!(!result.is_valid());
+    assert_eq!(result.errors, ["No reference covers the signed document"]);
+}
+
+#[test]
+fn sender_sections_outside_div_envelopes_do_not_cover_the_document() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let invoice = INVOICE.replace(
+        "\n  <cac:LegalMonetaryTotal>",
+        "\n  <cac:AdditionalDocumentReference Id=\"SenderSection\"><cbc:ID>A</cbc:ID></cac:AdditionalDocumentReference>\n  <cac:LegalMonetaryTotal>",
+    );
+    let signed = sign_div_envelope(&invoice, &key).unwrap();
+    // The rest of the invoice could be changed without breaking the signature
+    let changed = signed.replace("121.00", "1.00");
+
+    let result = &verify_signatures(&changed, &trusting(&root)).unwrap()[0];
+    assert!(result.signature_valid, "{:?}", result.errors);
+    assert_eq!(result.errors, ["No reference covers the signed document"]);
+    let report = validate_with_profile(&changed, &ValidationProfile::peppol_bis3(), Language::En);
+    assert!(report.issues.iter().any(|i| i.rule_id == "SIG-01"
+        && i.message
+            .contains("No reference covers the signed document")));
+}
+
+#[test]
+fn duplicate_ids_are_rejected() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let signed = sign_div_envelope(ENVELOPE, &key).unwrap();
+    // A second sender section, shown instead of the signed one
+    let spoofed = signed.replacen(
+        "<SenderDocument Id=\"SenderSection\">",
+        "<SenderDocument Id=\"SenderSection\"><Title>E-invoice: INV-999</Title></SenderDocument>\
+         <SenderDocument Id=\"SenderSection\">",
+        1,
+    );
+
+    let result = &verify_signatures(&spoofed, &trusting(&root)).unwrap()[0];
+    assert!(!result.is_valid());
+    assert!(result
+        .errors
+        .contains(&"Id SenderSection is used by more than one element".to_string()));
+}
+
+#[test]
+fn signing_certificate_digest_is_required() {
+    let root = ca("Test Root CA");
+    let key = signer(&root, (2020, 2040));
+    let signed = sign_invoice(INVOICE, &key).unwrap();
+    let start = signed.find("<xades:SigningCertificateV2>").unwrap();
+    let end = signed.find("</xades:SigningCertificateV2>").unwrap();
+    let stripped = format!(
+        "{}{}",
+        &signed[..start],
+        &signed[end + "</xades:SigningCertificateV2>".len()..]
+    );
+
+    let result = &verify_signatures(&stripped, &trusting(&root)).unwrap()[0];
+    assert!(!result.references_valid);
+    assert!(result
+        .errors
+        .contains(&"XAdES signing certificate digest is missing".to_string()));
+}
+
+#[test]
+fn claimed_signing_time_does_not_revive_expired_certificates() {
+    let root = ca("Test Root CA");
+    let expired = signer(&root, (2010, 2015));
+    let signed = XmlSigner::new(&expired)
+        .with_signing_time(Utc.with_ymd_and_hms(2012, 6, 1, 0, 0, 0).unwrap())
+        .sign(INVOICE)
+        .unwrap();
+
+    let result = &verify_signatures(&signed, &trusting(&root)).unwrap()[0];
+    assert!(result.is_valid(), "{:?}", result.errors);
+    assert_eq!(
+        result.signing_time,
+        Some(Utc.with_ymd_and_hms(2012, 6, 1, 0, 0, 0).unwrap())
+    );
+    assert!(!result.certificate_valid);
+}
@@ -1,5 +1,7 @@
+use crate::FailureKind;
 use anyhow::Result;
 use chrono::Utc;
+use lat_einv_core::models::DeliveryState;
 use serde::{Deserialize, Serialize};
 use std::fs::OpenOptions;
 use std::io::Write;
@@ -11,23 +13,29 @@ pub struct AuditEvent {
     pub event_type: String,
     pub job_id: String,
     pub invoice_hash: Option<String>,
+    #[serde(default)]
+    pub canonical_hash: Option<String>,
     pub transmission_id: Option<String>,
     pub state: String,
     pub error: Option<String>,
+    #[serde(default)]
+    pub failure: Option<FailureKind>,
     pub sender: Option<String>,
     pub receiver: Option<String>,
 }
 
 impl AuditEvent {
-    pub fn new(event_type: &str, job_id: &str, state: &str) -> Self {
+    pub fn new(event_type: &str, job_id: &str, state: DeliveryState) -> Self {
         Self {
             timestamp: Utc::now().to_rfc3339(),
             event_type: event_type.to_string(),
             job_id: job_id.to_string(),
             invoice_hash: None,
+            canonical_hash: None,
             transmission_id: None,
             state: state.to_string(),
             error: None,
+            failure: None,
             sender: None,
             receiver: None,
         }
@@ -38,6 +46,11 @@ impl AuditEvent {
         self
     }
 
+    pub fn with_canonical_hash(mut self, hash: String) -> Self {
+        self.canonical_hash = Some(hash);
+        self
+    }
+
     pub fn with_transmission_id(mut self, transmission_id: String) -> Self {
         self.transmission_id = Some(transmission_id);
         self
@@ -48,6 +61,11 @@ impl AuditEvent {
         self
     }
 
+    pub fn with_failure(mut self, failure: FailureKind) -> Self {
+        self.failure = Some(failure);
+        self
+    }
+
     pub fn with_parties(mut self, sender: String, receiver: String) -> Self {
         self.sender = Some(sender);
         self.receiver = Some(receiver);
@@ -0,0 +1,311 @@
+//! Jobs the queue gave up on, and retrying, cancelling and purging jobs by
+//! hand
+
+use crate::audit::{write_audit_event, AuditEvent};
+use crate::{
+    abort, announce, document_index_key, duplicate_index_keys, live_entry, transaction_error,
+    transition, update_state, JobPayload, JobQuery, JobRecord, Jobs, Queue,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use lat_einv_core::models::DeliveryState;
+use serde::{Deserialize, Serialize};
+use sled::transaction::{TransactionalTree, UnabortableTransactionError};
+use std::fmt;
+
+/// Why a job was dead-lettered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The access point refused the submission
+    Refused,
+    /// Submission failed transiently until the attempts ran out
+    RetriesExhausted,
+    /// The access point reported that delivery failed
+    DeliveryFailed,
+    /// The delivery status could not be queried
+    StatusError,
+    /// No final delivery status arrived before the deadline
+    DeadlineExpired,
+    /// Failed before failures were classified
+    Unknown,
+}
+
+impl FailureKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureKind::Refused => "refused",
+            FailureKind::RetriesExhausted => "retries_exhausted",
+            FailureKind::DeliveryFailed => "delivery_failed",
+            FailureKind::StatusError => "status_error",
+            FailureKind::DeadlineExpired => "deadline_expired",
+            FailureKind::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Selects dead letters to retry or purge; unset fields match any
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadLetterFilter {
+    pub failure: Option<FailureKind>,
+    pub receiver: Option<String>,
+    /// Dead-lettered at or after
+    pub since: Option<DateTime<Utc>>,
+    /// Dead-lettered before
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl DeadLetterFilter {
+    fn matches(&self, rec: &JobRecord) -> bool {
+        rec.state == DeliveryState::DeadLetter
+            && self.failure.is_none_or(|f| rec.failure == Some(f))
+            && self.since.is_none_or(|t| rec.updated_at >= t)
+            && self.until.is_none_or(|t| rec.updated_at < t)
+    }
+}
+
+impl Queue {
+    /// Fail a job for good and park it as a dead letter, recording it in
+    /// the audit log as `event_type`
+    pub(crate) fn dead_letter(
+        &self,
+        jobs: &Jobs,
+        job_id: &str,
+        failure: FailureKind,
+        error: String,
+        event_type: &str,
+    ) -> Result<()> {
+        update_state(jobs, job_id, DeliveryState::Failed, |rec| {
+            rec.last_error = Some(error.clone());
+            rec.next_attempt_at = None;
+            rec.next_status_check_at = None;
+        })?;
+        let rec = update_state(jobs, job_id, DeliveryState::DeadLetter, |rec| {
+            rec.failure = Some(failure);
+        })?;
+        tracing::warn!(job_id=%job_id, %failure, error=%error, "job dead-lettered");
+
+        // Audit log
+        let mut event = AuditEvent::new(event_type, job_id, DeliveryState::DeadLetter)
+            .with_failure(failure)
+            .with_error(error);
+        if let Some(transmission_id) = rec.transmission_id {
+            event = event.with_transmission_id(transmission_id);
+        }
+        let _ = write_audit_event(&event);
+        Ok(())
+    }
+
+    /// Dead letters matching `filter`, newest first
+    fn dead_letters(&self, filter: &DeadLetterFilter) -> Result<Vec<JobRecord>> {
+        let query = JobQuery {
+            states: vec![DeliveryState::DeadLetter],
+            receiver: filter.receiver.clone(),
+            limit: Some(usize::MAX),
+            ..JobQuery::default()
+        };
+        let mut jobs = self.list_status(&query)?.jobs;
+        jobs.retain(|rec| filter.matches(rec));
+        Ok(jobs)
+    }
+
+    /// Queue a dead-lettered job again with a fresh set of attempts. It is
+    /// submitted anew, so whatever was learnt from an earlier submission is
+    /// dropped.
+    pub fn retry_job(&self, job_id: &str) -> Result<JobRecord> {
+        let jobs = self.jobs_tree()?;
+        let rec = self.job(job_id)?;
+        let payload = self.payload(job_id)?;
+        let hash = rec.canonical_hash.as_deref().unwrap_or(&rec.invoice_hash);
+        let index_key = document_index_key(hash, &payload.receiver);
+        let duplicate_keys =
+            duplicate_index_keys(rec.duplicate_key.as_deref(), rec.canonical_hash.as_deref());
+
+        // Checked and claimed in one transaction, like on enqueue, so that a
+        // concurrent enqueue or retry cannot send the invoice as well
+        let documents = self.documents_tree()?;
+        let duplicates = self.duplicates_tree()?;
+        let retried = jobs.transact(&[&documents, &duplicates], |tx_jobs, others, index| {
+            let [tx_documents, tx_duplicates] = others else {
+                unreachable!()
+            };
+            let existing = tx_jobs
+                .get(job_id)?
+                .ok_or_else(|| abort(anyhow!("job not found: {job_id}")))?;
+            let mut rec: JobRecord = serde_json::from_slice(&existing).map_err(abort)?;
+            let state = rec.state;
+            if !matches!(state, DeliveryState::DeadLetter | DeliveryState::Failed) {
+                return Err(abort(anyhow!(
+                    "job {job_id} is {state}; only failed jobs can be retried"
+                )));
+            }
+            // Another job may have been enqueued for the document since
+            if let Some(live) = live_entry(tx_jobs, tx_documents, &index_key)? {
+                return Err(abort(anyhow!(
+                    "job {job_id}: document is already {} as job {}",
+                    live.state,
+                    live.job_id
+                )));
+            }
+            for key in &duplicate_keys {
+                if let Some(live) = live_entry(tx_jobs, tx_duplicates, key)? {
+                    return Err(abort(anyhow!(
+                        "job {job_id}: invoice is already {} as job {}",
+                        live.state,
+                        live.job_id
+                    )));
+                }
+            }
+
+            transition(&mut rec, DeliveryState::Queued, |rec| {
+                rec.attempts = 0;
+                rec.manual_retries += 1;
+                rec.next_attempt_at = None;
+                rec.transmission_id = None;
+                rec.status_checks = 0;
+                rec.next_status_check_at = None;
+                rec.failure = None;
+                rec.last_error = None;
+            })
+            .map_err(abort)?;
+            tx_jobs.insert(job_id.as_bytes(), serde_json::to_vec(&rec).map_err(abort)?)?;
+            index.state_changed(&rec, state)?;
+            tx_documents.insert(index_key.as_bytes(), job_id.as_bytes())?;
+            for key in &duplicate_keys {
+                tx_duplicates.insert(key.as_bytes(), job_id.as_bytes())?;
+            }
+            Ok((rec, state))
+        });
+        let (rec, state) = retried.map_err(transaction_error)?;
+        announce(&jobs, &rec, state);
+        tracing::info!(job_id=%job_id, "job retried");
+
+        // Audit log
+        let _ = write_audit_event(&AuditEvent::new(
+            "job_retried",
+            job_id,
+            DeliveryState::Queued,
+        ));
+        self.dispatch(job_id.to_string());
+        Ok(rec)
+    }
+
+    /// Retry the dead letters matching `filter`; returns the retried job IDs
+    pub fn retry_dead_letters(&self, filter: &DeadLetterFilter) -> Result<Vec<String>> {
+        let mut retried = Vec::new();
+        for rec in self.dead_letters(filter)? {
+            match self.retry_job(&rec.job_id) {
+                Ok(_) => retried.push(rec.job_id),
+                Err(e) => tracing::warn!(job_id=%rec.job_id, error=%e, "job not retried"),
+            }
+        }
+        Ok(retried)
+    }
+
+    /// Cancel a job that has not been submitted yet
+    pub fn cancel_job(&self, job_id: &str) -> Result<JobRecord> {
+        let rec = self.job(job_id)?;
+        if rec.state != DeliveryState::Queued {
+            bail!(
+                "job {job_id} is {}; only queued jobs can be cancelled",
+                rec.state
+            );
+        }
+        let rec = update_state(
+            &self.jobs_tree()?,
+            job_id,
+            DeliveryState::Cancelled,
+            |rec| {
+                rec.next_attempt_at = None;
+            },
+        )?;
+        tracing::info!(job_id=%job_id, "job cancelled");
+
+        // Audit log
+        let _ = write_audit_event(&AuditEvent::new(
+            "job_cancelled",
+            job_id,
+            DeliveryState::Cancelled,
+        ));
+        Ok(rec)
+    }
+
+    /// Delete the dead letters matching `filter` with their payloads;
+    /// returns how many were deleted
+    pub fn purge_dead_letters(&self, filter: &DeadLetterFilter) -> Result<usize> {
+        let jobs = self.jobs_tree()?;
+        let payloads = self.payloads_tree()?;
+        let documents = self.documents_tree()?;
+        let duplicates = self.duplicates_tree()?;
+        let mut purged = 0;
+        for rec in self.dead_letters(filter)? {
+            let deleted = delete_dead_letter(&jobs, &[&payloads, &documents, &duplicates], &rec)?;
+            if !deleted {
+                continue;
+            }
+            purged += 1;
+
+            // Audit log
+            let mut event =
+                AuditEvent::new("dead_letter_purged", &rec.job_id, DeliveryState::DeadLetter);
+            if let Some(failure) = rec.failure {
+                event = event.with_failure(failure);
+            }
+            let _ = write_audit_event(&event);
+        }
+        Ok(purged)
+    }
+}
+
+/// Delete dead letter `rec` with its payload and index entries, unless it
+/// was retried or purged since it was listed. `trees` are the payloads, the
+/// documents and the duplicates.
+fn delete_dead_letter(jobs: &Jobs, trees: &[&sled::Tree; 3], rec: &JobRecord) -> Result<bool> {
+    let job_id = rec.job_id.as_str();
+    jobs.transact(trees, |tx_jobs, others, index| {
+        let [tx_payloads, tx_documents, tx_duplicates] = others else {
+            unreachable!()
+        };
+        let Some(existing) = tx_jobs.get(job_id)? else {
+            return Ok(false);
+        };
+        let current: JobRecord = serde_json::from_slice(&existing).map_err(abort)?;
+        if current.state != DeliveryState::DeadLetter {
+            return Ok(false);
+        }
+        tx_jobs.remove(job_id)?;
+        index.remove(&current)?;
+        if let Some(payload) = tx_payloads.remove(job_id)? {
+            let payload: JobPayload = serde_json::from_slice(&payload).map_err(abort)?;
+            let hash = rec.canonical_hash.as_deref().unwrap_or(&rec.invoice_hash);
+            let index_key = document_index_key(hash, &payload.receiver);
+            release(tx_documents, &index_key, job_id)?;
+        }
+        let duplicate_keys =
+            duplicate_index_keys(rec.duplicate_key.as_deref(), rec.canonical_hash.as_deref());
+        for duplicate_key in duplicate_keys {
+            release(tx_duplicates, &duplicate_key, job_id)?;
+        }
+        Ok(true)
+    })
+    .map_err(transaction_error)
+}
+
+/// Remove index entry `key`, unless it points at a newer job than `job_id`
+fn release(
+    index: &TransactionalTree,
+    key: &str,
+    job_id: &str,
+) -> std::result::Result<(), UnabortableTransactionError> {
+    if index.get(key)?.is_some_and(|v| v == job_id.as_bytes()) {
+        index.remove(key)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use lat_einv_core::models::DeliveryState;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// Events kept for subscribers that fall behind; older ones are dropped
+pub(crate) const EVENT_CAPACITY: usize = 1024;
+
+/// Something that happened to a job, published to [`subscribe`] receivers
+///
+/// [`subscribe`]: crate::subscribe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEvent {
+    Enqueued {
+        job_id: String,
+        at: DateTime<Utc>,
+    },
+    StateChanged {
+        job_id: String,
+        from: DeliveryState,
+        to: DeliveryState,
+        at: DateTime<Utc>,
+        error: Option<String>,
+    },
+    RetryScheduled {
+        job_id: String,
+        attempts: u32,
+        retry_at: DateTime<Utc>,
+        error: String,
+    },
+    Delivered {
+        job_id: String,
+        transmission_id: Option<String>,
+        at: DateTime<Utc>,
+    },
+}
+
+impl JobEvent {
+    pub fn job_id(&self) -> &str {
+        match self {
+            JobEvent::Enqueued { job_id, .. }
+            | JobEvent::StateChanged { job_id, .. }
+            | JobEvent::RetryScheduled { job_id, .. }
+            | JobEvent::Delivered { job_id, .. } => job_id,
+        }
+    }
+}
+
+/// Publish `event`; it is dropped if nobody is subscribed
+pub(crate) fn publish(events: &broadcast::Sender<JobEvent>, event: JobEvent) {
+    tracing::trace!(job_id=%event.job_id(), ?event, "job event");
+    let _ = events.send(event);
+}
@@ -0,0 +1,221 @@
+//! Secondary indexes over the jobs tree
+//!
+//! Index keys end in the job's creation time and ID, so a range scan over
+//! one value (a state, a receiver, ...) finds its jobs in creation order
+//! without reading the others. They are written in the same transaction as
+//! the job records, so a crash cannot leave a job indexed under a state it
+//! has left.
+
+use crate::{JobQuery, JobRecord};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use lat_einv_core::models::DeliveryState;
+use sled::transaction::{TransactionalTree, UnabortableTransactionError};
+use sled::{Db, Tree};
+
+#[derive(Clone)]
+pub(crate) struct JobIndex {
+    created: Tree,
+    state: Tree,
+    receiver: Tree,
+    sender: Tree,
+    invoice: Tree,
+}
+
+/// Sorts like the times it encodes, including those before 1970
+fn time_key(at: DateTime<Utc>) -> [u8; 8] {
+    let nanos = at.timestamp_nanos_opt().unwrap_or(i64::MAX);
+    ((nanos as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+fn job_key(rec: &JobRecord) -> Vec<u8> {
+    let mut key = time_key(rec.created_at).to_vec();
+    key.extend_from_slice(rec.job_id.as_bytes());
+    key
+}
+
+fn prefixed(value: &str, rest: &[u8]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(value.len() + 1 + rest.len());
+    key.extend_from_slice(value.as_bytes());
+    key.push(0);
+    key.extend_from_slice(rest);
+    key
+}
+
+/// Position after `rec` in a listing, for [`JobQuery::cursor`]
+pub(crate) fn cursor(rec: &JobRecord) -> String {
+    let nanos = rec.created_at.timestamp_nanos_opt().unwrap_or(i64::MAX);
+    format!("{nanos}:{}", rec.job_id)
+}
+
+fn parse_cursor(cursor: &str) -> Result<Vec<u8>> {
+    let (nanos, job_id) = cursor
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid cursor: {cursor}"))?;
+    let nanos: i64 = nanos
+        .parse()
+        .map_err(|_| anyhow!("invalid cursor: {cursor}"))?;
+    let mut key = time_key(DateTime::from_timestamp_nanos(nanos)).to_vec();
+    key.extend_from_slice(job_id.as_bytes());
+    Ok(key)
+}
+
+impl JobIndex {
+    pub(crate) fn open(db: &Db) -> Result<Self> {
+        Ok(Self {
+            created: db.open_tree("jobs_by_created")?,
+            state: db.open_tree("jobs_by_state")?,
+            receiver: db.open_tree("jobs_by_receiver")?,
+            sender: db.open_tree("jobs_by_sender")?,
+            invoice: db.open_tree("jobs_by_invoice")?,
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.created.is_empty()
+    }
+
+    /// The index trees, in the order [`IndexTx::new`] takes them
+    pub(crate) fn trees(&self) -> [&Tree; 5] {
+        [
+            &self.created,
+            &self.state,
+            &self.receiver,
+            &self.sender,
+            &self.invoice,
+        ]
+    }
+
+    /// IDs of the jobs in `state`, oldest first
+    pub(crate) fn in_state(
+        &self,
+        state: DeliveryState,
+    ) -> impl Iterator<Item = Result<String>> + '_ {
+        let prefix = prefixed(state.as_str(), &[]);
+        let skip = prefix.len() + 8;
+        self.state.scan_prefix(prefix).map(move |item| {
+            let (key, _) = item?;
+            Ok(String::from_utf8_lossy(&key[skip..]).into_owned())
+        })
+    }
+
+    /// IDs of the jobs that may match `query`, newest first: those in the
+    /// most selective index it can use, created in its date range and
+    /// before its cursor
+    pub(crate) fn scan(&self, query: &JobQuery) -> Result<impl Iterator<Item = Result<String>>> {
+        let (tree, prefix) = match query {
+            JobQuery {
+                invoice_number: Some(number),
+                ..
+            } => (&self.invoice, prefixed(number.trim(), &[])),
+            JobQuery {
+                receiver: Some(receiver),
+                ..
+            } => (&self.receiver, prefixed(receiver, &[])),
+            JobQuery {
+                sender: Some(sender),
+                ..
+            } => (&self.sender, prefixed(sender, &[])),
+            JobQuery { states, .. } if states.len() == 1 => {
+                (&self.state, prefixed(states[0].as_str(), &[]))
+            }
+            _ => (&self.created, Vec::new()),
+        };
+
+        let start = match query.created_after {
+            Some(at) => [prefix.as_slice(), &time_key(at)].concat(),
+            None => prefix.clone(),
+        };
+        let mut end = match query.created_before {
+            Some(at) => [prefix.as_slice(), &time_key(at)].concat(),
+            None => [prefix.as_slice(), &[0xff; 9]].concat(),
+        };
+        if let Some(cursor) = &query.cursor {
+            end = end.min([prefix.as_slice(), &parse_cursor(cursor)?].concat());
+        }
+
+        let skip = prefix.len() + 8;
+        let range = if start < end {
+            tree.range(start..end)
+        } else {
+            tree.range(start.clone()..start)
+        };
+        Ok(range.rev().map(move |item| {
+            let (key, _) = item?;
+            Ok(String::from_utf8_lossy(&key[skip..]).into_owned())
+        }))
+    }
+}
+
+/// The index trees inside a transaction that also writes the jobs, so that
+/// the two cannot disagree
+pub(crate) struct IndexTx<'a> {
+    created: &'a TransactionalTree,
+    state: &'a TransactionalTree,
+    receiver: &'a TransactionalTree,
+    sender: &'a TransactionalTree,
+    invoice: &'a TransactionalTree,
+}
+
+type TxResult = std::result::Result<(), UnabortableTransactionError>;
+
+impl<'a> IndexTx<'a> {
+    /// Views of the trees of [`JobIndex::trees`], in the same order
+    pub(crate) fn new(trees: &'a [TransactionalTree]) -> Self {
+        let [created, state, receiver, sender, invoice] = trees else {
+            panic!("expected the 5 index trees, got {}", trees.len());
+        };
+        Self {
+            created,
+            state,
+            receiver,
+            sender,
+            invoice,
+        }
+    }
+
+    fn values<'r>(&self, rec: &'r JobRecord) -> [(&'a TransactionalTree, Option<&'r str>); 4] {
+        [
+            (self.state, Some(rec.state.as_str())),
+            (
+                self.receiver,
+                Some(rec.receiver.as_str()).filter(|r| !r.is_empty()),
+            ),
+            (
+                self.sender,
+                Some(rec.sender.as_str()).filter(|s| !s.is_empty()),
+            ),
+            (self.invoice, rec.invoice_number.as_deref()),
+        ]
+    }
+
+    pub(crate) fn insert(&self, rec: &JobRecord) -> TxResult {
+        let key = job_key(rec);
+        self.created.insert(key.as_slice(), &[])?;
+        for (tree, value) in self.values(rec) {
+            if let Some(value) = value {
+                tree.insert(prefixed(value, &key), &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn remove(&self, rec: &JobRecord) -> TxResult {
+        let key = job_key(rec);
+        self.created.remove(key.as_slice())?;
+        for (tree, value) in self.values(rec) {
+            if let Some(value) = value {
+                tree.remove(prefixed(value, &key))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move `rec` in the state index after it left state `from`
+    pub(crate) fn state_changed(&self, rec: &JobRecord, from: DeliveryState) -> TxResult {
+        let key = job_key(rec);
+        self.state.remove(prefixed(from.as_str(), &key))?;
+        self.state.insert(prefixed(rec.state.as_str(), &key), &[])?;
+        Ok(())
+    }
+}
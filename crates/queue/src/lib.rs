@@ -1,33 +1,270 @@
 mod audit;
+mod dead_letter;
+mod events;
+mod index;
+mod poll;
+mod retry;
+mod workers;
 
-use access_point::{AccessPointClient, DeliveryState};
-use anyhow::{anyhow, Result};
+use access_point::error::transient;
+use access_point::AccessPointClient;
+use anyhow::{anyhow, bail, Result};
 use audit::{write_audit_event, AuditEvent};
 use chrono::{DateTime, Utc};
-use lat_einv_core::parsing::compute_sha256_hex;
+use events::{publish, EVENT_CAPACITY};
+use index::{IndexTx, JobIndex};
+use lat_einv_core::duplicates::DuplicateKey;
+use lat_einv_core::parsing::{compute_canonical_sha256_hex, compute_sha256_hex, parse_ubl_invoice};
+use lat_einv_core::sbdh;
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+    TransactionResult, Transactional, TransactionalTree,
+};
 use sled::Db;
+use std::ops::Deref;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::time::{sleep, Duration};
+use tokio::sync::{broadcast, Notify};
+use tokio::time::sleep;
+use workers::{SharedReceiver, Task, WorkerPool};
+
+pub use dead_letter::{DeadLetterFilter, FailureKind};
+pub use events::JobEvent;
+pub use lat_einv_core::models::DeliveryState;
+pub use poll::PollPolicy;
+pub use retry::RetryPolicy;
+pub use workers::{RateLimiter, WorkerPolicy, WorkerStats};
 
 static GLOBAL_QUEUE: OnceCell<Arc<Queue>> = OnceCell::new();
 
+/// Jobs per page when a query sets no limit
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// How soon a job that fell due while its last task was still waiting for a
+/// worker is looked at again
+const BUSY_RECHECK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Invoices waiting to be sent or sent, stored in one sled database and
+/// submitted through one access point
 #[derive(Clone)]
-struct Queue {
+pub struct Queue {
     db: Db,
     access_point: Arc<dyn AccessPointClient + 'static>,
+    retry: RetryPolicy,
+    poll: PollPolicy,
+    workers: Arc<WorkerPool>,
+    events: broadcast::Sender<JobEvent>,
+    /// Wakes the scheduler when a retry or status check is scheduled
+    wake: Arc<Notify>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobRecord {
     pub job_id: String,
-    pub state: String,
+    pub state: DeliveryState,
     pub last_error: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub transmission_id: Option<String>,
     pub invoice_hash: String,
+    #[serde(default)]
+    pub sender: String,
+    #[serde(default)]
+    pub receiver: String,
+    #[serde(default)]
+    pub invoice_number: Option<String>,
+    /// Supplier, number, date and total used to detect resent invoices
+    #[serde(default)]
+    pub duplicate_key: Option<String>,
+    /// Hash of the canonical XML; equal for re-saved copies of a document
+    #[serde(default)]
+    pub canonical_hash: Option<String>,
+    /// State changes, oldest first
+    #[serde(default)]
+    pub history: Vec<StateTransition>,
+    /// Submission attempts made so far
+    #[serde(default)]
+    pub attempts: u32,
+    /// When a job waiting to be retried is due
+    #[serde(default)]
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// Delivery status checks made since sending
+    #[serde(default)]
+    pub status_checks: u32,
+    /// When the delivery status of a sent job is checked next
+    #[serde(default)]
+    pub next_status_check_at: Option<DateTime<Utc>>,
+    /// Why the job was dead-lettered
+    #[serde(default)]
+    pub failure: Option<FailureKind>,
+    /// Times the job was retried by hand after failing
+    #[serde(default)]
+    pub manual_retries: u32,
+    /// Answers from the access point, oldest first
+    #[serde(default)]
+    pub responses: Vec<ProviderResponse>,
+}
+
+impl JobRecord {
+    /// When the job was last sent
+    pub fn sent_at(&self) -> Option<DateTime<Utc>> {
+        self.history
+            .iter()
+            .rev()
+            .find(|t| t.to == DeliveryState::Sent)
+            .map(|t| t.at)
+    }
+
+    /// Record an access point answer, unless it repeats the last one
+    fn push_response(&mut self, response: ProviderResponse) {
+        let repeated = self.responses.last().is_some_and(|last| {
+            last.operation == response.operation
+                && last.state == response.state
+                && last.message == response.message
+                && last.payload_hash == response.payload_hash
+        });
+        if !repeated {
+            self.responses.push(response);
+        }
+    }
+
+    /// Passed to the access point with each submission. A job retried by
+    /// hand gets a new one, so that it is not answered with the failed
+    /// submission.
+    pub fn idempotency_key(&self) -> String {
+        match self.manual_retries {
+            0 => self.job_id.clone(),
+            n => format!("{}-{}", self.job_id, n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateTransition {
+    pub from: DeliveryState,
+    pub to: DeliveryState,
+    pub at: DateTime<Utc>,
+    /// Error recorded on the job when it entered `to`
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderOperation {
+    Submit,
+    Status,
+}
+
+/// What the access point answered to a submission or status check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderResponse {
+    pub at: DateTime<Utc>,
+    pub operation: ProviderOperation,
+    pub transmission_id: Option<String>,
+    /// Delivery state reported by a status check
+    pub state: Option<DeliveryState>,
+    /// Status message, or the error for a failed request
+    pub message: Option<String>,
+    /// SHA-256 of the invoice as submitted, which the access point may have
+    /// signed
+    #[serde(default)]
+    pub payload_hash: Option<String>,
+}
+
+impl ProviderResponse {
+    fn new(operation: ProviderOperation) -> Self {
+        Self {
+            at: Utc::now(),
+            operation,
+            transmission_id: None,
+            state: None,
+            message: None,
+            payload_hash: None,
+        }
+    }
+}
+
+/// Selects jobs to list; unset fields match any
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobQuery {
+    /// Jobs in any of these states
+    pub states: Vec<DeliveryState>,
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+    pub invoice_number: Option<String>,
+    /// Created at or after
+    pub created_after: Option<DateTime<Utc>>,
+    /// Created before
+    pub created_before: Option<DateTime<Utc>>,
+    /// Found, ignoring case, in the job ID, invoice number, parties,
+    /// transmission ID or last error
+    pub text: Option<String>,
+    /// Jobs per page, 100 if unset
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl JobQuery {
+    fn matches(&self, rec: &JobRecord) -> bool {
+        let text = self.text.as_deref().map(str::to_lowercase);
+        let found = |field: Option<&str>| {
+            field.is_some_and(|f| f.to_lowercase().contains(text.as_deref().unwrap_or("")))
+        };
+        (self.states.is_empty() || self.states.contains(&rec.state))
+            && self.sender.as_ref().is_none_or(|s| *s == rec.sender)
+            && self.receiver.as_ref().is_none_or(|r| *r == rec.receiver)
+            && self
+                .invoice_number
+                .as_deref()
+                .is_none_or(|n| rec.invoice_number.as_deref() == Some(n.trim()))
+            && self.created_after.is_none_or(|t| rec.created_at >= t)
+            && self.created_before.is_none_or(|t| rec.created_at < t)
+            && (text.is_none()
+                || found(Some(&rec.job_id))
+                || found(rec.invoice_number.as_deref())
+                || found(Some(&rec.sender))
+                || found(Some(&rec.receiver))
+                || found(rec.transmission_id.as_deref())
+                || found(rec.last_error.as_deref()))
+    }
+}
+
+/// One page of jobs, newest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobPage {
+    pub jobs: Vec<JobRecord>,
+    /// Continues the listing after this page; unset on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// A job with the invoice it sends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobDetail {
+    #[serde(flatten)]
+    pub record: JobRecord,
+    pub profile: String,
+    /// The invoice as enqueued, before the access point signs it
+    pub xml: String,
+    /// The invoice as last submitted; unset until it is
+    pub transmitted_xml: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QueueOptions {
+    pub retry: RetryPolicy,
+    pub poll: PollPolicy,
+    pub workers: WorkerPolicy,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EnqueueOptions {
+    /// Enqueue a new job even if the same invoice was already sent or queued
+    pub allow_duplicate: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,167 +273,791 @@ struct JobPayload {
     sender: String,
     receiver: String,
     profile: String,
+    /// The invoice as last submitted, if the access point changed it
+    #[serde(default)]
+    transmitted: Option<String>,
 }
 
 impl Queue {
-    fn new(db: Db, access_point: Arc<dyn AccessPointClient + 'static>) -> Self {
-        Self { db, access_point }
+    /// Open the queue stored at `path`, indexing jobs stored by earlier
+    /// versions
+    pub fn open(
+        path: impl AsRef<Path>,
+        access_point: Arc<dyn AccessPointClient + 'static>,
+        options: QueueOptions,
+    ) -> Result<Self> {
+        let queue = Self::new(sled::open(path)?, access_point, options);
+        queue.index_documents()?;
+        queue.index_duplicates()?;
+        queue.index_jobs()?;
+        Ok(queue)
     }
 
-    fn jobs_tree(&self) -> Result<sled::Tree> {
-        Ok(self.db.open_tree("jobs")?)
+    fn new(
+        db: Db,
+        access_point: Arc<dyn AccessPointClient + 'static>,
+        options: QueueOptions,
+    ) -> Self {
+        let rate_limit = options
+            .workers
+            .rate_limit
+            .unwrap_or_else(|| access_point.submit_rate_limit());
+        let workers = WorkerPool::new(options.workers.concurrency, rate_limit);
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            db,
+            access_point,
+            retry: options.retry,
+            poll: options.poll,
+            workers: Arc::new(workers),
+            events,
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    fn jobs_tree(&self) -> Result<Jobs> {
+        Ok(Jobs {
+            tree: self.db.open_tree("jobs")?,
+            index: JobIndex::open(&self.db)?,
+            events: self.events.clone(),
+        })
     }
 
     fn payloads_tree(&self) -> Result<sled::Tree> {
         Ok(self.db.open_tree("payloads")?)
     }
 
-    async fn enqueue(&self, payload: JobPayload) -> Result<String> {
-        let job_id = self.generate_job_id();
+    fn job(&self, job_id: &str) -> Result<JobRecord> {
+        let v = self
+            .jobs_tree()?
+            .get(job_id.as_bytes())?
+            .ok_or_else(|| anyhow!("job not found: {job_id}"))?;
+        Ok(serde_json::from_slice(&v)?)
+    }
+
+    fn payload(&self, job_id: &str) -> Result<JobPayload> {
+        let v = self
+            .payloads_tree()?
+            .get(job_id.as_bytes())?
+            .ok_or_else(|| anyhow!("payload missing"))?;
+        Ok(serde_json::from_slice(&v)?)
+    }
+
+    /// Job ID by document hash and receiver, see [`document_index_key`]
+    fn documents_tree(&self) -> Result<sled::Tree> {
+        Ok(self.db.open_tree("documents")?)
+    }
+
+    /// Live job ID by duplicate key or canonical hash, see
+    /// [`duplicate_index_keys`]
+    fn duplicates_tree(&self) -> Result<sled::Tree> {
+        Ok(self.db.open_tree("duplicates")?)
+    }
+
+    /// Index the jobs stored before the document index existed
+    fn index_documents(&self) -> Result<()> {
+        let documents = self.documents_tree()?;
+        if !documents.is_empty() {
+            return Ok(());
+        }
+        let payloads = self.payloads_tree()?;
+        for item in self.jobs_tree()?.iter() {
+            let (k, v) = item?;
+            let rec: JobRecord = serde_json::from_slice(&v)?;
+            let Some(payload) = payloads.get(&k)? else {
+                continue;
+            };
+            let payload: JobPayload = serde_json::from_slice(&payload)?;
+            let hash = rec.canonical_hash.as_deref().unwrap_or(&rec.invoice_hash);
+            documents.insert(document_index_key(hash, &payload.receiver), k)?;
+        }
+        Ok(())
+    }
+
+    /// Index the live jobs stored before the duplicate index existed
+    fn index_duplicates(&self) -> Result<()> {
+        let duplicates = self.duplicates_tree()?;
+        if !duplicates.is_empty() {
+            return Ok(());
+        }
+        for item in self.jobs_tree()?.iter() {
+            let (k, v) = item?;
+            let rec: JobRecord = serde_json::from_slice(&v)?;
+            if !is_live(rec.state) {
+                continue;
+            }
+            let keys =
+                duplicate_index_keys(rec.duplicate_key.as_deref(), rec.canonical_hash.as_deref());
+            for key in keys {
+                duplicates.insert(key, k.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Index the jobs stored before the job indexes existed, filling in the
+    /// parties and invoice numbers their records did not have yet
+    fn index_jobs(&self) -> Result<()> {
+        let jobs = self.jobs_tree()?;
+        if !jobs.index.is_empty() {
+            return Ok(());
+        }
+        for item in jobs.iter() {
+            let (k, v) = item?;
+            let mut rec: JobRecord = serde_json::from_slice(&v)?;
+            if rec.sender.is_empty() {
+                if let Ok(payload) = self.payload(&rec.job_id) {
+                    rec.invoice_number = invoice_number(&payload.xml);
+                    rec.sender = payload.sender;
+                    rec.receiver = payload.receiver;
+                }
+            }
+            let rec_json = serde_json::to_vec(&rec)?;
+            let indexed: TransactionResult<(), anyhow::Error> =
+                jobs.transact(&[], |tx_jobs, _, index| {
+                    tx_jobs.insert(&k, rec_json.as_slice())?;
+                    index.insert(&rec)?;
+                    Ok(())
+                });
+            indexed.map_err(transaction_error)?;
+        }
+        Ok(())
+    }
+
+    /// Job that the entry `key` of the document or duplicate index points
+    /// at, if it has not failed, been rejected or been cancelled
+    fn live_job_for(&self, index: &sled::Tree, key: &str) -> Result<Option<JobRecord>> {
+        let Some(job_id) = index.get(key)? else {
+            return Ok(None);
+        };
+        let Some(v) = self.jobs_tree()?.get(job_id)? else {
+            return Ok(None);
+        };
+        let rec: JobRecord = serde_json::from_slice(&v)?;
+        Ok(is_live(rec.state).then_some(rec))
+    }
+
+    async fn enqueue(&self, payload: JobPayload, options: EnqueueOptions) -> Result<String> {
+        let duplicate_key = DuplicateKey::from_xml(&payload.xml).map(|k| k.to_string());
+        let canonical_hash = compute_canonical_sha256_hex(&payload.xml).ok();
         let hash = compute_sha256_hex(&payload.xml);
+        let index_key = document_index_key(
+            canonical_hash.as_deref().unwrap_or(&hash),
+            &payload.receiver,
+        );
+
+        let job_id = self.generate_job_id();
         let now = Utc::now();
         let rec = JobRecord {
             job_id: job_id.clone(),
-            state: "queued".to_string(),
+            state: DeliveryState::Queued,
             last_error: None,
             created_at: now,
             updated_at: now,
             transmission_id: None,
             invoice_hash: hash.clone(),
+            sender: payload.sender.clone(),
+            receiver: payload.receiver.clone(),
+            invoice_number: invoice_number(&payload.xml),
+            duplicate_key: duplicate_key.clone(),
+            canonical_hash: canonical_hash.clone(),
+            history: Vec::new(),
+            attempts: 0,
+            next_attempt_at: None,
+            status_checks: 0,
+            next_status_check_at: None,
+            failure: None,
+            manual_retries: 0,
+            responses: Vec::new(),
         };
 
         let jobs = self.jobs_tree()?;
         let payloads = self.payloads_tree()?;
+        let documents = self.documents_tree()?;
+        let duplicates = self.duplicates_tree()?;
+        let rec_json = serde_json::to_vec(&rec)?;
+        let payload_json = serde_json::to_vec(&payload)?;
+        let duplicate_keys =
+            duplicate_index_keys(duplicate_key.as_deref(), canonical_hash.as_deref());
 
-        jobs.insert(job_id.as_bytes(), serde_json::to_vec(&rec)?)?;
-        payloads.insert(job_id.as_bytes(), serde_json::to_vec(&payload)?)?;
+        // Checked and claimed in one transaction, so that of two enqueues of
+        // the same invoice only one gets through
+        let enqueued = jobs.transact(
+            &[&payloads, &documents, &duplicates],
+            |tx_jobs, others, index| {
+                let [tx_payloads, tx_documents, tx_duplicates] = others else {
+                    unreachable!()
+                };
+                // Enqueuing a document again returns the job already sending it
+                if !options.allow_duplicate {
+                    if let Some(existing) = live_entry(tx_jobs, tx_documents, &index_key)? {
+                        return Err(ConflictableTransactionError::Abort(Conflict::Enqueued(
+                            existing,
+                        )));
+                    }
+                }
 
-        // Audit log
-        let _ = write_audit_event(
-            &AuditEvent::new("job_enqueued", &job_id, "queued")
-                .with_hash(hash)
-                .with_parties(payload.sender.clone(), payload.receiver.clone()),
+                let mut duplicate_of = None;
+                for key in &duplicate_keys {
+                    match live_entry(tx_jobs, tx_duplicates, key)? {
+                        Some(existing) => {
+                            duplicate_of.get_or_insert(existing);
+                        }
+                        None => {
+                            tx_duplicates.insert(key.as_bytes(), job_id.as_bytes())?;
+                        }
+                    }
+                }
+                if let Some(existing) = &duplicate_of {
+                    if !options.allow_duplicate {
+                        return Err(ConflictableTransactionError::Abort(Conflict::Duplicate(
+                            existing.clone(),
+                        )));
+                    }
+                }
+
+                tx_jobs.insert(job_id.as_bytes(), rec_json.as_slice())?;
+                tx_payloads.insert(job_id.as_bytes(), payload_json.as_slice())?;
+                tx_documents.insert(index_key.as_bytes(), job_id.as_bytes())?;
+                index.insert(&rec)?;
+                Ok(duplicate_of)
+            },
         );
+        let duplicate_of = match enqueued {
+            Ok(duplicate_of) => duplicate_of,
+            Err(TransactionError::Abort(Conflict::Enqueued(existing))) => {
+                tracing::info!(
+                    job_id=%existing.job_id, state=%existing.state,
+                    "document already enqueued"
+                );
+                let _ = write_audit_event(&AuditEvent::new(
+                    "enqueue_deduplicated",
+                    &existing.job_id,
+                    existing.state,
+                ));
+                return Ok(existing.job_id);
+            }
+            Err(TransactionError::Abort(Conflict::Duplicate(existing))) => {
+                let reason = match &duplicate_key {
+                    Some(key) if existing.duplicate_key.as_ref() == Some(key) => key.as_str(),
+                    _ => "same document",
+                };
+                return Err(anyhow!(
+                    "Duplicate of job {} ({}): invoice already {}",
+                    existing.job_id,
+                    reason,
+                    existing.state
+                ));
+            }
+            Err(TransactionError::Abort(Conflict::Invalid(e))) => return Err(e),
+            Err(TransactionError::Storage(e)) => return Err(e.into()),
+        };
+
+        // Audit log
+        let mut event = AuditEvent::new("job_enqueued", &job_id, DeliveryState::Queued)
+            .with_hash(hash)
+            .with_parties(payload.sender.clone(), payload.receiver.clone());
+        if let Some(canonical_hash) = canonical_hash {
+            event = event.with_canonical_hash(canonical_hash);
+        }
+        let _ = write_audit_event(&event);
 
+        if let Some(existing) = duplicate_of {
+            let _ = write_audit_event(
+                &AuditEvent::new("duplicate_allowed", &job_id, DeliveryState::Queued)
+                    .with_error(format!("duplicate of job {}", existing.job_id)),
+            );
+        }
+
+        publish(
+            &self.events,
+            JobEvent::Enqueued {
+                job_id: job_id.clone(),
+                at: now,
+            },
+        );
         self.dispatch(job_id.clone());
         Ok(job_id)
     }
 
-    fn dispatch(&self, job_id: String) {
-        let jobs = self.jobs_tree().expect("jobs tree");
-        let payloads = self.payloads_tree().expect("payloads tree");
-        let client = Arc::clone(&self.access_point);
+    /// Enqueue an invoice for sending and return its job ID, see
+    /// [`enqueue_send_job_with`]
+    pub async fn enqueue_send_job(
+        &self,
+        xml: &str,
+        sender: &str,
+        receiver: &str,
+        profile: &str,
+        options: EnqueueOptions,
+    ) -> Result<String> {
+        let payload = JobPayload {
+            xml: xml.to_string(),
+            sender: sender.to_string(),
+            receiver: receiver.to_string(),
+            profile: profile.to_string(),
+            transmitted: None,
+        };
+        self.enqueue(payload, options).await
+    }
 
-        tokio::spawn(async move {
-            if let Err(e) = Self::process_job(client, jobs, payloads, job_id.clone()).await {
-                tracing::error!(job_id=%job_id, error=%e, "job processing failed");
+    /// Queued or sent job for the invoice identified by `key`, if any
+    pub fn find_duplicate(&self, key: &DuplicateKey) -> Result<Option<JobRecord>> {
+        let duplicates = self.duplicates_tree()?;
+        for index_key in duplicate_index_keys(Some(&key.to_string()), None) {
+            if let Some(rec) = self.live_job_for(&duplicates, &index_key)? {
+                return Ok(Some(rec));
             }
-        });
+        }
+        Ok(None)
     }
 
-    async fn process_job(
-        client: Arc<dyn AccessPointClient + 'static>,
-        jobs: sled::Tree,
-        payloads: sled::Tree,
-        job_id: String,
-    ) -> Result<()> {
-        update_state(&jobs, &job_id, |rec| {
-            rec.state = "in_flight".into();
-            rec.updated_at = Utc::now();
+    /// Submissions running and waiting for a free worker
+    pub fn worker_stats(&self) -> WorkerStats {
+        self.workers.stats()
+    }
+
+    /// Receive the events of all jobs from now on, see [`subscribe`]
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    /// Hand a job to the workers for submission; returns false if its last
+    /// task has not finished yet
+    fn dispatch(&self, job_id: String) -> bool {
+        self.start_workers();
+        self.workers.push(Task::Submit(job_id))
+    }
+
+    /// Start the workers, on the first dispatch
+    fn start_workers(&self) {
+        let Some(receiver) = self.workers.take_receiver() else {
+            return;
+        };
+        let receiver: SharedReceiver = Arc::new(tokio::sync::Mutex::new(receiver));
+        for _ in 0..self.workers.concurrency {
+            let queue = self.clone();
+            let receiver = receiver.clone();
+            tokio::spawn(async move { queue.work(receiver).await });
+        }
+    }
+
+    /// Submit dispatched jobs and check their status one at a time, within
+    /// the rate limit
+    async fn work(&self, receiver: SharedReceiver) {
+        loop {
+            let Some(task) = receiver.lock().await.recv().await else {
+                return;
+            };
+            self.workers.limiter.acquire().await;
+            self.workers.started();
+            match &task {
+                Task::Submit(job_id) => {
+                    if let Err(e) = self.process_job(job_id).await {
+                        tracing::error!(job_id=%job_id, error=%e, "job processing failed");
+                    }
+                }
+                Task::CheckStatus {
+                    job_id,
+                    transmission_id,
+                } => {
+                    if let Err(e) = self.check_status(job_id, transmission_id).await {
+                        tracing::error!(job_id=%job_id, error=%e, "status check failed");
+                    }
+                }
+            }
+            self.workers.finished(task.job_id());
+        }
+    }
+
+    /// Resume unfinished jobs, then dispatch retries and delivery status
+    /// checks as they fall due. Runs until the process exits.
+    pub async fn run_scheduler(&self) {
+        if let Err(e) = self.recover() {
+            tracing::error!(error=%e, "recovering unfinished jobs failed");
+        }
+        loop {
+            let next = match self.dispatch_due() {
+                Ok(next) => next,
+                Err(e) => {
+                    tracing::error!(error=%e, "job scheduling failed");
+                    Some(Utc::now() + chrono::Duration::seconds(5))
+                }
+            };
+            match next {
+                Some(at) => {
+                    let wait = (at - Utc::now()).to_std().unwrap_or_default();
+                    tokio::select! {
+                        _ = sleep(wait) => {}
+                        _ = self.wake.notified() => {}
+                    }
+                }
+                None => self.wake.notified().await,
+            }
+        }
+    }
+
+    /// Dispatch the retries and status checks that are due; returns when
+    /// the next one is
+    fn dispatch_due(&self) -> Result<Option<DateTime<Utc>>> {
+        let now = Utc::now();
+        let jobs = self.jobs_tree()?;
+        let mut next: Option<DateTime<Utc>> = None;
+        let mut later = |at: DateTime<Utc>| next = Some(next.map_or(at, |n| n.min(at)));
+        // Only queued and sent jobs have anything scheduled
+        let job_ids = jobs
+            .index
+            .in_state(DeliveryState::Queued)
+            .chain(jobs.index.in_state(DeliveryState::Sent));
+        for job_id in job_ids {
+            // Moved on or purged since it was indexed
+            let Some(v) = jobs.get(job_id?)? else {
+                continue;
+            };
+            let rec: JobRecord = serde_json::from_slice(&v)?;
+            let due = match rec.state {
+                DeliveryState::Queued => rec.next_attempt_at,
+                // Recovered jobs and ones sent by an earlier version have no
+                // check scheduled
+                DeliveryState::Sent => Some(rec.next_status_check_at.unwrap_or(now)),
+                _ => None,
+            };
+            match due {
+                Some(at) if at > now => later(at),
+                Some(_) if rec.state == DeliveryState::Queued => {
+                    // Its last submission has not finished yet; not looking
+                    // again could leave the scheduler asleep for good
+                    let busy = !self.dispatch(rec.job_id);
+                    if busy {
+                        later(now + chrono::Duration::from_std(BUSY_RECHECK)?);
+                    }
+                }
+                Some(_) => {
+                    let job_id = rec.job_id.clone();
+                    match self.poll(&jobs, rec) {
+                        Ok(Some(at)) => later(at),
+                        Ok(None) => {}
+                        Err(e) => tracing::error!(job_id=%job_id, error=%e, "status check failed"),
+                    }
+                }
+                None => {}
+            }
+        }
+        Ok(next)
+    }
+
+    /// Check the delivery status of a sent job, or dead-letter it once the
+    /// deadline
+    /// for a final status has passed; returns when it is checked next
+    fn poll(&self, jobs: &Jobs, rec: JobRecord) -> Result<Option<DateTime<Utc>>> {
+        let now = Utc::now();
+        let sent_at = rec.sent_at().unwrap_or(rec.updated_at);
+        if now - sent_at >= chrono::Duration::from_std(self.poll.deadline)? {
+            let error = format!(
+                "No delivery confirmation within {} hours",
+                self.poll.deadline.as_secs_f64() / 3600.0
+            );
+            self.dead_letter(
+                jobs,
+                &rec.job_id,
+                FailureKind::DeadlineExpired,
+                error,
+                "delivery_deadline_expired",
+            )?;
+            return Ok(None);
+        }
+
+        let transmission_id = rec
+            .transmission_id
+            .ok_or_else(|| anyhow!("sent job has no transmission ID"))?;
+        // The worker schedules the next check when it runs this one
+        if self.dispatch_status(rec.job_id, transmission_id) {
+            Ok(None)
+        } else {
+            Ok(Some(now + chrono::Duration::from_std(BUSY_RECHECK)?))
+        }
+    }
+
+    async fn process_job(&self, job_id: &str) -> Result<()> {
+        let jobs = self.jobs_tree()?;
+        let client = &self.access_point;
+
+        // The scheduler may dispatch a job again before its first dispatch
+        // picked it up; only one of them gets to move it on
+        let claimed = update_state(&jobs, job_id, DeliveryState::InFlight, |rec| {
+            rec.attempts += 1;
+            rec.next_attempt_at = None;
             rec.last_error = None;
-        })?;
+        });
+        let rec = match claimed {
+            Ok(rec) => rec,
+            Err(e) => {
+                tracing::debug!(job_id=%job_id, error=%e, "job not claimed");
+                return Ok(());
+            }
+        };
 
-        let payload_bytes = payloads
-            .get(job_id.as_bytes())?
-            .ok_or_else(|| anyhow!("payload missing"))?;
-        let payload: JobPayload = serde_json::from_slice(&payload_bytes)?;
+        let mut payload = self.payload(job_id)?;
 
-        let transmit_result = client
-            .submit(
-                &payload.xml,
-                &payload.sender,
-                &payload.receiver,
-                &payload.profile,
-            )
-            .await;
+        // Kept so that the job shows what was actually sent
+        let transmitted = client.prepare(&payload.xml);
+        let payload_hash = transmitted.as_ref().ok().map(|xml| compute_sha256_hex(xml));
+        let transmit_result = match transmitted {
+            Ok(xml) => {
+                payload.transmitted = (xml != payload.xml).then(|| xml.clone());
+                self.payloads_tree()?
+                    .insert(job_id.as_bytes(), serde_json::to_vec(&payload)?)?;
+                // The key stays the same across retries and restarts
+                client
+                    .submit_with_key(
+                        &xml,
+                        &payload.sender,
+                        &payload.receiver,
+                        &payload.profile,
+                        &rec.idempotency_key(),
+                    )
+                    .await
+            }
+            Err(err) => Err(err),
+        };
 
         match transmit_result {
             Ok(transmission_id) => {
-                update_state(&jobs, &job_id, |rec| {
-                    rec.state = "sent".into();
-                    rec.updated_at = Utc::now();
+                let interval = self.access_point.status_poll_interval();
+                let first_check =
+                    Utc::now() + chrono::Duration::from_std(self.poll.delay(interval, 0))?;
+                update_state(&jobs, job_id, DeliveryState::Sent, |rec| {
+                    rec.push_response(ProviderResponse {
+                        transmission_id: Some(transmission_id.clone()),
+                        payload_hash: payload_hash.clone(),
+                        ..ProviderResponse::new(ProviderOperation::Submit)
+                    });
                     rec.transmission_id = Some(transmission_id.clone());
+                    rec.status_checks = 0;
+                    rec.next_status_check_at = Some(first_check);
                 })?;
 
                 // Audit log
                 let _ = write_audit_event(
-                    &AuditEvent::new("invoice_submitted", &job_id, "sent")
+                    &AuditEvent::new("invoice_submitted", job_id, DeliveryState::Sent)
                         .with_transmission_id(transmission_id.clone()),
                 );
-
-                // Simulate polling for delivery (mock client reports delivered immediately).
-                sleep(Duration::from_millis(100)).await;
-
-                let status_res = client.status(transmission_id.as_str()).await;
-                match status_res {
-                    Ok(status) => {
-                        let final_state = match status.state {
-                            DeliveryState::Delivered => "delivered",
-                            DeliveryState::Failed => "failed",
-                            DeliveryState::InFlight => "in_flight",
-                            DeliveryState::Pending => "pending",
-                        };
-
-                        update_state(&jobs, &job_id, |rec| {
-                            rec.state = final_state.into();
-                            rec.updated_at = Utc::now();
-                            rec.last_error = match status.state {
-                                DeliveryState::Failed => status.message.clone(),
-                                _ => None,
-                            };
-                        })?;
-
-                        // Audit log
-                        let mut event =
-                            AuditEvent::new("delivery_status_updated", &job_id, final_state)
-                                .with_transmission_id(transmission_id.clone());
-                        if let DeliveryState::Failed = status.state {
-                            if let Some(msg) = status.message {
-                                event = event.with_error(msg);
-                            }
-                        }
-                        let _ = write_audit_event(&event);
-                    }
-                    Err(err) => {
-                        update_state(&jobs, &job_id, |rec| {
-                            rec.state = "failed".into();
-                            rec.updated_at = Utc::now();
-                            rec.last_error = Some(format!("status error: {err}"));
-                        })?;
-
-                        // Audit log
-                        let _ = write_audit_event(
-                            &AuditEvent::new("delivery_status_error", &job_id, "failed")
-                                .with_error(err.to_string()),
-                        );
-                    }
-                }
+                self.wake.notify_one();
             }
             Err(err) => {
-                update_state(&jobs, &job_id, |rec| {
-                    rec.state = "failed".into();
-                    rec.updated_at = Utc::now();
-                    rec.last_error = Some(err.to_string());
+                update_job(&jobs, job_id, |rec| {
+                    rec.push_response(ProviderResponse {
+                        message: Some(err.to_string()),
+                        payload_hash: payload_hash.clone(),
+                        ..ProviderResponse::new(ProviderOperation::Submit)
+                    });
+                    Ok(())
+                })?;
+                self.submission_failed(&jobs, job_id, rec.attempts, err)?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hand a sent job to the workers for a status check; returns false if
+    /// its last task has not finished yet
+    fn dispatch_status(&self, job_id: String, transmission_id: String) -> bool {
+        self.start_workers();
+        self.workers.push(Task::CheckStatus {
+            job_id,
+            transmission_id,
+        })
+    }
+
+    /// Ask the access point how a sent job is doing and record the answer
+    async fn check_status(&self, job_id: &str, transmission_id: &str) -> Result<()> {
+        let jobs = self.jobs_tree()?;
+        // Counted and the next one scheduled only now that the check runs, so
+        // that checks waiting for a worker do not use up the deadline
+        let interval = self.access_point.status_poll_interval();
+        let rec = update_job(&jobs, job_id, |rec| {
+            if rec.state == DeliveryState::Sent {
+                rec.status_checks += 1;
+                let delay = self.poll.delay(interval, rec.status_checks);
+                rec.next_status_check_at = Some(Utc::now() + chrono::Duration::from_std(delay)?);
+            }
+            Ok(())
+        })?;
+        // Delivered or dead-lettered while the check waited
+        if rec.state != DeliveryState::Sent {
+            return Ok(());
+        }
+        self.wake.notify_one();
+        let result = self.access_point.status(transmission_id).await;
+        let response = match &result {
+            Ok(status) => ProviderResponse {
+                state: Some(status.state),
+                message: status.message.clone(),
+                ..ProviderResponse::new(ProviderOperation::Status)
+            },
+            Err(err) => ProviderResponse {
+                message: Some(err.to_string()),
+                ..ProviderResponse::new(ProviderOperation::Status)
+            },
+        };
+        update_job(&jobs, job_id, |rec| {
+            rec.push_response(ProviderResponse {
+                transmission_id: Some(transmission_id.to_string()),
+                ..response.clone()
+            });
+            Ok(())
+        })?;
+        match result {
+            // Still on its way
+            Ok(status) if status.state == DeliveryState::Sent => {}
+            Ok(status) if status.state == DeliveryState::Failed => {
+                let error = status
+                    .message
+                    .unwrap_or_else(|| "delivery failed".to_string());
+                self.dead_letter(
+                    &jobs,
+                    job_id,
+                    FailureKind::DeliveryFailed,
+                    error,
+                    "delivery_status_updated",
+                )?;
+            }
+            Ok(status) => {
+                let error = match status.state {
+                    DeliveryState::Rejected => status.message,
+                    _ => None,
+                };
+                update_state(&jobs, job_id, status.state, |rec| {
+                    rec.last_error = error.clone();
+                    rec.next_status_check_at = None;
                 })?;
 
                 // Audit log
-                let _ = write_audit_event(
-                    &AuditEvent::new("submission_failed", &job_id, "failed")
-                        .with_error(err.to_string()),
-                );
+                let mut event = AuditEvent::new("delivery_status_updated", job_id, status.state)
+                    .with_transmission_id(transmission_id.to_string());
+                if let Some(msg) = error {
+                    event = event.with_error(msg);
+                }
+                let _ = write_audit_event(&event);
+            }
+            // The job stays sent and is checked again at its next poll
+            Err(err) if transient(&err).is_some() => {
+                tracing::warn!(job_id=%job_id, error=%err, "delivery status unavailable");
+            }
+            Err(err) => self.dead_letter(
+                &jobs,
+                job_id,
+                FailureKind::StatusError,
+                format!("status error: {err}"),
+                "delivery_status_error",
+            )?,
+        }
+        Ok(())
+    }
+
+    /// Pick up the jobs an earlier run left unfinished
+    fn recover(&self) -> Result<()> {
+        let jobs = self.jobs_tree()?;
+        for item in jobs.iter() {
+            let (_k, v) = item?;
+            let rec: JobRecord = serde_json::from_slice(&v)?;
+            let job_id = rec.job_id.clone();
+            if let Err(e) = self.recover_job(&jobs, rec) {
+                tracing::error!(job_id=%job_id, error=%e, "job recovery failed");
+            }
+        }
+        Ok(())
+    }
+
+    /// Submit a queued job not waiting for a retry, mark one submitted just
+    /// before the application closed sent, queue again one interrupted
+    /// during submission and dead-letter one that failed before it could be
+    fn recover_job(&self, jobs: &Jobs, rec: JobRecord) -> Result<()> {
+        let job_id = rec.job_id;
+        let state = match (rec.state, rec.transmission_id) {
+            (DeliveryState::Queued, _) if rec.next_attempt_at.is_none() => {
+                self.dispatch(job_id.clone());
+                DeliveryState::Queued
+            }
+            // Its status is checked at the first poll
+            (DeliveryState::InFlight, Some(_)) => {
+                update_state(jobs, &job_id, DeliveryState::Sent, |rec| {
+                    rec.next_status_check_at = None;
+                })?;
+                DeliveryState::Sent
+            }
+            // Whether the access point received it is unknown
+            (DeliveryState::InFlight, None) => {
+                update_state(jobs, &job_id, DeliveryState::Queued, |rec| {
+                    rec.last_error = Some("interrupted during submission".to_string());
+                })?;
+                self.dispatch(job_id.clone());
+                DeliveryState::Queued
+            }
+            (DeliveryState::Failed, _) => {
+                update_state(jobs, &job_id, DeliveryState::DeadLetter, |rec| {
+                    rec.failure = Some(FailureKind::Unknown);
+                })?;
+                DeliveryState::DeadLetter
             }
+            _ => return Ok(()),
+        };
+        tracing::info!(job_id=%job_id, %state, "resuming unfinished job");
+
+        // Audit log
+        let _ = write_audit_event(&AuditEvent::new("job_recovered", &job_id, state));
+        Ok(())
+    }
+
+    /// Queue a job whose submission failed for another attempt if the error
+    /// is transient and attempts remain, dead-letter it otherwise
+    fn submission_failed(
+        &self,
+        jobs: &Jobs,
+        job_id: &str,
+        attempts: u32,
+        err: anyhow::Error,
+    ) -> Result<()> {
+        let transient = transient(&err);
+        // Slow down every worker, not just this job
+        if let Some(t) = transient.filter(|t| t.throttled || t.retry_after.is_some()) {
+            tracing::warn!(retry_after=?t.retry_after, "access point is throttling submissions");
+            self.workers.limiter.throttle(t.retry_after);
         }
+        let delay = transient.and_then(|t| self.retry.next_delay(attempts, t.retry_after));
+        let Some(delay) = delay else {
+            let failure = match transient {
+                Some(_) => FailureKind::RetriesExhausted,
+                None => FailureKind::Refused,
+            };
+            return self.dead_letter(jobs, job_id, failure, err.to_string(), "submission_failed");
+        };
 
+        let at = Utc::now() + chrono::Duration::from_std(delay)?;
+        update_state(jobs, job_id, DeliveryState::Queued, |rec| {
+            rec.last_error = Some(err.to_string());
+            rec.next_attempt_at = Some(at);
+        })?;
+        tracing::info!(
+            job_id=%job_id, attempts, retry_at=%at, error=%err,
+            "submission will be retried"
+        );
+
+        // Audit log
+        let _ = write_audit_event(
+            &AuditEvent::new("submission_retry_scheduled", job_id, DeliveryState::Queued)
+                .with_error(err.to_string()),
+        );
+        publish(
+            &self.events,
+            JobEvent::RetryScheduled {
+                job_id: job_id.to_string(),
+                attempts,
+                retry_at: at,
+                error: err.to_string(),
+            },
+        );
+        self.wake.notify_one();
         Ok(())
     }
 
@@ -209,37 +1070,266 @@ impl Queue {
             .collect()
     }
 
-    fn list(&self) -> Result<Vec<JobRecord>> {
+    /// A job with its state history, the access point's answers and the
+    /// invoice it sends
+    pub fn get_job(&self, job_id: &str) -> Result<JobDetail> {
+        let record = self.job(job_id)?;
+        let payload = self.payload(job_id)?;
+        let submitted = record.responses.iter().any(|r| r.payload_hash.is_some());
+        let transmitted_xml = payload
+            .transmitted
+            .or_else(|| submitted.then(|| payload.xml.clone()));
+        Ok(JobDetail {
+            record,
+            profile: payload.profile,
+            xml: payload.xml,
+            transmitted_xml,
+        })
+    }
+
+    /// One page of the jobs matching `query`, newest first, read through
+    /// the index that narrows them down most
+    pub fn list_status(&self, query: &JobQuery) -> Result<JobPage> {
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
         let jobs = self.jobs_tree()?;
-        let mut out = Vec::new();
-        for item in jobs.iter() {
-            let (_k, v) = item?;
+        let mut page = JobPage::default();
+        for job_id in jobs.index.scan(query)? {
+            // Purged since it was indexed
+            let Some(v) = jobs.get(job_id?)? else {
+                continue;
+            };
             let rec: JobRecord = serde_json::from_slice(&v)?;
-            out.push(rec);
+            if !query.matches(&rec) {
+                continue;
+            }
+            if page.jobs.len() == limit {
+                page.next_cursor = page.jobs.last().map(index::cursor);
+                break;
+            }
+            page.jobs.push(rec);
         }
-        out.sort_by_key(|r| r.created_at);
-        out.reverse();
-        Ok(out)
+        Ok(page)
+    }
+}
+
+/// The jobs tree with its secondary indexes, which [`update_job`] keeps in
+/// step with it, and the channel it announces state changes on
+struct Jobs {
+    tree: sled::Tree,
+    index: JobIndex,
+    events: broadcast::Sender<JobEvent>,
+}
+
+impl Jobs {
+    /// Run `f` in a transaction over the jobs, `others` and the index, with
+    /// the views of `others` in the same order. Like any transaction, `f` is
+    /// run again if another write got in between.
+    fn transact<A, E, F>(&self, others: &[&sled::Tree], f: F) -> TransactionResult<A, E>
+    where
+        F: Fn(
+            &TransactionalTree,
+            &[TransactionalTree],
+            IndexTx,
+        ) -> ConflictableTransactionResult<A, E>,
+    {
+        let mut trees = vec![&self.tree];
+        trees.extend_from_slice(others);
+        trees.extend(self.index.trees());
+        trees.as_slice().transaction(|views| {
+            let (tx_jobs, rest) = views.split_first().expect("jobs tree");
+            let (tx_others, tx_index) = rest.split_at(others.len());
+            f(tx_jobs, tx_others, IndexTx::new(tx_index))
+        })
+    }
+}
+
+impl Deref for Jobs {
+    type Target = sled::Tree;
+
+    fn deref(&self) -> &sled::Tree {
+        &self.tree
+    }
+}
+
+/// Number of the invoice in `xml`, in an SBDH or not
+fn invoice_number(xml: &str) -> Option<String> {
+    let payload = sbdh::payload(xml);
+    parse_ubl_invoice(payload.as_deref().unwrap_or(xml))
+        .ok()
+        .map(|inv| inv.invoice_number.trim().to_string())
+        .filter(|n| !n.is_empty() && n != "UNKNOWN")
+}
+
+/// Whether a job still counts as sending its document
+fn is_live(state: DeliveryState) -> bool {
+    !matches!(
+        state,
+        DeliveryState::Failed
+            | DeliveryState::Rejected
+            | DeliveryState::DeadLetter
+            | DeliveryState::Cancelled
+    )
+}
+
+/// Key of the document index: the document's canonical hash (its plain
+/// hash if it could not be canonicalized) and its receiver
+fn document_index_key(hash: &str, receiver: &str) -> String {
+    format!("{hash}:{receiver}")
+}
+
+/// Keys of the duplicate index for a job with `duplicate_key` and
+/// `canonical_hash`
+fn duplicate_index_keys(duplicate_key: Option<&str>, canonical_hash: Option<&str>) -> Vec<String> {
+    let invoice = duplicate_key.map(|key| format!("invoice:{key}"));
+    let document = canonical_hash.map(|hash| format!("document:{hash}"));
+    invoice.into_iter().chain(document).collect()
+}
+
+/// Why an enqueue transaction did not add the job
+enum Conflict {
+    /// The document is already being sent to the receiver by this job
+    Enqueued(Box<JobRecord>),
+    /// The invoice is already being sent by this job
+    Duplicate(Box<JobRecord>),
+    /// A stored job record could not be read
+    Invalid(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Conflict {
+    fn from(e: anyhow::Error) -> Self {
+        Conflict::Invalid(e)
+    }
+}
+
+/// [`Queue::live_job_for`] inside a transaction
+fn live_entry<E: From<anyhow::Error>>(
+    jobs: &TransactionalTree,
+    index: &TransactionalTree,
+    key: &str,
+) -> std::result::Result<Option<Box<JobRecord>>, ConflictableTransactionError<E>> {
+    let Some(job_id) = index.get(key)? else {
+        return Ok(None);
+    };
+    let Some(v) = jobs.get(job_id)? else {
+        return Ok(None);
+    };
+    let rec: JobRecord = serde_json::from_slice(&v)
+        .map_err(|e| ConflictableTransactionError::Abort(anyhow::Error::from(e).into()))?;
+    Ok(is_live(rec.state).then(|| Box::new(rec)))
+}
+
+/// Abort a transaction with `e`
+fn abort(e: impl Into<anyhow::Error>) -> ConflictableTransactionError<anyhow::Error> {
+    ConflictableTransactionError::Abort(e.into())
+}
+
+/// The error a transaction aborted with, or the storage error it failed on
+fn transaction_error(e: TransactionError<anyhow::Error>) -> anyhow::Error {
+    match e {
+        TransactionError::Abort(e) => e,
+        TransactionError::Storage(e) => e.into(),
+    }
+}
+
+/// Apply `f` to a job's record and store it, unless `f` fails. Workers, the
+/// poller and the scheduler update records concurrently; when another update
+/// gets in first, `f` is applied again to the record it stored.
+fn update_job<F>(jobs: &Jobs, job_id: &str, f: F) -> Result<JobRecord>
+where
+    F: Fn(&mut JobRecord) -> Result<()>,
+{
+    let (rec, state) = jobs
+        .transact(&[], |tx_jobs, _, index| {
+            let existing = tx_jobs
+                .get(job_id)?
+                .ok_or_else(|| abort(anyhow!("job not found: {job_id}")))?;
+            let mut rec: JobRecord = serde_json::from_slice(&existing).map_err(abort)?;
+            let state = rec.state;
+            f(&mut rec).map_err(abort)?;
+            tx_jobs.insert(job_id.as_bytes(), serde_json::to_vec(&rec).map_err(abort)?)?;
+            if rec.state != state {
+                index.state_changed(&rec, state)?;
+            }
+            Ok((rec, state))
+        })
+        .map_err(transaction_error)?;
+    announce(jobs, &rec, state);
+    Ok(rec)
+}
+
+/// Publish the state change of `rec`, if it left state `from`
+fn announce(jobs: &Jobs, rec: &JobRecord, from: DeliveryState) {
+    if rec.state == from {
+        return;
+    }
+    publish(
+        &jobs.events,
+        JobEvent::StateChanged {
+            job_id: rec.job_id.clone(),
+            from,
+            to: rec.state,
+            at: rec.updated_at,
+            error: rec.last_error.clone(),
+        },
+    );
+    if rec.state == DeliveryState::Delivered {
+        publish(
+            &jobs.events,
+            JobEvent::Delivered {
+                job_id: rec.job_id.clone(),
+                transmission_id: rec.transmission_id.clone(),
+                at: rec.updated_at,
+            },
+        );
     }
 }
 
-fn update_state<F>(jobs: &sled::Tree, job_id: &str, mut f: F) -> Result<()>
+/// Move a job to state `next`, applying `f` to its record first
+///
+/// Transitions the state machine does not allow are rejected without
+/// changing the job; allowed ones are appended to its history.
+fn update_state<F>(jobs: &Jobs, job_id: &str, next: DeliveryState, f: F) -> Result<JobRecord>
 where
-    F: FnMut(&mut JobRecord),
+    F: Fn(&mut JobRecord),
 {
-    let key = job_id.as_bytes();
-    let existing = jobs
-        .get(key)?
-        .ok_or_else(|| anyhow!("job not found: {job_id}"))?;
-    let mut rec: JobRecord = serde_json::from_slice(&existing)?;
-    f(&mut rec);
-    jobs.insert(key, serde_json::to_vec(&rec)?)?;
+    update_job(jobs, job_id, |rec| transition(rec, next, &f))
+}
+
+/// [`update_state`] on a record read already
+fn transition<F>(rec: &mut JobRecord, next: DeliveryState, f: F) -> Result<()>
+where
+    F: Fn(&mut JobRecord),
+{
+    if !rec.state.can_transition_to(next) {
+        bail!(
+            "job {}: illegal state transition {} -> {}",
+            rec.job_id,
+            rec.state,
+            next
+        );
+    }
+    f(rec);
+    let now = Utc::now();
+    rec.history.push(StateTransition {
+        from: rec.state,
+        to: next,
+        at: now,
+        error: rec.last_error.clone(),
+    });
+    rec.state = next;
+    rec.updated_at = now;
     Ok(())
 }
 
 pub fn init(access_point: Arc<dyn AccessPointClient + 'static>) -> Result<()> {
-    let db = sled::open(".einv_queue")?;
-    let queue = Arc::new(Queue::new(db, access_point));
+    init_with(access_point, QueueOptions::default())
+}
+
+pub fn init_with(
+    access_point: Arc<dyn AccessPointClient + 'static>,
+    options: QueueOptions,
+) -> Result<()> {
+    let queue = Arc::new(Queue::open(".einv_queue", access_point, options)?);
     GLOBAL_QUEUE
         .set(queue)
         .map_err(|_| anyhow!("queue already initialized"))?;
@@ -252,21 +1342,112 @@ pub async fn enqueue_send_job(
     receiver: &str,
     profile: &str,
 ) -> Result<String> {
-    let payload = JobPayload {
-        xml: xml.to_string(),
-        sender: sender.to_string(),
-        receiver: receiver.to_string(),
-        profile: profile.to_string(),
-    };
+    enqueue_send_job_with(xml, sender, receiver, profile, EnqueueOptions::default()).await
+}
+
+/// Enqueue an invoice for sending and return its job ID. The same document
+/// enqueued again for the same receiver returns the existing job instead,
+/// unless that job failed or was rejected or `allow_duplicate` is set.
+pub async fn enqueue_send_job_with(
+    xml: &str,
+    sender: &str,
+    receiver: &str,
+    profile: &str,
+    options: EnqueueOptions,
+) -> Result<String> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    queue
+        .enqueue_send_job(xml, sender, receiver, profile, options)
+        .await
+}
+
+/// Queued or sent job for the invoice identified by `key`, if any
+pub fn find_duplicate(key: &DuplicateKey) -> Result<Option<JobRecord>> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    queue.find_duplicate(key)
+}
+
+/// One page of the jobs matching `query`, newest first. Pass the page's
+/// `next_cursor` in the next query for the page after it.
+pub fn list_status(query: &JobQuery) -> Result<JobPage> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    queue.list_status(query)
+}
+
+/// A job with its state history, the access point's answers and the invoice
+/// it sends
+pub fn get_job(job_id: &str) -> Result<JobDetail> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    queue.get_job(job_id)
+}
+
+/// Queue a failed job again with a fresh set of attempts
+pub fn retry_job(job_id: &str) -> Result<JobRecord> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    queue.retry_job(job_id)
+}
+
+/// Retry every dead letter matching `filter` and return the retried job IDs.
+/// Dead letters whose document has since been enqueued again are skipped.
+pub fn retry_dead_letters(filter: &DeadLetterFilter) -> Result<Vec<String>> {
     let queue = GLOBAL_QUEUE
         .get()
         .ok_or_else(|| anyhow!("queue not initialized"))?;
-    queue.enqueue(payload).await
+    queue.retry_dead_letters(filter)
 }
 
-pub fn list_status() -> Result<Vec<JobRecord>> {
+/// Cancel a queued job, including one waiting for a retry
+pub fn cancel_job(job_id: &str) -> Result<JobRecord> {
     let queue = GLOBAL_QUEUE
         .get()
         .ok_or_else(|| anyhow!("queue not initialized"))?;
-    queue.list()
+    queue.cancel_job(job_id)
+}
+
+/// Delete the dead letters matching `filter` and return how many were
+/// deleted
+pub fn purge_dead_letters(filter: &DeadLetterFilter) -> Result<usize> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    queue.purge_dead_letters(filter)
+}
+
+/// Submissions running and waiting for a free worker
+pub fn worker_stats() -> Result<WorkerStats> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    Ok(queue.worker_stats())
+}
+
+/// Receive the events of all jobs from now on. A receiver that falls more
+/// than 1024 events behind misses the oldest ones and is told how many.
+pub fn subscribe() -> Result<broadcast::Receiver<JobEvent>> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    Ok(queue.subscribe())
+}
+
+/// Resume jobs left unfinished when the application last closed, then
+/// submit jobs waiting for a retry and check the delivery status of sent
+/// ones as they fall due. Runs until the
+/// process exits; spawn it once after [`init`].
+pub async fn run_scheduler() -> Result<()> {
+    let queue = GLOBAL_QUEUE
+        .get()
+        .ok_or_else(|| anyhow!("queue not initialized"))?;
+    queue.run_scheduler().await;
+    Ok(())
 }
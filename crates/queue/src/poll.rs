@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+/// How the delivery status of sent jobs is checked
+///
+/// The first check comes one access point poll interval (or `interval`, if
+/// set) after sending; the wait grows by `multiplier` after each check that
+/// found the invoice still on its way, up to `max_interval`. Jobs without a
+/// final status `deadline` after sending fail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollPolicy {
+    /// Overrides the access point's own poll interval
+    pub interval: Option<Duration>,
+    pub max_interval: Duration,
+    pub multiplier: f64,
+    pub deadline: Duration,
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            max_interval: Duration::from_secs(60 * 60),
+            multiplier: 1.5,
+            deadline: Duration::from_secs(72 * 60 * 60),
+        }
+    }
+}
+
+impl PollPolicy {
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn with_max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Wait before the next check after `checks` checks, for an access point
+    /// polled every `provider_interval`
+    pub fn delay(&self, provider_interval: Duration, checks: u32) -> Duration {
+        let base = self.interval.unwrap_or(provider_interval);
+        let max = self.max_interval.max(base);
+        let exponent = checks.min(i32::MAX as u32) as i32;
+        let delay = base.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::try_from_secs_f64(delay.min(max.as_secs_f64())).unwrap_or(max)
+    }
+}
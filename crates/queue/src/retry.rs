@@ -0,0 +1,84 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Longest `Retry-After` honoured; servers asking for more are retried sooner
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How submissions that failed transiently are retried
+///
+/// After `n` failed attempts the job waits `initial_delay * multiplier^(n-1)`,
+/// capped at `max_delay`, less a random part of up to `jitter` of it so that
+/// jobs failing together do not retry together. A `Retry-After` sent by the
+/// access point is always waited out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Submission attempts per job, including the first
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    /// Fraction of each delay that is randomized, from 0 to 1
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(30 * 60),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that fails jobs on their first error
+    pub fn never() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_delays(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_delay = initial;
+        self.max_delay = max.max(initial);
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Wait after `attempts` failed attempts, before jitter
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        Duration::try_from_secs_f64(delay.min(self.max_delay.as_secs_f64()))
+            .unwrap_or(self.max_delay)
+    }
+
+    /// Wait before the next attempt after `attempts` failed ones, `None` once
+    /// all attempts are used
+    pub fn next_delay(&self, attempts: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        let jitter = self.jitter.clamp(0.0, 1.0) * rand::thread_rng().gen::<f64>();
+        let delay = self.backoff(attempts).mul_f64(1.0 - jitter);
+        Some(match retry_after {
+            Some(after) => delay.max(after.min(MAX_RETRY_AFTER)),
+            None => delay,
+        })
+    }
+}
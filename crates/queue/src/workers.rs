@@ -0,0 +1,209 @@
+use access_point::RateLimit;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Instant};
+
+/// How many requests are made to the access point at once, and how fast
+///
+/// At most `concurrency` submissions and delivery status checks run at the
+/// same time, started no faster than the access point's rate limit (or
+/// `rate_limit`, if set) allows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkerPolicy {
+    pub concurrency: usize,
+    /// Overrides the access point's own rate limit
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for WorkerPolicy {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            rate_limit: None,
+        }
+    }
+}
+
+impl WorkerPolicy {
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+}
+
+/// Token bucket holding up to `burst` tokens, refilled at `per_second`
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(limit.burst),
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Wait for a token
+    pub async fn acquire(&self) {
+        loop {
+            let wait = self.try_acquire();
+            if wait.is_zero() {
+                return;
+            }
+            sleep(wait).await;
+        }
+    }
+
+    /// Take a token if one is available, or return how long until one is
+    fn try_acquire(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        if let Some(until) = bucket.paused_until {
+            if until > now {
+                return until - now;
+            }
+            bucket.paused_until = None;
+            bucket.refilled_at = now;
+        }
+        let elapsed = (now - bucket.refilled_at).as_secs_f64();
+        bucket.tokens =
+            (bucket.tokens + elapsed * self.limit.per_second).min(f64::from(self.limit.burst));
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Duration::ZERO;
+        }
+        let missing = (1.0 - bucket.tokens) / self.limit.per_second.max(f64::EPSILON);
+        Duration::try_from_secs_f64(missing)
+            .unwrap_or(Duration::MAX)
+            .max(Duration::from_millis(1))
+    }
+
+    /// Back off after the access point asked for fewer requests: hold all
+    /// tokens for `retry_after`, or empty the bucket if it gave no time
+    pub fn throttle(&self, retry_after: Option<Duration>) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.tokens = 0.0;
+        bucket.refilled_at = Instant::now();
+        if let Some(wait) = retry_after {
+            let until = Instant::now() + wait;
+            bucket.paused_until = Some(bucket.paused_until.map_or(until, |u| u.max(until)));
+        }
+    }
+}
+
+/// Submissions and status checks running and waiting for a worker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerStats {
+    pub in_flight: usize,
+    pub queued: usize,
+    pub concurrency: usize,
+}
+
+/// A request to the access point for one job
+#[derive(Debug, Clone)]
+pub(crate) enum Task {
+    Submit(String),
+    CheckStatus {
+        job_id: String,
+        transmission_id: String,
+    },
+}
+
+impl Task {
+    pub(crate) fn job_id(&self) -> &str {
+        match self {
+            Task::Submit(job_id) | Task::CheckStatus { job_id, .. } => job_id,
+        }
+    }
+}
+
+/// Jobs dispatched for submission or a status check, handed to a fixed
+/// number of workers
+pub(crate) struct WorkerPool {
+    pub(crate) concurrency: usize,
+    pub(crate) limiter: RateLimiter,
+    sender: mpsc::UnboundedSender<Task>,
+    /// Taken by the first dispatch, which starts the workers
+    receiver: Mutex<Option<mpsc::UnboundedReceiver<Task>>>,
+    /// Jobs with a task not yet finished, so that a job is not dispatched
+    /// twice while it waits
+    pending: Mutex<HashSet<String>>,
+    in_flight: AtomicUsize,
+}
+
+impl WorkerPool {
+    pub(crate) fn new(concurrency: usize, rate_limit: RateLimit) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            concurrency: concurrency.max(1),
+            limiter: RateLimiter::new(rate_limit),
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+            pending: Mutex::new(HashSet::new()),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Queue `task` for a worker unless its job has a task waiting or
+    /// running already; returns whether it was queued
+    pub(crate) fn push(&self, task: Task) -> bool {
+        let queued = self
+            .pending
+            .lock()
+            .unwrap()
+            .insert(task.job_id().to_string());
+        if queued {
+            // The receiver lives as long as the pool
+            let _ = self.sender.send(task);
+        }
+        queued
+    }
+
+    /// The receiver to start the workers with, the first time only
+    pub(crate) fn take_receiver(&self) -> Option<mpsc::UnboundedReceiver<Task>> {
+        self.receiver.lock().unwrap().take()
+    }
+
+    pub(crate) fn started(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn finished(&self, job_id: &str) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.pending.lock().unwrap().remove(job_id);
+    }
+
+    pub(crate) fn stats(&self) -> WorkerStats {
+        let in_flight = self.in_flight.load(Ordering::SeqCst);
+        WorkerStats {
+            in_flight,
+            queued: self.pending.lock().unwrap().len().saturating_sub(in_flight),
+            concurrency: self.concurrency,
+        }
+    }
+}
+
+/// Receiver shared by the workers; each takes the next job when it is free
+pub(crate) type SharedReceiver = Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<Task>>>;
@@ -0,0 +1,205 @@
+//! Queues on temporary databases, sending through a scripted access point
+
+#![allow(dead_code)]
+
+use access_point::error::HttpError;
+use access_point::{AccessPointClient, DeliveryStatus, RateLimit};
+use anyhow::Result;
+use async_trait::async_trait;
+use lat_einv_core::models::DeliveryState;
+use queue::{Queue, QueueOptions};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Access point that accepts submissions and reports `status` for them
+pub struct TestAccessPoint {
+    /// Submissions never finish while set
+    pub hang: AtomicBool,
+    /// Number of coming submissions that fail with 503 Service Unavailable
+    pub unavailable: AtomicUsize,
+    /// Submissions fail with 400 Bad Request while set
+    pub rejecting: AtomicBool,
+    /// Submissions are signed, by appending a comment, while set
+    pub signing: AtomicBool,
+    /// Idempotency key of each submission
+    pub keys: Mutex<Vec<String>>,
+    /// Invoice of each submission
+    pub invoices: Mutex<Vec<String>>,
+    pub submitted: AtomicUsize,
+    pub status_checks: AtomicUsize,
+    pub status: Mutex<DeliveryState>,
+    /// How long a status check takes
+    pub status_delay: Mutex<Duration>,
+    status_running: AtomicUsize,
+    /// Most status checks that ran at the same time
+    pub max_status_running: AtomicUsize,
+}
+
+impl TestAccessPoint {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            hang: AtomicBool::new(false),
+            unavailable: AtomicUsize::new(0),
+            rejecting: AtomicBool::new(false),
+            signing: AtomicBool::new(false),
+            keys: Mutex::new(Vec::new()),
+            invoices: Mutex::new(Vec::new()),
+            submitted: AtomicUsize::new(0),
+            status_checks: AtomicUsize::new(0),
+            status: Mutex::new(DeliveryState::Delivered),
+            status_delay: Mutex::new(Duration::ZERO),
+            status_running: AtomicUsize::new(0),
+            max_status_running: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn hanging() -> Arc<Self> {
+        let access_point = Self::new();
+        access_point.hang.store(true, Ordering::SeqCst);
+        access_point
+    }
+}
+
+#[async_trait]
+impl AccessPointClient for TestAccessPoint {
+    async fn submit(
+        &self,
+        xml: &str,
+        _sender: &str,
+        _receiver: &str,
+        _profile: &str,
+    ) -> Result<String> {
+        self.invoices.lock().unwrap().push(xml.to_string());
+        let n = self.submitted.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.hang.load(Ordering::SeqCst) {
+            std::future::pending::<()>().await;
+        }
+        let unavailable = self
+            .unavailable
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        let status = if unavailable.is_ok() {
+            reqwest::StatusCode::SERVICE_UNAVAILABLE
+        } else if self.rejecting.load(Ordering::SeqCst) {
+            reqwest::StatusCode::BAD_REQUEST
+        } else {
+            return Ok(format!("tx-{n}"));
+        };
+        Err(anyhow::Error::new(HttpError {
+            operation: "Test submit".to_string(),
+            status,
+            retry_after: None,
+            body: String::new(),
+        }))
+    }
+
+    async fn submit_with_key(
+        &self,
+        xml: &str,
+        sender: &str,
+        receiver: &str,
+        profile: &str,
+        idempotency_key: &str,
+    ) -> Result<String> {
+        self.keys.lock().unwrap().push(idempotency_key.to_string());
+        self.submit(xml, sender, receiver, profile).await
+    }
+
+    fn prepare(&self, xml: &str) -> Result<String> {
+        match self.signing.load(Ordering::SeqCst) {
+            true => Ok(format!("{xml}<!-- signed -->")),
+            false => Ok(xml.to_string()),
+        }
+    }
+
+    async fn status(&self, transmission_id: &str) -> Result<DeliveryStatus> {
+        self.status_checks.fetch_add(1, Ordering::SeqCst);
+        let running = self.status_running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_status_running.fetch_max(running, Ordering::SeqCst);
+        let delay = *self.status_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+        self.status_running.fetch_sub(1, Ordering::SeqCst);
+        Ok(DeliveryStatus {
+            transmission_id: transmission_id.to_string(),
+            state: *self.status.lock().unwrap(),
+            message: None,
+        })
+    }
+
+    fn status_poll_interval(&self) -> Duration {
+        Duration::from_millis(50)
+    }
+
+    fn submit_rate_limit(&self) -> RateLimit {
+        RateLimit::new(1000.0, 1000)
+    }
+}
+
+/// Database directory, removed when dropped
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        Self(std::env::temp_dir().join(format!("einv-queue-{}", rand::random::<u64>())))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn open(dir: &TempDir, access_point: Arc<TestAccessPoint>) -> Queue {
+    open_with(dir, access_point, QueueOptions::default())
+}
+
+pub fn open_with(
+    dir: &TempDir,
+    access_point: Arc<TestAccessPoint>,
+    options: QueueOptions,
+) -> Queue {
+    Queue::open(&dir.0, access_point, options).unwrap()
+}
+
+/// Run `phase` on a runtime of its own, then drop the runtime with whatever
+/// it was still doing, as if the application had been closed
+pub fn run_until_closed<F: std::future::Future<Output = ()>>(phase: F) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(phase);
+}
+
+/// Invoice `number` of supplier 40003032949; `note` changes the document
+/// but not the invoice
+pub fn invoice(number: &str, note: &str) -> String {
+    format!(
+        r#"<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2" xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2" xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>{number}</cbc:ID>
+  <cbc:IssueDate>2026-10-01</cbc:IssueDate>
+  <cbc:Note>{note}</cbc:Note>
+  <cac:AccountingSupplierParty>
+    <cac:Party>
+      <cbc:EndpointID schemeID="0218">40003032949</cbc:EndpointID>
+    </cac:Party>
+  </cac:AccountingSupplierParty>
+  <cac:LegalMonetaryTotal>
+    <cbc:PayableAmount currencyID="EUR">121.00</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+</Invoice>"#
+    )
+}
+
+/// Wait up to five seconds for job `job_id` to reach `state`
+pub async fn wait_for(queue: &Queue, job_id: &str, state: DeliveryState) {
+    for _ in 0..500 {
+        if queue.get_job(job_id).unwrap().record.state == state {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!(
+        "job {job_id} is {}, expected {state}",
+        queue.get_job(job_id).unwrap().record.state
+    );
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use common::{invoice, open, run_until_closed, wait_for, TempDir, TestAccessPoint};
+use lat_einv_core::models::DeliveryState;
+use queue::{DeadLetterFilter, FailureKind, JobQuery};
+use std::sync::atomic::Ordering;
+
+const RECEIVER: &str = "0218:90000010008";
+
+#[test]
+fn retried_jobs_are_submitted_again_after_a_restart() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    *access_point.status.lock().unwrap() = DeliveryState::Failed;
+    let mut job_id = String::new();
+    run_until_closed(async {
+        let queue = open(&dir, access_point.clone());
+        let scheduler = queue.clone();
+        tokio::spawn(async move { scheduler.run_scheduler().await });
+        let xml = invoice("INV-001", "");
+        job_id = queue
+            .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+            .await
+            .unwrap();
+        wait_for(&queue, &job_id, DeliveryState::DeadLetter).await;
+        let rec = queue.get_job(&job_id).unwrap().record;
+        assert_eq!(rec.failure, Some(FailureKind::DeliveryFailed));
+        assert_eq!(rec.transmission_id.as_deref(), Some("tx-1"));
+
+        // Closed while the retry is being submitted
+        access_point.hang.store(true, Ordering::SeqCst);
+        let rec = queue.retry_job(&job_id).unwrap();
+        assert_eq!(rec.transmission_id, None);
+        assert_eq!(rec.status_checks, 0);
+        assert_eq!(rec.next_status_check_at, None);
+        wait_for(&queue, &job_id, DeliveryState::InFlight).await;
+    });
+
+    access_point.hang.store(false, Ordering::SeqCst);
+    *access_point.status.lock().unwrap() = DeliveryState::Delivered;
+    run_until_closed(async {
+        let queue = open(&dir, access_point.clone());
+        let scheduler = queue.clone();
+        tokio::spawn(async move { scheduler.run_scheduler().await });
+        wait_for(&queue, &job_id, DeliveryState::Delivered).await;
+
+        // Not taken for sent with the transmission of the failed attempt
+        let rec = queue.get_job(&job_id).unwrap().record;
+        assert_eq!(rec.transmission_id.as_deref(), Some("tx-3"));
+        let resumed = rec
+            .history
+            .iter()
+            .rposition(|t| t.to == DeliveryState::Queued)
+            .unwrap();
+        assert_eq!(
+            rec.history[resumed].error.as_deref(),
+            Some("interrupted during submission")
+        );
+    });
+    assert_eq!(access_point.submitted.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn dead_letters_are_retried_and_purged_by_filter() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    access_point.rejecting.store(true, Ordering::SeqCst);
+    let queue = open(&dir, access_point.clone());
+    let mut ids = Vec::new();
+    for (number, receiver) in [("INV-001", RECEIVER), ("INV-002", "0218:40003245752")] {
+        let job_id = queue
+            .enqueue_send_job(
+                &invoice(number, ""),
+                "s",
+                receiver,
+                "peppol",
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        wait_for(&queue, &job_id, DeliveryState::DeadLetter).await;
+        ids.push(job_id);
+    }
+    let refused = DeadLetterFilter {
+        failure: Some(FailureKind::Refused),
+        ..Default::default()
+    };
+    let expired = DeadLetterFilter {
+        failure: Some(FailureKind::DeadlineExpired),
+        ..Default::default()
+    };
+    assert!(queue.retry_dead_letters(&expired).unwrap().is_empty());
+
+    access_point.rejecting.store(false, Ordering::SeqCst);
+    let first = DeadLetterFilter {
+        receiver: Some(RECEIVER.to_string()),
+        ..refused.clone()
+    };
+    assert_eq!(queue.retry_dead_letters(&first).unwrap(), [ids[0].clone()]);
+    wait_for(&queue, &ids[0], DeliveryState::Sent).await;
+    let rec = queue.get_job(&ids[0]).unwrap().record;
+    assert_eq!(
+        (rec.failure, rec.last_error, rec.manual_retries),
+        (None, None, 1)
+    );
+    assert!(queue.retry_job(&ids[0]).is_err());
+
+    assert_eq!(queue.purge_dead_letters(&expired).unwrap(), 0);
+    assert_eq!(queue.purge_dead_letters(&refused).unwrap(), 1);
+    assert!(queue.get_job(&ids[1]).is_err());
+    let dead_letters = JobQuery {
+        states: vec![DeliveryState::DeadLetter],
+        ..Default::default()
+    };
+    assert!(queue.list_status(&dead_letters).unwrap().jobs.is_empty());
+    assert_eq!(
+        queue.list_status(&JobQuery::default()).unwrap().jobs.len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn only_queued_jobs_are_cancelled() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::new());
+    let job_id = queue
+        .enqueue_send_job(
+            &invoice("INV-001", ""),
+            "s",
+            RECEIVER,
+            "peppol",
+            Default::default(),
+        )
+        .await
+        .unwrap();
+    wait_for(&queue, &job_id, DeliveryState::Sent).await;
+    let err = queue.cancel_job(&job_id).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("only queued jobs can be cancelled"));
+    assert!(queue.cancel_job("missing").is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_retries_and_enqueues_send_an_invoice_once() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    access_point.rejecting.store(true, Ordering::SeqCst);
+    let queue = open(&dir, access_point.clone());
+    let job_id = queue
+        .enqueue_send_job(
+            &invoice("INV-001", ""),
+            "s",
+            RECEIVER,
+            "peppol",
+            Default::default(),
+        )
+        .await
+        .unwrap();
+    wait_for(&queue, &job_id, DeliveryState::DeadLetter).await;
+    // Whichever gets through stays in flight
+    access_point.rejecting.store(false, Ordering::SeqCst);
+    access_point.hang.store(true, Ordering::SeqCst);
+
+    let retries: Vec<_> = (0..4)
+        .map(|_| {
+            let (queue, job_id) = (queue.clone(), job_id.clone());
+            tokio::task::spawn_blocking(move || queue.retry_job(&job_id).is_ok())
+        })
+        .collect();
+    let enqueues: Vec<_> = (0..4)
+        .map(|i| {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                let xml = invoice("INV-001", &format!("copy {i}"));
+                queue
+                    .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+                    .await
+                    .is_ok()
+            })
+        })
+        .collect();
+    let mut admitted = 0;
+    for task in retries.into_iter().chain(enqueues) {
+        admitted += usize::from(task.await.unwrap());
+    }
+    assert_eq!(admitted, 1);
+
+    let live: Vec<_> = queue
+        .list_status(&JobQuery::default())
+        .unwrap()
+        .jobs
+        .into_iter()
+        .filter(|rec| rec.state != DeliveryState::DeadLetter)
+        .collect();
+    assert_eq!(live.len(), 1);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::{invoice, open, open_with, wait_for, TempDir, TestAccessPoint};
+use lat_einv_core::models::DeliveryState;
+use lat_einv_core::parsing::compute_sha256_hex;
+use queue::{JobDetail, ProviderOperation, QueueOptions, RetryPolicy};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+const RECEIVER: &str = "0218:90000010008";
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn details_show_the_history_answers_and_invoice() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    access_point.unavailable.store(1, Ordering::SeqCst);
+    *access_point.status.lock().unwrap() = DeliveryState::Sent;
+    let options = QueueOptions {
+        retry: RetryPolicy::default()
+            .with_delays(Duration::from_millis(10), Duration::from_millis(10))
+            .with_jitter(0.0),
+        ..Default::default()
+    };
+    let queue = open_with(&dir, access_point.clone(), options);
+    let scheduler = queue.clone();
+    tokio::spawn(async move { scheduler.run_scheduler().await });
+
+    let xml = invoice("INV-001", "");
+    let job_id = queue
+        .enqueue_send_job(&xml, "40003032949", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+    wait_for(&queue, &job_id, DeliveryState::Sent).await;
+    // Let a few checks find it still sent
+    while access_point.status_checks.load(Ordering::SeqCst) < 3 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    *access_point.status.lock().unwrap() = DeliveryState::Delivered;
+    wait_for(&queue, &job_id, DeliveryState::Delivered).await;
+
+    let detail = queue.get_job(&job_id).unwrap();
+    assert_eq!(detail.xml, xml);
+    assert_eq!(detail.transmitted_xml.as_deref(), Some(xml.as_str()));
+    assert_eq!(
+        (
+            detail.record.sender.as_str(),
+            detail.record.receiver.as_str()
+        ),
+        ("40003032949", RECEIVER)
+    );
+    assert_eq!(detail.profile, "peppol");
+
+    let record = &detail.record;
+    assert_eq!(record.invoice_number.as_deref(), Some("INV-001"));
+    let transitions: Vec<_> = record.history.iter().map(|t| (t.from, t.to)).collect();
+    assert_eq!(
+        transitions,
+        [
+            (DeliveryState::Queued, DeliveryState::InFlight),
+            (DeliveryState::InFlight, DeliveryState::Queued),
+            (DeliveryState::Queued, DeliveryState::InFlight),
+            (DeliveryState::InFlight, DeliveryState::Sent),
+            (DeliveryState::Sent, DeliveryState::Delivered),
+        ]
+    );
+    assert!(record.history[1]
+        .error
+        .as_deref()
+        .is_some_and(|e| e.contains("503")));
+    assert!(record.history.windows(2).all(|w| w[0].at <= w[1].at));
+
+    // Repeated status answers are kept once
+    let responses: Vec<_> = record
+        .responses
+        .iter()
+        .map(|r| (r.operation, r.transmission_id.as_deref(), r.state))
+        .collect();
+    assert_eq!(
+        responses,
+        [
+            (ProviderOperation::Submit, None, None),
+            (ProviderOperation::Submit, Some("tx-2"), None),
+            (
+                ProviderOperation::Status,
+                Some("tx-2"),
+                Some(DeliveryState::Sent)
+            ),
+            (
+                ProviderOperation::Status,
+                Some("tx-2"),
+                Some(DeliveryState::Delivered)
+            ),
+        ]
+    );
+    assert!(record.responses[0].message.is_some());
+
+    // The record's fields sit next to the payload's, each once
+    let json = serde_json::to_string(&detail).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["job_id"], job_id.as_str());
+    assert_eq!(value["receiver"], RECEIVER);
+    assert_eq!(value["xml"], xml.as_str());
+    assert_eq!(json.matches("\"receiver\":").count(), 1);
+    let read: JobDetail = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        (read.record.sender, read.record.receiver),
+        ("40003032949".to_string(), RECEIVER.to_string())
+    );
+    assert_eq!(read.record.history.len(), record.history.len());
+    assert_eq!((read.profile, read.xml), (detail.profile, detail.xml));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn details_show_the_invoice_as_signed_for_sending() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    access_point.signing.store(true, Ordering::SeqCst);
+    access_point.unavailable.store(1, Ordering::SeqCst);
+    let options = QueueOptions {
+        retry: RetryPolicy::default()
+            .with_delays(Duration::from_millis(10), Duration::from_millis(10))
+            .with_jitter(0.0),
+        ..Default::default()
+    };
+    let queue = open_with(&dir, access_point.clone(), options);
+    let scheduler = queue.clone();
+    tokio::spawn(async move { scheduler.run_scheduler().await });
+
+    let xml = invoice("INV-001", "");
+    let job_id = queue
+        .enqueue_send_job(&xml, "40003032949", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+    wait_for(&queue, &job_id, DeliveryState::Sent).await;
+
+    let signed = format!("{xml}<!-- signed -->");
+    assert_eq!(*access_point.invoices.lock().unwrap(), [signed.clone(), signed.clone()]);
+    let detail = queue.get_job(&job_id).unwrap();
+    assert_eq!(detail.xml, xml);
+    assert_eq!(detail.transmitted_xml.as_deref(), Some(signed.as_str()));
+    // Each submission names what it sent, the failed one included
+    let hashes: Vec<_> = detail
+        .record
+        .responses
+        .iter()
+        .filter(|r| r.operation == ProviderOperation::Submit)
+        .map(|r| r.payload_hash.clone())
+        .collect();
+    let hash = Some(compute_sha256_hex(&signed));
+    assert_eq!(hashes, [hash.clone(), hash]);
+}
+
+#[tokio::test]
+async fn unknown_jobs_have_no_details() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::new());
+    let err = queue.get_job("missing").unwrap_err();
+    assert!(err.to_string().contains("missing"));
+}
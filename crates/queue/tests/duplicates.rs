@@ -0,0 +1,139 @@
+mod common;
+
+use common::{invoice, open, TempDir, TestAccessPoint};
+use lat_einv_core::duplicates::DuplicateKey;
+use queue::{EnqueueOptions, JobQuery};
+
+const RECEIVER: &str = "0218:90000010008";
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_enqueues_of_a_document_share_one_job() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::hanging());
+    let xml = invoice("INV-001", "");
+
+    let tasks: Vec<_> = (0..8)
+        .map(|_| {
+            let (queue, xml) = (queue.clone(), xml.clone());
+            tokio::spawn(async move {
+                queue
+                    .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+                    .await
+            })
+        })
+        .collect();
+    let mut ids = Vec::new();
+    for task in tasks {
+        ids.push(task.await.unwrap().unwrap());
+    }
+    ids.dedup();
+    assert_eq!(ids.len(), 1);
+    assert_eq!(
+        queue.list_status(&JobQuery::default()).unwrap().jobs.len(),
+        1
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_enqueues_of_an_invoice_admit_one() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::hanging());
+
+    // Same invoice, different documents
+    let tasks: Vec<_> = (0..8)
+        .map(|i| {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                let xml = invoice("INV-001", &format!("copy {i}"));
+                queue
+                    .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+                    .await
+            })
+        })
+        .collect();
+    let mut enqueued = 0;
+    for task in tasks {
+        match task.await.unwrap() {
+            Ok(_) => enqueued += 1,
+            Err(e) => assert!(e.to_string().starts_with("Duplicate of job")),
+        }
+    }
+    assert_eq!(enqueued, 1);
+    assert_eq!(
+        queue.list_status(&JobQuery::default()).unwrap().jobs.len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn duplicates_are_found_and_enqueued_only_on_request() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::hanging());
+    let first = invoice("INV-001", "");
+    let job_id = queue
+        .enqueue_send_job(&first, "s", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+
+    let key = DuplicateKey::from_xml(&first).unwrap();
+    assert_eq!(queue.find_duplicate(&key).unwrap().unwrap().job_id, job_id);
+    let other = DuplicateKey::from_xml(&invoice("INV-002", "")).unwrap();
+    assert!(queue.find_duplicate(&other).unwrap().is_none());
+
+    let corrected = invoice("INV-001", "corrected");
+    let err = queue
+        .enqueue_send_job(&corrected, "s", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains(&job_id));
+
+    let allow = EnqueueOptions {
+        allow_duplicate: true,
+    };
+    let second = queue
+        .enqueue_send_job(&corrected, "s", RECEIVER, "peppol", allow)
+        .await
+        .unwrap();
+    assert_ne!(second, job_id);
+}
+
+#[tokio::test]
+async fn cancelled_jobs_release_their_invoice() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::hanging());
+    // Only queued jobs can be cancelled; hold the first job in the queue by
+    // giving the workers another to hang on
+    let workers = queue.worker_stats();
+    for i in 0..workers.concurrency {
+        queue
+            .enqueue_send_job(
+                &invoice(&format!("BUSY-{i}"), ""),
+                "s",
+                RECEIVER,
+                "peppol",
+                Default::default(),
+            )
+            .await
+            .unwrap();
+    }
+    let xml = invoice("INV-001", "");
+    let job_id = queue
+        .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+    queue.cancel_job(&job_id).unwrap();
+
+    let key = DuplicateKey::from_xml(&xml).unwrap();
+    assert!(queue.find_duplicate(&key).unwrap().is_none());
+    let again = queue
+        .enqueue_send_job(
+            &invoice("INV-001", "again"),
+            "s",
+            RECEIVER,
+            "peppol",
+            Default::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(queue.find_duplicate(&key).unwrap().unwrap().job_id, again);
+}
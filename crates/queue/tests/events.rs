@@ -0,0 +1,19 @@
+use chrono::{TimeZone, Utc};
+use queue::{DeliveryState, JobEvent};
+
+#[test]
+fn events_serialize_tagged_by_type() {
+    let at = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+    let event = JobEvent::StateChanged {
+        job_id: "abc123".to_string(),
+        from: DeliveryState::Sent,
+        to: DeliveryState::Delivered,
+        at,
+        error: None,
+    };
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["type"], "state_changed");
+    assert_eq!(json["to"], "delivered");
+    assert_eq!(serde_json::from_value::<JobEvent>(json).unwrap(), event);
+    assert_eq!(event.job_id(), "abc123");
+}
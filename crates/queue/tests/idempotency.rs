@@ -0,0 +1,112 @@
+mod common;
+
+use common::{invoice, open, open_with, wait_for, TempDir, TestAccessPoint};
+use lat_einv_core::models::DeliveryState;
+use queue::{EnqueueOptions, JobQuery, QueueOptions, RetryPolicy};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+const RECEIVER: &str = "0218:90000010008";
+
+#[tokio::test]
+async fn the_same_document_returns_its_job() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::hanging());
+    let xml = invoice("INV-001", "");
+    let job_id = queue
+        .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+
+    // Formatting differences do not make it another document
+    let reformatted = xml.replace("\n  ", "\n    ");
+    for xml in [&xml, &reformatted] {
+        let again = queue
+            .enqueue_send_job(xml, "s", RECEIVER, "peppol", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(again, job_id);
+    }
+
+    let allow = EnqueueOptions {
+        allow_duplicate: true,
+    };
+    let forced = queue
+        .enqueue_send_job(&xml, "s", RECEIVER, "peppol", allow)
+        .await
+        .unwrap();
+    assert_ne!(forced, job_id);
+    assert_eq!(
+        queue.list_status(&JobQuery::default()).unwrap().jobs.len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn failed_documents_are_enqueued_again() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    access_point.rejecting.store(true, Ordering::SeqCst);
+    let queue = open(&dir, access_point.clone());
+    let xml = invoice("INV-001", "");
+    let failed = queue
+        .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+    wait_for(&queue, &failed, DeliveryState::DeadLetter).await;
+
+    access_point.rejecting.store(false, Ordering::SeqCst);
+    let resent = queue
+        .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+    assert_ne!(resent, failed);
+    wait_for(&queue, &resent, DeliveryState::Sent).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn attempts_share_an_idempotency_key_until_retried_by_hand() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    access_point.unavailable.store(2, Ordering::SeqCst);
+    let options = QueueOptions {
+        retry: RetryPolicy::default()
+            .with_max_attempts(3)
+            .with_delays(Duration::from_millis(10), Duration::from_millis(10))
+            .with_jitter(0.0),
+        ..Default::default()
+    };
+    let queue = open_with(&dir, access_point.clone(), options);
+    let scheduler = queue.clone();
+    tokio::spawn(async move { scheduler.run_scheduler().await });
+
+    let xml = invoice("INV-001", "");
+    let job_id = queue
+        .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+        .await
+        .unwrap();
+    wait_for(&queue, &job_id, DeliveryState::Sent).await;
+    assert_eq!(*access_point.keys.lock().unwrap(), vec![job_id.clone(); 3]);
+
+    // A manual retry must not be answered with the earlier submission
+    access_point.keys.lock().unwrap().clear();
+    access_point.rejecting.store(true, Ordering::SeqCst);
+    let other = queue
+        .enqueue_send_job(
+            &invoice("INV-002", ""),
+            "s",
+            RECEIVER,
+            "peppol",
+            Default::default(),
+        )
+        .await
+        .unwrap();
+    wait_for(&queue, &other, DeliveryState::DeadLetter).await;
+    access_point.rejecting.store(false, Ordering::SeqCst);
+    queue.retry_job(&other).unwrap();
+    wait_for(&queue, &other, DeliveryState::Sent).await;
+    assert_eq!(
+        *access_point.keys.lock().unwrap(),
+        [other.clone(), format!("{other}-1")]
+    );
+}
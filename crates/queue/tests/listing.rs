@@ -0,0 +1,178 @@
+mod common;
+
+use common::{invoice, open, wait_for, TempDir, TestAccessPoint};
+use lat_einv_core::models::DeliveryState;
+use queue::{JobQuery, JobRecord, Queue};
+use std::sync::atomic::Ordering;
+
+const RECEIVER: &str = "0218:90000010008";
+const OTHER_RECEIVER: &str = "0218:40003245752";
+
+/// Seven jobs: INV-000 and INV-001 refused and dead-lettered, the others sent,
+/// every third to the other receiver
+async fn queue_with_jobs(dir: &TempDir) -> Queue {
+    let access_point = TestAccessPoint::new();
+    let queue = open(dir, access_point.clone());
+    for i in 0..7 {
+        access_point.rejecting.store(i < 2, Ordering::SeqCst);
+        let receiver = if i % 3 == 0 { OTHER_RECEIVER } else { RECEIVER };
+        let job_id = queue
+            .enqueue_send_job(
+                &invoice(&format!("INV-{i:03}"), ""),
+                "s",
+                receiver,
+                "peppol",
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        let state = if i < 2 {
+            DeliveryState::DeadLetter
+        } else {
+            DeliveryState::Sent
+        };
+        wait_for(&queue, &job_id, state).await;
+    }
+    queue
+}
+
+/// Invoice numbers of every job matching `query`, reading `limit` at a time
+fn list_all(queue: &Queue, query: &JobQuery, limit: usize) -> Vec<String> {
+    let mut query = JobQuery {
+        limit: Some(limit),
+        ..query.clone()
+    };
+    let mut numbers = Vec::new();
+    loop {
+        let page = queue.list_status(&query).unwrap();
+        assert!(page.jobs.len() <= limit);
+        numbers.extend(page.jobs.iter().map(number));
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => return numbers,
+        }
+    }
+}
+
+fn number(rec: &JobRecord) -> String {
+    rec.invoice_number.clone().unwrap()
+}
+
+fn numbers(range: impl DoubleEndedIterator<Item = usize>) -> Vec<String> {
+    range.rev().map(|i| format!("INV-{i:03}")).collect()
+}
+
+#[tokio::test]
+async fn pages_list_every_job_once_newest_first() {
+    let dir = TempDir::new();
+    let queue = queue_with_jobs(&dir).await;
+
+    let all = queue.list_status(&JobQuery::default()).unwrap();
+    assert_eq!(
+        all.jobs.iter().map(number).collect::<Vec<_>>(),
+        numbers(0..7)
+    );
+    assert!(all
+        .jobs
+        .windows(2)
+        .all(|w| w[0].created_at >= w[1].created_at));
+    assert_eq!(all.next_cursor, None);
+
+    for limit in [1, 3, 6, 7, 8] {
+        assert_eq!(list_all(&queue, &JobQuery::default(), limit), numbers(0..7));
+    }
+    // A full last page has no cursor
+    let exact = JobQuery {
+        limit: Some(7),
+        ..Default::default()
+    };
+    assert_eq!(queue.list_status(&exact).unwrap().next_cursor, None);
+    let short = JobQuery {
+        limit: Some(6),
+        ..Default::default()
+    };
+    assert!(queue.list_status(&short).unwrap().next_cursor.is_some());
+}
+
+#[tokio::test]
+async fn filters_hold_across_pages() {
+    let dir = TempDir::new();
+    let queue = queue_with_jobs(&dir).await;
+
+    let other = JobQuery {
+        receiver: Some(OTHER_RECEIVER.to_string()),
+        ..Default::default()
+    };
+    let to_other = ["INV-006", "INV-003", "INV-000"];
+    assert_eq!(list_all(&queue, &other, 1), to_other);
+
+    let sent = JobQuery {
+        states: vec![DeliveryState::Sent],
+        ..Default::default()
+    };
+    assert_eq!(list_all(&queue, &sent, 2), numbers(2..7));
+    let failed_or_queued = JobQuery {
+        states: vec![DeliveryState::DeadLetter, DeliveryState::Queued],
+        ..Default::default()
+    };
+    assert_eq!(list_all(&queue, &failed_or_queued, 1), numbers(0..2));
+
+    let dead_to_other = JobQuery {
+        states: vec![DeliveryState::DeadLetter],
+        ..other.clone()
+    };
+    assert_eq!(list_all(&queue, &dead_to_other, 1), ["INV-000"]);
+
+    let by_number = JobQuery {
+        invoice_number: Some(" INV-004 ".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(list_all(&queue, &by_number, 1), ["INV-004"]);
+    let text = JobQuery {
+        text: Some("inv-00".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(list_all(&queue, &text, 4), numbers(0..7));
+    let text = JobQuery {
+        text: Some("40003245".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(list_all(&queue, &text, 2), to_other);
+}
+
+#[tokio::test]
+async fn date_ranges_include_their_start_only() {
+    let dir = TempDir::new();
+    let queue = queue_with_jobs(&dir).await;
+    let all = queue.list_status(&JobQuery::default()).unwrap().jobs;
+
+    // Newest first: INV-006 is all[0], INV-000 is all[6]
+    let range = JobQuery {
+        created_after: Some(all[5].created_at),
+        created_before: Some(all[1].created_at),
+        ..Default::default()
+    };
+    assert_eq!(list_all(&queue, &range, 2), numbers(1..5));
+    let sent_in_range = JobQuery {
+        states: vec![DeliveryState::Sent],
+        ..range.clone()
+    };
+    assert_eq!(list_all(&queue, &sent_in_range, 1), numbers(2..5));
+    let empty = JobQuery {
+        created_after: Some(all[1].created_at),
+        created_before: Some(all[5].created_at),
+        ..Default::default()
+    };
+    assert!(queue.list_status(&empty).unwrap().jobs.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_cursors_are_rejected() {
+    let dir = TempDir::new();
+    let queue = open(&dir, TestAccessPoint::new());
+    let query = JobQuery {
+        cursor: Some("yesterday".to_string()),
+        ..Default::default()
+    };
+    assert!(queue.list_status(&query).is_err());
+}
@@ -0,0 +1,29 @@
+use queue::PollPolicy;
+use std::time::Duration;
+
+#[test]
+fn checks_start_at_provider_interval_and_back_off() {
+    let policy = PollPolicy::default()
+        .with_multiplier(2.0)
+        .with_max_interval(Duration::from_secs(300));
+    let provider = Duration::from_secs(60);
+    assert_eq!(policy.delay(provider, 0), Duration::from_secs(60));
+    assert_eq!(policy.delay(provider, 1), Duration::from_secs(120));
+    assert_eq!(policy.delay(provider, 3), Duration::from_secs(300));
+    assert_eq!(policy.delay(provider, 1000), Duration::from_secs(300));
+}
+
+#[test]
+fn configured_interval_overrides_provider() {
+    let policy = PollPolicy::default().with_interval(Duration::from_secs(10));
+    assert_eq!(
+        policy.delay(Duration::from_secs(300), 0),
+        Duration::from_secs(10)
+    );
+    // A provider interval above the maximum is still respected
+    let slow = PollPolicy::default().with_max_interval(Duration::from_secs(60));
+    assert_eq!(
+        slow.delay(Duration::from_secs(300), 5),
+        Duration::from_secs(300)
+    );
+}
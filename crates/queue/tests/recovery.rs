@@ -0,0 +1,115 @@
+mod common;
+
+use common::{invoice, open, open_with, run_until_closed, wait_for, TempDir, TestAccessPoint};
+use lat_einv_core::models::DeliveryState;
+use queue::{QueueOptions, RetryPolicy, WorkerPolicy};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+const RECEIVER: &str = "0218:90000010008";
+
+#[test]
+fn interrupted_and_waiting_jobs_are_sent_after_a_restart() {
+    let dir = TempDir::new();
+    let mut ids = Vec::new();
+    run_until_closed(async {
+        // One worker: the first job hangs in submission, the second waits
+        let options = QueueOptions {
+            workers: WorkerPolicy::default().with_concurrency(1),
+            ..Default::default()
+        };
+        let queue = open_with(&dir, TestAccessPoint::hanging(), options);
+        for number in ["INV-001", "INV-002"] {
+            let xml = invoice(number, "");
+            let job_id = queue
+                .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+                .await
+                .unwrap();
+            ids.push(job_id);
+        }
+        wait_for(&queue, &ids[0], DeliveryState::InFlight).await;
+        assert_eq!(
+            queue.get_job(&ids[1]).unwrap().record.state,
+            DeliveryState::Queued
+        );
+    });
+
+    let access_point = TestAccessPoint::new();
+    run_until_closed(async {
+        let queue = open(&dir, access_point.clone());
+        let scheduler = queue.clone();
+        tokio::spawn(async move { scheduler.run_scheduler().await });
+        for job_id in &ids {
+            wait_for(&queue, job_id, DeliveryState::Delivered).await;
+        }
+
+        let interrupted = queue.get_job(&ids[0]).unwrap().record;
+        assert_eq!(interrupted.attempts, 2);
+        let states: Vec<_> = interrupted.history.iter().map(|t| t.to).collect();
+        assert_eq!(
+            states,
+            [
+                DeliveryState::InFlight,
+                DeliveryState::Queued,
+                DeliveryState::InFlight,
+                DeliveryState::Sent,
+                DeliveryState::Delivered
+            ]
+        );
+        assert_eq!(
+            interrupted.history[1].error.as_deref(),
+            Some("interrupted during submission")
+        );
+        assert_eq!(queue.get_job(&ids[1]).unwrap().record.attempts, 1);
+    });
+    assert_eq!(access_point.submitted.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn retries_stay_scheduled_across_a_restart() {
+    let dir = TempDir::new();
+    let options = || QueueOptions {
+        retry: RetryPolicy::default()
+            .with_delays(Duration::from_millis(500), Duration::from_millis(500))
+            .with_jitter(0.0),
+        ..Default::default()
+    };
+    let mut job_id = String::new();
+    run_until_closed(async {
+        let access_point = TestAccessPoint::new();
+        access_point.unavailable.store(1, Ordering::SeqCst);
+        let queue = open_with(&dir, access_point, options());
+        job_id = queue
+            .enqueue_send_job(
+                &invoice("INV-001", ""),
+                "s",
+                RECEIVER,
+                "peppol",
+                Default::default(),
+            )
+            .await
+            .unwrap();
+        for _ in 0..100 {
+            let rec = queue.get_job(&job_id).unwrap().record;
+            if rec.next_attempt_at.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    });
+
+    let access_point = TestAccessPoint::new();
+    run_until_closed(async {
+        let queue = open_with(&dir, access_point.clone(), options());
+        let rec = queue.get_job(&job_id).unwrap().record;
+        assert_eq!(rec.state, DeliveryState::Queued);
+        let retry_at = rec.next_attempt_at.unwrap();
+
+        let scheduler = queue.clone();
+        tokio::spawn(async move { scheduler.run_scheduler().await });
+        wait_for(&queue, &job_id, DeliveryState::Sent).await;
+        let submitted_at = queue.get_job(&job_id).unwrap().record.history[2].at;
+        assert!(submitted_at >= retry_at);
+    });
+    assert_eq!(access_point.submitted.load(Ordering::SeqCst), 1);
+}
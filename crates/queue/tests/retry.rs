@@ -0,0 +1,80 @@
+use access_point::error::{parse_retry_after, transient, HttpError, Transient};
+use queue::RetryPolicy;
+use reqwest::StatusCode;
+use std::time::Duration;
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::default()
+        .with_max_attempts(4)
+        .with_delays(Duration::from_secs(10), Duration::from_secs(60))
+        .with_multiplier(3.0)
+}
+
+fn http_error(status: StatusCode, retry_after: Option<Duration>) -> anyhow::Error {
+    anyhow::Error::new(HttpError {
+        operation: "Test submit".to_string(),
+        status,
+        retry_after,
+        body: String::new(),
+    })
+    .context("Failed to submit invoice")
+}
+
+#[test]
+fn backoff_grows_exponentially_up_to_max() {
+    let policy = policy();
+    assert_eq!(policy.backoff(1), Duration::from_secs(10));
+    assert_eq!(policy.backoff(2), Duration::from_secs(30));
+    assert_eq!(policy.backoff(3), Duration::from_secs(60));
+    assert_eq!(policy.backoff(40), Duration::from_secs(60));
+}
+
+#[test]
+fn jitter_shortens_delay_within_bounds() {
+    let policy = policy().with_jitter(0.5);
+    for _ in 0..100 {
+        let delay = policy.next_delay(2, None).unwrap();
+        assert!(delay >= Duration::from_secs(15) && delay <= Duration::from_secs(30));
+    }
+    let exact = policy.with_jitter(0.0);
+    assert_eq!(exact.next_delay(2, None), Some(Duration::from_secs(30)));
+}
+
+#[test]
+fn attempts_are_limited_and_retry_after_is_honoured() {
+    let policy = policy().with_jitter(0.0);
+    assert!(policy.next_delay(3, None).is_some());
+    assert!(policy.next_delay(4, None).is_none());
+    assert!(RetryPolicy::never().next_delay(1, None).is_none());
+
+    let after = Some(Duration::from_secs(120));
+    assert_eq!(policy.next_delay(1, after), Some(Duration::from_secs(120)));
+    let sooner = Some(Duration::from_secs(1));
+    assert_eq!(policy.next_delay(1, sooner), Some(Duration::from_secs(10)));
+}
+
+#[test]
+fn server_errors_and_throttling_are_transient() {
+    let throttled = http_error(StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_secs(7)));
+    assert_eq!(
+        transient(&throttled),
+        Some(Transient {
+            retry_after: Some(Duration::from_secs(7)),
+            throttled: true,
+        })
+    );
+    let unavailable = transient(&http_error(StatusCode::BAD_GATEWAY, None));
+    assert!(unavailable.is_some_and(|t| !t.throttled));
+    assert!(transient(&http_error(StatusCode::BAD_REQUEST, None)).is_none());
+    assert!(transient(&anyhow::anyhow!("Failed to parse UBL invoice")).is_none());
+}
+
+#[test]
+fn retry_after_accepts_seconds_and_dates() {
+    assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(parse_retry_after("soon"), None);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use access_point::RateLimit;
+use common::{invoice, open_with, wait_for, TempDir, TestAccessPoint};
+use lat_einv_core::models::DeliveryState;
+use queue::{Queue, QueueOptions, WorkerPolicy};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+const RECEIVER: &str = "0218:90000010008";
+
+async fn send_all(queue: &Queue, count: usize) -> Vec<String> {
+    let scheduler = queue.clone();
+    tokio::spawn(async move { scheduler.run_scheduler().await });
+    let mut ids = Vec::new();
+    for i in 0..count {
+        let xml = invoice(&format!("INV-{i:03}"), "");
+        let job_id = queue
+            .enqueue_send_job(&xml, "s", RECEIVER, "peppol", Default::default())
+            .await
+            .unwrap();
+        ids.push(job_id);
+    }
+    for job_id in &ids {
+        wait_for(queue, job_id, DeliveryState::Delivered).await;
+    }
+    ids
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn status_checks_share_the_worker_limit() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    *access_point.status_delay.lock().unwrap() = Duration::from_millis(100);
+    let options = QueueOptions {
+        workers: WorkerPolicy::default().with_concurrency(2),
+        ..Default::default()
+    };
+    let queue = open_with(&dir, access_point.clone(), options);
+
+    send_all(&queue, 6).await;
+    assert_eq!(access_point.status_checks.load(Ordering::SeqCst), 6);
+    assert_eq!(access_point.max_status_running.load(Ordering::SeqCst), 2);
+    // Workers free their slot just after storing the outcome
+    for _ in 0..100 {
+        if queue.worker_stats().in_flight == 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("workers still busy: {:?}", queue.worker_stats());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn status_checks_count_against_the_rate_limit() {
+    let dir = TempDir::new();
+    let options = QueueOptions {
+        workers: WorkerPolicy::default().with_rate_limit(RateLimit::new(20.0, 1)),
+        ..Default::default()
+    };
+    let queue = open_with(&dir, TestAccessPoint::new(), options);
+
+    // Three submissions and three status checks, one token each: the first
+    // from the burst, the other five 50 ms apart
+    let start = Instant::now();
+    send_all(&queue, 3).await;
+    assert!(start.elapsed() >= Duration::from_millis(240));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn checks_waiting_for_a_worker_are_not_counted() {
+    let dir = TempDir::new();
+    let access_point = TestAccessPoint::new();
+    *access_point.status.lock().unwrap() = DeliveryState::Sent;
+    let options = QueueOptions {
+        workers: WorkerPolicy::default().with_concurrency(1),
+        ..Default::default()
+    };
+    let queue = open_with(&dir, access_point.clone(), options);
+    let scheduler = queue.clone();
+    tokio::spawn(async move { scheduler.run_scheduler().await });
+    let sent = queue
+        .enqueue_send_job(
+            &invoice("INV-001", ""),
+            "s",
+            RECEIVER,
+            "peppol",
+            Default::default(),
+        )
+        .await
+        .unwrap();
+    wait_for(&queue, &sent, DeliveryState::Sent).await;
+
+    // The only worker hangs on another submission while checks fall due
+    access_point.hang.store(true, Ordering::SeqCst);
+    queue
+        .enqueue_send_job(
+            &invoice("INV-002", ""),
+            "s",
+            RECEIVER,
+            "peppol",
+            Default::default(),
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(400)).await;
+
+    let rec = queue.get_job(&sent).unwrap().record;
+    assert_eq!(
+        rec.status_checks as usize,
+        access_point.status_checks.load(Ordering::SeqCst)
+    );
+    assert!(rec.next_status_check_at.unwrap() <= chrono::Utc::now());
+}
@@ -0,0 +1,33 @@
+use access_point::RateLimit;
+use queue::{RateLimiter, WorkerPolicy};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn bursts_then_refills_at_rate() {
+    let limiter = RateLimiter::new(RateLimit::new(20.0, 3));
+    let start = Instant::now();
+    for _ in 0..3 {
+        limiter.acquire().await;
+    }
+    assert!(start.elapsed() < Duration::from_millis(20));
+
+    // Two more tokens at 20 per second
+    limiter.acquire().await;
+    limiter.acquire().await;
+    assert!(start.elapsed() >= Duration::from_millis(95));
+}
+
+#[tokio::test]
+async fn throttling_holds_tokens_for_retry_after() {
+    let limiter = RateLimiter::new(RateLimit::new(1000.0, 10));
+    limiter.throttle(Some(Duration::from_millis(150)));
+    let start = Instant::now();
+    limiter.acquire().await;
+    assert!(start.elapsed() >= Duration::from_millis(145));
+}
+
+#[test]
+fn concurrency_is_at_least_one() {
+    assert_eq!(WorkerPolicy::default().concurrency, 4);
+    assert_eq!(WorkerPolicy::default().with_concurrency(0).concurrency, 1);
+}
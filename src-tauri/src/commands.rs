@@ -1,5 +1,13 @@
+use lat_einv_core::duplicates::{find_duplicates, DuplicateKey};
+use lat_einv_core::fixer::{self, Fix, FixOptions};
+use lat_einv_core::validation::{
+    localized, render_message, ExportFormat, FileReport, Language, Severity, ValidationCache,
+    ValidationIssue, ValidationProfile, ValidationReport,
+};
+use lat_einv_core::xmldsig::SignatureVerification;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use walkdir::WalkDir;
 
 #[tauri::command]
@@ -24,6 +32,11 @@ pub struct ValidationResult {
     pub path: String,
     pub valid: bool,
     pub errors: Vec<String>,
+    pub issues: Vec<ValidationIssue>,
+    #[serde(default)]
+    pub profile: String,
+    #[serde(default)]
+    pub signatures: Vec<SignatureVerification>,
 }
 
 #[tauri::command]
@@ -50,23 +63,288 @@ pub async fn scan_folder(dir: String) -> Result<Vec<InvoiceFile>, String> {
     Ok(result)
 }
 
+/// Use the code lists from the configured directory, if any, so updated lists
+/// take effect on the next validation run
+fn install_code_lists(cfg: &config::AppConfig) {
+    use lat_einv_core::codelists::{self, CodeLists};
+
+    let lists = match cfg.validation.codelist_dir.as_deref() {
+        Some(dir) if !dir.is_empty() => {
+            CodeLists::with_overrides(std::path::Path::new(dir)).unwrap_or_else(|e| {
+                tracing::warn!(error=%e, "Failed to load code lists, using bundled lists");
+                CodeLists::bundled()
+            })
+        }
+        _ => CodeLists::bundled(),
+    };
+    codelists::install(lists);
+}
+
+fn install_trust_anchors(cfg: &config::AppConfig) {
+    use lat_einv_core::xmldsig::trust::{self, TrustStore};
+
+    let store = match cfg.validation.trust_anchor_dir.as_deref() {
+        Some(dir) if !dir.is_empty() => {
+            TrustStore::with_dir(std::path::Path::new(dir)).unwrap_or_else(|e| {
+                tracing::warn!(error=%e, "Failed to load trust anchors, using bundled anchors");
+                TrustStore::bundled()
+            })
+        }
+        _ => TrustStore::bundled(),
+    };
+    trust::install(store);
+}
+
+fn install_custom_rules(cfg: &config::AppConfig) {
+    let specs = match cfg.validation.custom_rules_path.as_deref() {
+        Some(path) if !path.is_empty() => config::load_custom_rules(std::path::Path::new(path))
+            .unwrap_or_else(|e| {
+                tracing::warn!(error=%e, "Failed to load custom rules");
+                Vec::new()
+            }),
+        _ => Vec::new(),
+    };
+    if let Err(e) = lat_einv_core::validation::install_custom_rules(specs) {
+        tracing::warn!(error=%e, "Invalid custom rules, none installed");
+        let _ = lat_einv_core::validation::install_custom_rules(Vec::new());
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationProgress {
+    pub done: usize,
+    pub total: usize,
+    pub path: String,
+    pub valid: bool,
+}
+
+/// Event emitted to the window after each file of a batch is validated
+pub const VALIDATION_PROGRESS_EVENT: &str = "validation-progress";
+
+static VALIDATION_CACHE: OnceLock<Option<ValidationCache>> = OnceLock::new();
+
+/// Local store of earlier results; validation still works if it cannot be opened
+fn validation_cache() -> Option<&'static ValidationCache> {
+    VALIDATION_CACHE
+        .get_or_init(|| {
+            ValidationCache::open(".einv_validation_cache")
+                .map_err(|e| tracing::warn!(error=%e, "Failed to open validation cache"))
+                .ok()
+        })
+        .as_ref()
+}
+
+fn validate_cached(
+    xml: &str,
+    profile: &ValidationProfile,
+    language: Language,
+    fingerprint: &str,
+) -> ValidationReport {
+    let cache = validation_cache();
+    if let Some(report) = cache.and_then(|c| c.get(fingerprint, xml)) {
+        return report;
+    }
+    let report = lat_einv_core::validation::validate_with_profile(xml, profile, language);
+    if let Some(cache) = cache {
+        if let Err(e) = cache.insert(fingerprint, xml, &report) {
+            tracing::warn!(error=%e, "Failed to store validation result");
+        }
+    }
+    report
+}
+
+/// Read and validate one file; a read error becomes the file's result
+fn validate_file(
+    path: String,
+    profile: &ValidationProfile,
+    language: Language,
+    fingerprint: &str,
+) -> (ValidationResult, Option<DuplicateKey>) {
+    let mut key = None;
+    let report = match std::fs::read_to_string(&path) {
+        Ok(xml) => {
+            key = DuplicateKey::from_xml(&xml);
+            validate_cached(&xml, profile, language, fingerprint)
+        }
+        Err(e) => ValidationReport {
+            profile: profile.name.clone(),
+            issues: vec![ValidationIssue {
+                rule_id: "IO".to_string(),
+                severity: Severity::Error,
+                message: render_message(
+                    localized(language, "IO", "Failed to read file: {0}"),
+                    &[e.to_string()],
+                ),
+                location: None,
+                args: vec![e.to_string()],
+            }],
+            signatures: Vec::new(),
+        },
+    };
+    let result = ValidationResult {
+        path,
+        valid: report.is_valid(),
+        errors: report.issues.iter().map(ToString::to_string).collect(),
+        issues: report.issues,
+        profile: report.profile,
+        signatures: report.signatures,
+    };
+    (result, key)
+}
+
+/// Other occurrences of each invoice, in this batch (by path) or in the
+/// queue (by job)
+fn duplicates_of(paths: &[String], keys: &[Option<DuplicateKey>]) -> Vec<Vec<String>> {
+    find_duplicates(keys)
+        .into_iter()
+        .zip(keys)
+        .map(|(others, key)| {
+            let mut found: Vec<String> = others.iter().map(|&j| paths[j].clone()).collect();
+            if let Some(job) = key.as_ref().and_then(|k| queue::find_duplicate(k).ok().flatten()) {
+                found.push(format!("job {} ({})", job.job_id, job.state));
+            }
+            found
+        })
+        .collect()
+}
+
 #[tauri::command]
-pub async fn validate_invoices(paths: Vec<String>) -> Result<Vec<ValidationResult>, String> {
-    let mut out = Vec::new();
-    for p in paths {
-        let xml = std::fs::read_to_string(&p).map_err(|e| e.to_string())?;
-        let res = lat_einv_core::validation::validate(&xml);
-        let (valid, errors) = match res {
-            Ok(_) => (true, Vec::new()),
-            Err(errs) => (false, errs),
+pub async fn validate_invoices(
+    window: tauri::Window,
+    paths: Vec<String>,
+) -> Result<Vec<ValidationResult>, String> {
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let cfg = config::load().unwrap_or_default();
+    install_code_lists(&cfg);
+    install_trust_anchors(&cfg);
+    install_custom_rules(&cfg);
+    let language = cfg.language;
+    let profile = cfg.validation_profile().map_err(|e| e.to_string())?;
+    // Results from other rules, code lists or profiles are no longer reachable
+    let fingerprint = lat_einv_core::validation::ruleset_fingerprint(&profile, language);
+    if let Some(cache) = validation_cache() {
+        if let Err(e) = cache.retain_fingerprint(&fingerprint) {
+            tracing::warn!(error=%e, "Failed to prune validation cache");
+        }
+    }
+
+    // Validate across all cores off the async runtime; results keep input order
+    let validated = tokio::task::spawn_blocking(move || {
+        let total = paths.len();
+        let done = AtomicUsize::new(0);
+        paths
+            .into_par_iter()
+            .map(|path| {
+                let (result, key) = validate_file(path, &profile, language, &fingerprint);
+                let progress = ValidationProgress {
+                    done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                    path: result.path.clone(),
+                    valid: result.valid,
+                };
+                if let Err(e) = window.emit(VALIDATION_PROGRESS_EVENT, progress) {
+                    tracing::debug!(error=%e, "Failed to emit validation progress");
+                }
+                (result, key)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (mut results, keys): (Vec<ValidationResult>, Vec<Option<DuplicateKey>>) =
+        validated.into_iter().unzip();
+    let paths: Vec<String> = results.iter().map(|r| r.path.clone()).collect();
+    for (result, found) in results.iter_mut().zip(duplicates_of(&paths, &keys)) {
+        if found.is_empty() {
+            continue;
+        }
+        let issue = ValidationIssue {
+            rule_id: "DUP-01".to_string(),
+            severity: Severity::Warning,
+            message: render_message(
+                localized(language, "DUP-01", "Same invoice as {0}"),
+                &[found.join(", ")],
+            ),
+            location: None,
+            args: vec![found.join(", ")],
         };
-        out.push(ValidationResult {
-            path: p,
-            valid,
-            errors,
-        });
+        result.errors.push(issue.to_string());
+        result.issues.push(issue);
     }
-    Ok(out)
+    Ok(results)
+}
+
+/// Write SVRL, JSON and HTML reports for validation results into `dir`
+#[tauri::command]
+pub async fn export_validation_report(
+    results: Vec<ValidationResult>,
+    dir: String,
+    formats: Option<Vec<ExportFormat>>,
+) -> Result<Vec<String>, String> {
+    let files: Vec<FileReport> = results
+        .into_iter()
+        .map(|r| FileReport {
+            path: r.path,
+            report: ValidationReport {
+                profile: r.profile,
+                issues: r.issues,
+                signatures: r.signatures,
+            },
+        })
+        .collect();
+    let formats = formats
+        .filter(|f| !f.is_empty())
+        .unwrap_or_else(|| ExportFormat::ALL.to_vec());
+    let language = config::load().unwrap_or_default().language;
+    let written = lat_einv_core::validation::write_reports(
+        &files,
+        std::path::Path::new(&dir),
+        &formats,
+        language,
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(written.iter().map(|p| p.display().to_string()).collect())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixPreview {
+    pub path: String,
+    pub applied: Vec<Fix>,
+    pub skipped: Vec<Fix>,
+    pub diff: String,
+    /// Fixed copy written next to the original, when applied
+    pub written: Option<String>,
+}
+
+/// Propose normalizations for one invoice; with `apply`, write the fixed copy
+#[tauri::command]
+pub async fn fix_invoice(
+    path: String,
+    apply: bool,
+    options: Option<FixOptions>,
+) -> Result<FixPreview, String> {
+    let xml = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let options = options.unwrap_or_default();
+    let outcome = fixer::fix_document(&xml, &path, &options).map_err(|e| e.to_string())?;
+
+    let mut written = None;
+    if apply && outcome.changed() {
+        let target = fixer::fixed_path(std::path::Path::new(&path));
+        std::fs::write(&target, &outcome.fixed).map_err(|e| e.to_string())?;
+        tracing::info!(path=%path, fixed=%target.display(), fixes=outcome.applied.len(), "wrote fixed invoice");
+        written = Some(target.display().to_string());
+    }
+
+    Ok(FixPreview {
+        path,
+        applied: outcome.applied,
+        skipped: outcome.skipped,
+        diff: outcome.diff,
+        written,
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,6 +353,9 @@ pub struct SendRequest {
     pub sender: String,
     pub receiver: String,
     pub profile: String,
+    /// Send invoices even if they duplicate others in the batch or queue
+    #[serde(default)]
+    pub allow_duplicates: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,12 +365,44 @@ pub struct EnqueueResponse {
 
 #[tauri::command]
 pub async fn enqueue_send(req: SendRequest) -> Result<EnqueueResponse, String> {
+    let mut documents = Vec::new();
+    for p in &req.paths {
+        let xml = std::fs::read_to_string(p).map_err(|e| e.to_string())?;
+        documents.push(xml);
+    }
+
+    // Refuse the whole batch before anything is queued
+    if !req.allow_duplicates {
+        let keys: Vec<Option<DuplicateKey>> = documents
+            .iter()
+            .map(|xml| DuplicateKey::from_xml(xml))
+            .collect();
+        let blocked: Vec<String> = req
+            .paths
+            .iter()
+            .zip(duplicates_of(&req.paths, &keys))
+            .filter(|(_, found)| !found.is_empty())
+            .map(|(p, found)| format!("{} duplicates {}", p, found.join(", ")))
+            .collect();
+        if !blocked.is_empty() {
+            return Err(format!("Duplicate invoices: {}", blocked.join("; ")));
+        }
+    }
+
+    let options = queue::EnqueueOptions {
+        allow_duplicate: req.allow_duplicates,
+    };
     let mut job_ids = Vec::new();
-    for p in req.paths {
-        let xml = std::fs::read_to_string(&p).map_err(|e| e.to_string())?;
-        let job_id = queue::enqueue_send_job(&xml, &req.sender, &req.receiver, &req.profile)
-            .await
-            .map_err(|e| e.to_string())?;
+    for (p, xml) in req.paths.into_iter().zip(documents) {
+        let job_id = queue::enqueue_send_job_with(
+            &xml,
+            &req.sender,
+            &req.receiver,
+            &req.profile,
+            options.clone(),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
         tracing::info!(%job_id, path=%p, "enqueued invoice");
         job_ids.push(job_id);
     }
@@ -99,34 +412,102 @@ pub async fn enqueue_send(req: SendRequest) -> Result<EnqueueResponse, String> {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobStatus {
     pub job_id: String,
+    pub invoice_number: Option<String>,
+    pub receiver: String,
     pub state: String,
     pub last_error: Option<String>,
     pub updated_at: String,
     pub transmission_id: Option<String>,
+    pub attempts: u32,
+    pub next_attempt_at: Option<String>,
+    pub next_status_check_at: Option<String>,
+    pub failure: Option<String>,
 }
 
-#[tauri::command]
-pub async fn list_status() -> Result<Vec<JobStatus>, String> {
-    let statuses = queue::list_status().map_err(|e| e.to_string())?;
-    Ok(statuses
-        .into_iter()
-        .map(|s| JobStatus {
+impl From<queue::JobRecord> for JobStatus {
+    fn from(s: queue::JobRecord) -> Self {
+        JobStatus {
             job_id: s.job_id,
-            state: s.state,
+            invoice_number: s.invoice_number,
+            receiver: s.receiver,
+            state: s.state.to_string(),
             last_error: s.last_error,
             updated_at: s.updated_at.to_rfc3339(),
             transmission_id: s.transmission_id,
-        })
-        .collect())
+            attempts: s.attempts,
+            next_attempt_at: s.next_attempt_at.map(|t| t.to_rfc3339()),
+            next_status_check_at: s.next_status_check_at.map(|t| t.to_rfc3339()),
+            failure: s.failure.map(|f| f.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatusPage {
+    pub jobs: Vec<JobStatus>,
+    pub next_cursor: Option<String>,
+}
+
+/// One page of the jobs matching `query`, newest first
+#[tauri::command]
+pub async fn list_status(query: Option<queue::JobQuery>) -> Result<JobStatusPage, String> {
+    let page = queue::list_status(&query.unwrap_or_default()).map_err(|e| e.to_string())?;
+    Ok(JobStatusPage {
+        jobs: page.jobs.into_iter().map(JobStatus::from).collect(),
+        next_cursor: page.next_cursor,
+    })
+}
+
+/// A job with its history, the access point's answers and the invoice sent
+#[tauri::command]
+pub async fn get_job(job_id: String) -> Result<queue::JobDetail, String> {
+    queue::get_job(&job_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn retry_job(job_id: String) -> Result<JobStatus, String> {
+    let rec = queue::retry_job(&job_id).map_err(|e| e.to_string())?;
+    Ok(rec.into())
+}
+
+/// Retry the dead letters matching `filter`; returns the retried job IDs
+#[tauri::command]
+pub async fn retry_failed(filter: queue::DeadLetterFilter) -> Result<Vec<String>, String> {
+    queue::retry_dead_letters(&filter).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_job(job_id: String) -> Result<JobStatus, String> {
+    let rec = queue::cancel_job(&job_id).map_err(|e| e.to_string())?;
+    Ok(rec.into())
+}
+
+/// Delete the dead letters matching `filter`; returns how many were deleted
+#[tauri::command]
+pub async fn purge_dead_letters(filter: queue::DeadLetterFilter) -> Result<usize, String> {
+    queue::purge_dead_letters(&filter).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn queue_stats() -> Result<queue::WorkerStats, String> {
+    queue::worker_stats().map_err(|e| e.to_string())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     pub provider_kind: Option<String>,
     pub certificate_thumbprint: Option<String>,
+    #[serde(default)]
+    pub certificate_path: Option<String>,
+    /// Stored in the keychain, never returned by `get_settings`
+    #[serde(default)]
+    pub certificate_password: Option<String>,
     pub unifiedpost_address: Option<String>,
     pub from_title: Option<String>,
     pub from_eadrese: Option<String>,
+    pub rule_pack: Option<String>,
+    pub validation_profile: Option<String>,
+    pub language: Option<Language>,
 }
 
 #[tauri::command]
@@ -135,9 +516,14 @@ pub async fn get_settings() -> Result<Settings, String> {
     Ok(Settings {
         provider_kind: Some(cfg.provider.kind),
         certificate_thumbprint: cfg.certificate.thumbprint,
+        certificate_path: cfg.certificate.pkcs12_path,
+        certificate_password: None,
         unifiedpost_address: cfg.provider.base_url,
         from_title: cfg.sender.from_title,
         from_eadrese: cfg.sender.from_eadrese,
+        rule_pack: cfg.sender.rule_pack,
+        validation_profile: Some(cfg.validation.profile),
+        language: Some(cfg.language),
     })
 }
 
@@ -149,9 +535,28 @@ pub async fn update_settings(settings: Settings) -> Result<(), String> {
         cfg.provider.kind = kind;
     }
     cfg.certificate.thumbprint = settings.certificate_thumbprint;
+    cfg.certificate.pkcs12_path = settings.certificate_path;
+    if let Some(password) = settings.certificate_password.filter(|p| !p.is_empty()) {
+        config::store_secret(config::CERTIFICATE_PASSWORD_SECRET, &password)
+            .map_err(|e| e.to_string())?;
+    }
     cfg.provider.base_url = settings.unifiedpost_address;
     cfg.sender.from_title = settings.from_title;
     cfg.sender.from_eadrese = settings.from_eadrese;
+    cfg.sender.rule_pack = match settings.rule_pack.filter(|p| !p.trim().is_empty()) {
+        Some(pack) => Some(
+            lat_einv_core::validation::rule_pack_group(&pack)
+                .map_err(|e| e.to_string())?
+                .to_string(),
+        ),
+        None => None,
+    };
+    if let Some(profile) = settings.validation_profile {
+        cfg.validation.profile = profile;
+    }
+    if let Some(language) = settings.language {
+        cfg.language = language;
+    }
 
     config::store(&cfg).map_err(|e| e.to_string())?;
     tracing::info!("Settings updated");
@@ -210,6 +615,14 @@ pub async fn test_connection() -> Result<ConnectionTestResult, String> {
                 }
             };
 
+            // A configured signing certificate must open with the stored password
+            if let Err(e) = config::load_signing_key(&cfg.certificate) {
+                return Ok(ConnectionTestResult {
+                    success: false,
+                    message: format!("Signing certificate could not be loaded: {:#}", e),
+                });
+            }
+
             // Try to create the client (validates configuration structure)
             match access_point::div_service::DivServiceClient::new(
                 base_url.clone(),
@@ -221,7 +634,7 @@ pub async fn test_connection() -> Result<ConnectionTestResult, String> {
                     // Note: Actual network connection test would require:
                     // 1. Certificate loading from file/keychain
                     // 2. TLS client certificate setup
-                    // 3. SOAP signing implementation
+                    // 3. WS-Security signing of the SOAP message
                     // For now, we only validate configuration completeness
                     
                     tracing::info!("DIV configuration validated successfully");
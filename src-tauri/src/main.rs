@@ -9,9 +9,10 @@ use access_point::{
     div_service::DivServiceClient,
     mock::MockClient,
     unifiedpost::{UnifiedpostAuth, UnifiedpostClient},
-    AccessPointClient,
+    AccessPointClient, RateLimit,
 };
 use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 fn init_tracing() {
@@ -51,11 +52,20 @@ fn create_access_point_client() -> anyhow::Result<Arc<dyn AccessPointClient>> {
                 }
             };
 
-            tracing::info!("Using DIV UnifiedService");
-            Ok(DivServiceClient::new(
+            let signing_key = match config::load_signing_key(&cfg.certificate) {
+                Ok(key) => key,
+                Err(e) => {
+                    tracing::warn!(error=%e, "Failed to load signing certificate, sending unsigned");
+                    None
+                }
+            };
+
+            tracing::info!(signed = signing_key.is_some(), "Using DIV UnifiedService");
+            Ok(DivServiceClient::new_with_signing_key(
                 base_url,
                 cert_thumbprint,
                 sender_eaddress,
+                signing_key,
             ))
         }
                 "unifiedpost" => {
@@ -115,6 +125,55 @@ fn create_access_point_client() -> anyhow::Result<Arc<dyn AccessPointClient>> {
     }
 }
 
+fn queue_options(cfg: &config::QueueConfig) -> queue::QueueOptions {
+    let retry = queue::RetryPolicy::default()
+        .with_max_attempts(cfg.max_attempts)
+        .with_delays(
+            Duration::from_secs(cfg.initial_retry_delay_secs),
+            Duration::from_secs(cfg.max_retry_delay_secs),
+        );
+    let mut poll = queue::PollPolicy::default()
+        .with_deadline(Duration::from_secs(cfg.delivery_deadline_hours * 60 * 60));
+    if let Some(secs) = cfg.status_poll_interval_secs {
+        poll = poll.with_interval(Duration::from_secs(secs));
+    }
+    let mut workers = queue::WorkerPolicy::default().with_concurrency(cfg.concurrency);
+    if let Some(per_second) = cfg.submissions_per_second {
+        let burst = cfg.submission_burst.unwrap_or(per_second.ceil() as u32);
+        workers = workers.with_rate_limit(RateLimit::new(per_second, burst));
+    }
+    queue::QueueOptions {
+        retry,
+        poll,
+        workers,
+    }
+}
+
+/// Re-emit queue events to the UI as `job-event`
+fn forward_job_events(app: tauri::AppHandle) -> anyhow::Result<()> {
+    use tauri::Manager;
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut events = queue::subscribe()?;
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Err(e) = app.emit_all("job-event", &event) {
+                        tracing::warn!(error=%e, "failed to emit job event");
+                    }
+                }
+                // The UI reloads the job table on the next event it gets
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "job events dropped");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+    Ok(())
+}
+
 fn main() {
     init_tracing();
 
@@ -123,15 +182,30 @@ fn main() {
             commands::pick_folder,
             commands::scan_folder,
             commands::validate_invoices,
+            commands::export_validation_report,
+            commands::fix_invoice,
             commands::enqueue_send,
             commands::list_status,
+            commands::get_job,
+            commands::retry_job,
+            commands::retry_failed,
+            commands::cancel_job,
+            commands::purge_dead_letters,
+            commands::queue_stats,
             commands::get_settings,
             commands::update_settings,
             commands::test_connection
         ])
-        .setup(|_app| {
+        .setup(|app| {
             let client = create_access_point_client()?;
-            queue::init(client)?;
+            let cfg = config::load().unwrap_or_default();
+            queue::init_with(client, queue_options(&cfg.queue))?;
+            forward_job_events(app.handle())?;
+            tauri::async_runtime::spawn(async {
+                if let Err(e) = queue::run_scheduler().await {
+                    tracing::error!(error=%e, "retry scheduler stopped");
+                }
+            });
             Ok(())
         })
         .run(tauri::generate_context!())
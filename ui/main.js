@@ -12,7 +12,17 @@ async function pickFolder() {
   }
 }
 
-const state = { dir: null, files: [], results: [] };
+const state = { dir: null, files: [], results: [], jobCursors: [null], jobNextCursor: null };
+
+function signatureSummary(r) {
+  return (r.signatures || [])
+    .map((s) => {
+      const signer = s.signer ? s.signer.subject : "unknown signer";
+      const status = s.signature_valid && s.references_valid ? (s.trusted ? "trusted" : "untrusted") : "invalid";
+      return `Signed by ${signer} (${status}${s.signing_time ? ", " + s.signing_time : ""})`;
+    })
+    .join("; ");
+}
 
 function renderFiles() {
   const tbody = document.getElementById("list");
@@ -20,7 +30,7 @@ function renderFiles() {
   for (const f of state.files) {
     const r = state.results.find((x) => x.path === f.path);
     const tr = document.createElement("tr");
-    tr.innerHTML = `<td>${f.path}</td><td>${f.size_bytes}</td><td>${r ? (r.valid ? '<span class="ok">OK</span>' : '<span class="err">NO</span>') : ''}</td><td>${r && r.errors ? r.errors.join("; ") : ''}</td>`;
+    tr.innerHTML = `<td>${f.path}</td><td>${f.size_bytes}</td><td>${r ? (r.valid ? '<span class="ok">OK</span>' : '<span class="err">NO</span>') : ''}</td><td>${r && r.errors ? r.errors.join("; ") : ''}${r && r.signatures && r.signatures.length ? `<br>${signatureSummary(r)}` : ''}</td>`;
     tbody.appendChild(tr);
   }
 }
@@ -31,11 +41,77 @@ function renderJobs(jobs) {
   for (const j of jobs) {
     const tr = document.createElement("tr");
     const updated = j.updated_at ? new Date(j.updated_at).toLocaleString() : "";
-    tr.innerHTML = `<td>${j.job_id}</td><td>${j.state}</td><td>${updated}</td><td>${j.transmission_id || ''}</td><td>${j.last_error || ''}</td>`;
+    const next = j.next_attempt_at ? ` (next ${new Date(j.next_attempt_at).toLocaleString()})` : "";
+    const check = j.next_status_check_at ? ` (status check ${new Date(j.next_status_check_at).toLocaleString()})` : "";
+    const failure = j.failure ? ` (${j.failure})` : "";
+    tr.innerHTML = `<td>${j.job_id}</td><td>${j.invoice_number || ''}</td><td>${j.receiver}</td><td>${j.state}${failure}${check}</td><td>${j.attempts}${next}</td><td>${updated}</td><td>${j.transmission_id || ''}</td><td>${j.last_error || ''}</td><td></td>`;
+    tr.firstElementChild.className = "job-link";
+    tr.firstElementChild.onclick = () => showJob(j.job_id);
+    const action = jobAction(j);
+    if (action) tr.lastElementChild.appendChild(action);
+    tbody.appendChild(tr);
+  }
+}
+
+function fillRows(tbody, rows) {
+  tbody.innerHTML = "";
+  for (const cells of rows) {
+    const tr = document.createElement("tr");
+    for (const value of cells) {
+      const td = document.createElement("td");
+      td.textContent = value == null ? "" : value;
+      tr.appendChild(td);
+    }
     tbody.appendChild(tr);
   }
 }
 
+// Everything stored for a job, including the invoice as it was sent
+async function showJob(jobId) {
+  let job;
+  try {
+    job = await invoke("get_job", { jobId });
+  } catch (e) {
+    return alert(`Failed to load job: ${e}`);
+  }
+  const time = (t) => new Date(t).toLocaleString();
+  document.getElementById("job-title").textContent = `Job ${job.job_id} (${job.state})`;
+  document.getElementById("job-summary").textContent =
+    `${job.sender} → ${job.receiver}, ${job.profile}; created ${time(job.created_at)}`;
+  fillRows(
+    document.getElementById("job-history"),
+    job.history.map((t) => [time(t.at), t.from, t.to, t.error])
+  );
+  fillRows(
+    document.getElementById("job-responses"),
+    job.responses.map((r) => [time(r.at), r.operation, r.transmission_id, r.state, r.message])
+  );
+  document.getElementById("job-xml").textContent = job.xml;
+  document.getElementById("job-modal").style.display = "block";
+}
+
+// Retry button for failed jobs, cancel button for queued ones
+function jobAction(j) {
+  const [label, cmd] =
+    j.state === "dead_letter" || j.state === "failed"
+      ? ["Retry", "retry_job"]
+      : j.state === "queued"
+        ? ["Cancel", "cancel_job"]
+        : [];
+  if (!cmd) return null;
+  const button = document.createElement("button");
+  button.textContent = label;
+  button.onclick = async () => {
+    try {
+      await invoke(cmd, { jobId: j.job_id });
+    } catch (e) {
+      alert(`${label} failed: ${e}`);
+    }
+    await refreshJobs();
+  };
+  return button;
+}
+
 async function invoke(cmd, args) {
   // Use the global __TAURI_INVOKE__ injected by Tauri
   if (typeof window.__TAURI_INVOKE__ === "function") {
@@ -48,10 +124,35 @@ async function invoke(cmd, args) {
   throw new Error("Tauri invoke not available");
 }
 
+// Filters from the controls above the job table; dates are local days
+function jobQuery() {
+  const value = (id) => document.getElementById(id).value;
+  const day = (d, offset) => {
+    if (!d) return null;
+    const t = new Date(`${d}T00:00:00`);
+    t.setDate(t.getDate() + offset);
+    return t.toISOString();
+  };
+  return {
+    states: value("job-state") ? [value("job-state")] : [],
+    text: value("job-search").trim() || null,
+    created_after: day(value("job-from"), 0),
+    created_before: day(value("job-to"), 1),
+    limit: 50,
+    cursor: state.jobCursors[state.jobCursors.length - 1],
+  };
+}
+
 async function refreshJobs() {
   try {
-    const jobs = await invoke("list_status");
-    renderJobs(jobs);
+    const page = await invoke("list_status", { query: jobQuery() });
+    state.jobNextCursor = page.next_cursor;
+    document.getElementById("jobs-next").disabled = !page.next_cursor;
+    document.getElementById("jobs-prev").disabled = state.jobCursors.length === 1;
+    renderJobs(page.jobs);
+    const stats = await invoke("queue_stats");
+    document.getElementById("queue-stats").textContent =
+      `${stats.in_flight} sending, ${stats.queued} waiting (${stats.concurrency} at a time)`;
   } catch (e) {
     console.error(e);
   }
@@ -80,6 +181,13 @@ async function main() {
   
   updateDebugStatus("Ready! Tauri API loaded successfully.");
 
+  if (window.__TAURI__.event && window.__TAURI__.event.listen) {
+    await window.__TAURI__.event.listen("validation-progress", (event) => {
+      const p = event.payload;
+      updateDebugStatus(`Validating… ${p.done}/${p.total}`);
+    });
+  }
+
   document.getElementById("pick").onclick = async () => {
     updateDebugStatus("Opening folder picker...");
     console.log("Pick button clicked");
@@ -107,25 +215,85 @@ async function main() {
     if (state.files.length === 0) return alert("Scan first");
     console.log("Validating", state.files.length, "files");
     const paths = state.files.map((f) => f.path);
-    state.results = await invoke("validate_invoices", { paths });
+    try {
+      state.results = await invoke("validate_invoices", { paths });
+    } catch (e) {
+      console.error(e);
+      alert(`Validation failed: ${e}`);
+    }
     renderFiles();
+    updateDebugStatus(`Validated ${state.results.length} files`);
     console.log("Validation complete");
   };
 
+  document.getElementById("export-report").onclick = async () => {
+    if (state.results.length === 0) return alert("Validate first");
+    const dir = await pickFolder();
+    if (!dir) return;
+    try {
+      const written = await invoke("export_validation_report", { results: state.results, dir });
+      alert(`Wrote ${written.length} report files to ${dir}`);
+    } catch (e) {
+      console.error(e);
+      alert(`Failed to export report: ${e}`);
+    }
+  };
+
+  document.getElementById("fix").onclick = async () => {
+    const invalid = state.results.filter((r) => !r.valid);
+    if (invalid.length === 0) return alert("Validate first; only invalid invoices are fixed");
+    const previews = [];
+    for (const r of invalid) {
+      try {
+        const preview = await invoke("fix_invoice", { path: r.path, apply: false });
+        if (preview.applied.length > 0) previews.push(preview);
+      } catch (e) {
+        console.error(r.path, e);
+      }
+    }
+    if (previews.length === 0) return alert("No automatic fixes available");
+    previews.forEach((p) => console.log(p.diff));
+    const summary = previews
+      .map((p) => `${p.path}:\n  ` + p.applied.map((f) => f.description).join("\n  "))
+      .join("\n");
+    if (!confirm(`Write fixed copies (*.fixed.xml)?\n\n${summary}`)) return;
+    let written = 0;
+    for (const p of previews) {
+      try {
+        const result = await invoke("fix_invoice", { path: p.path, apply: true });
+        if (result.written) written++;
+      } catch (e) {
+        console.error(p.path, e);
+      }
+    }
+    alert(`Wrote ${written} fixed files. Scan again to include them.`);
+  };
+
   document.getElementById("send").onclick = async () => {
     if (state.results.length === 0 || state.results.some((r) => !r.valid)) {
       return alert("Validate and ensure all invoices are valid before sending");
     }
     const paths = state.files.map((f) => f.path);
-    try {
-      const resp = await invoke("enqueue_send", {
+    const enqueue = (allow_duplicates) =>
+      invoke("enqueue_send", {
         req: {
           paths,
           sender: "LV:YOUR-SENDER-ID",
           receiver: "LV:RECEIVER-ID",
           profile: "peppol-bis-3",
+          allow_duplicates,
         },
       });
+    try {
+      let resp;
+      try {
+        resp = await enqueue(false);
+      } catch (e) {
+        if (!String(e).startsWith("Duplicate invoices") || !confirm(`${e}\n\nSend anyway?`)) {
+          throw e;
+        }
+        resp = await enqueue(true);
+      }
       if (resp && resp.job_ids) {
         alert(`Enqueued ${resp.job_ids.length} invoices`);
       }
@@ -136,8 +304,62 @@ async function main() {
     }
   };
 
+  document.getElementById("retry-failed").onclick = async () => {
+    try {
+      const retried = await invoke("retry_failed", { filter: {} });
+      alert(`Retrying ${retried.length} jobs`);
+    } catch (e) {
+      alert(`Retry failed: ${e}`);
+    }
+    await refreshJobs();
+  };
+
+  document.getElementById("purge-dead-letters").onclick = async () => {
+    if (!confirm("Delete all failed jobs and their invoices from the queue?")) return;
+    try {
+      const purged = await invoke("purge_dead_letters", { filter: {} });
+      alert(`Deleted ${purged} jobs`);
+    } catch (e) {
+      alert(`Purge failed: ${e}`);
+    }
+    await refreshJobs();
+  };
+
+  // Filtering starts over at the newest jobs
+  const resetJobs = () => {
+    state.jobCursors = [null];
+    refreshJobs();
+  };
+  for (const id of ["job-state", "job-from", "job-to"]) {
+    document.getElementById(id).onchange = resetJobs;
+  }
+  document.getElementById("job-search").oninput = resetJobs;
+  document.getElementById("jobs-next").onclick = () => {
+    if (!state.jobNextCursor) return;
+    state.jobCursors.push(state.jobNextCursor);
+    refreshJobs();
+  };
+  document.getElementById("jobs-prev").onclick = () => {
+    if (state.jobCursors.length > 1) state.jobCursors.pop();
+    refreshJobs();
+  };
+
+  document.getElementById("job-close").onclick = () => {
+    document.getElementById("job-modal").style.display = "none";
+  };
+
   await refreshJobs();
-  setInterval(refreshJobs, 2000);
+  // The job table follows queue events; without the event API it is polled
+  if (window.__TAURI__ && window.__TAURI__.event) {
+    let pending = null;
+    await window.__TAURI__.event.listen("job-event", () => {
+      // A batch of events reloads the table once
+      clearTimeout(pending);
+      pending = setTimeout(refreshJobs, 200);
+    });
+  } else {
+    setInterval(refreshJobs, 2000);
+  }
   console.log("App initialized successfully");
 
   // Settings UI
@@ -152,9 +374,14 @@ async function main() {
       const settings = await invoke("get_settings");
       document.getElementById("provider-kind").value = settings.provider_kind || "mock";
       document.getElementById("cert-thumbprint").value = settings.certificate_thumbprint || "";
+      document.getElementById("cert-path").value = settings.certificate_path || "";
+      document.getElementById("cert-password").value = "";
       document.getElementById("unifiedpost-address").value = settings.unifiedpost_address || "";
       document.getElementById("from-title").value = settings.from_title || "";
       document.getElementById("from-eadrese").value = settings.from_eadrese || "";
+      document.getElementById("rule-pack").value = settings.rule_pack || "";
+      document.getElementById("validation-profile").value = settings.validation_profile || "peppol-bis3";
+      document.getElementById("language").value = settings.language || "en";
     } catch (e) {
       console.error("Failed to load settings:", e);
       alert("Failed to load settings: " + e);
@@ -180,9 +407,14 @@ async function main() {
       const settings = {
         provider_kind: document.getElementById("provider-kind").value.trim() || null,
         certificate_thumbprint: document.getElementById("cert-thumbprint").value.trim() || null,
+        certificate_path: document.getElementById("cert-path").value.trim() || null,
+        certificate_password: document.getElementById("cert-password").value || null,
         unifiedpost_address: document.getElementById("unifiedpost-address").value.trim() || null,
         from_title: document.getElementById("from-title").value.trim() || null,
         from_eadrese: document.getElementById("from-eadrese").value.trim() || null,
+        rule_pack: document.getElementById("rule-pack").value || null,
+        validation_profile: document.getElementById("validation-profile").value || null,
+        language: document.getElementById("language").value || null,
       };
       
       // Convert empty strings to null
@@ -210,9 +442,14 @@ async function main() {
       const settings = {
         provider_kind: document.getElementById("provider-kind").value.trim() || null,
         certificate_thumbprint: document.getElementById("cert-thumbprint").value.trim() || null,
+        certificate_path: document.getElementById("cert-path").value.trim() || null,
+        certificate_password: document.getElementById("cert-password").value || null,
         unifiedpost_address: document.getElementById("unifiedpost-address").value.trim() || null,
         from_title: document.getElementById("from-title").value.trim() || null,
         from_eadrese: document.getElementById("from-eadrese").value.trim() || null,
+        rule_pack: document.getElementById("rule-pack").value || null,
+        validation_profile: document.getElementById("validation-profile").value || null,
+        language: document.getElementById("language").value || null,
       };
       
       // Convert empty strings to null
@@ -264,6 +501,10 @@ async function main() {
     if (event.target == settingsModal) {
       settingsModal.style.display = "none";
     }
+    const jobModal = document.getElementById("job-modal");
+    if (event.target == jobModal) {
+      jobModal.style.display = "none";
+    }
   };
 }
 